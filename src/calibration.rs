@@ -0,0 +1,447 @@
+// Calibrations: mapping screen coordinates into a user-defined coordinate
+// system with an affine or perspective transform fitted to reference points
+
+use serde::{Deserialize, Serialize};
+
+/// Fewest reference points of a calibration, enough for an affine transform
+pub const MIN_POINTS: usize = 3;
+/// Reference points needed for a perspective transform
+pub const PERSPECTIVE_POINTS: usize = 4;
+
+/// Most decimal places shown for calibrated coordinates
+pub const MAX_DECIMALS: u32 = 3;
+
+/// Pivots below this are treated as zero, on normalized coordinates
+const SINGULAR_EPSILON: f64 = 1e-10;
+
+/// A screen position and the coordinates it has in the target system
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReferencePoint {
+    pub screen_x: i32,
+    pub screen_y: i32,
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Kind of transform fitted to the reference points
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Model {
+    /// Affine for three points, perspective for four or more
+    #[default]
+    Auto,
+    /// Translation, rotation, scaling and shearing; keeps parallel lines parallel
+    Affine,
+    /// Additionally maps any quadrilateral onto any other, e.g. a projected
+    /// image seen at an angle
+    Perspective,
+}
+
+/// Named calibration as stored in the configuration
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Calibration {
+    pub points: Vec<ReferencePoint>,
+    pub model: Model,
+    /// Decimal places of the transformed coordinates
+    pub decimals: u32,
+}
+
+impl Calibration {
+    /// Transform through the reference points, exact for three affine or four
+    /// perspective points and a least-squares fit for more
+    pub fn transform(&self) -> Result<Transform, String> {
+        let model = match self.model {
+            Model::Auto if self.points.len() >= PERSPECTIVE_POINTS => Model::Perspective,
+            Model::Auto => Model::Affine,
+            model => model,
+        };
+        match model {
+            Model::Perspective => fit_perspective(&self.points),
+            _ => fit_affine(&self.points),
+        }
+    }
+}
+
+/// The calibration used by the calibrated display mode, fitted once
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActiveCalibration {
+    pub name: String,
+    pub transform: Transform,
+    pub decimals: u32,
+}
+
+impl ActiveCalibration {
+    pub fn new(name: &str, calibration: &Calibration) -> Result<ActiveCalibration, String> {
+        Ok(ActiveCalibration {
+            name: name.to_string(),
+            transform: calibration.transform()?,
+            decimals: calibration.decimals.min(MAX_DECIMALS),
+        })
+    }
+
+    /// Calibrated coordinates of a screen position as whole numbers with
+    /// `decimals` implied decimal places
+    pub fn apply(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (x, y) = self.transform.apply(x as f64, y as f64)?;
+        let scale = 10f64.powi(self.decimals as i32);
+        Some(((x * scale).round() as i32, (y * scale).round() as i32))
+    }
+}
+
+/// Reference points collected by the calibration wizard
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Wizard {
+    pub name: String,
+    pub points: Vec<ReferencePoint>,
+}
+
+impl Wizard {
+    pub fn new(name: &str) -> Wizard {
+        Wizard { name: name.to_string(), points: Vec::new() }
+    }
+
+    pub fn add(&mut self, screen_x: i32, screen_y: i32, (x, y): (f64, f64)) {
+        self.points.push(ReferencePoint { screen_x, screen_y, x, y });
+    }
+
+    /// Whether enough points were entered to compute a calibration
+    pub fn can_finish(&self) -> bool {
+        self.points.len() >= MIN_POINTS
+    }
+
+    /// The calibration through the entered points, checked to be computable
+    pub fn finish(&self) -> Result<(Calibration, Transform), String> {
+        let calibration = Calibration { points: self.points.clone(), ..Default::default() };
+        let transform = calibration.transform()?;
+        Ok((calibration, transform))
+    }
+}
+
+/// Projective transform as a 3x3 matrix on homogeneous coordinates; affine
+/// transforms have `[0, 0, 1]` as last row
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub matrix: [[f64; 3]; 3],
+}
+
+impl Transform {
+    /// Target coordinates of a screen position; `None` on the horizon line of
+    /// a perspective transform
+    pub fn apply(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let [a, b, c] = self.matrix;
+        let w = c[0] * x + c[1] * y + c[2];
+        if w.abs() < f64::EPSILON {
+            return None;
+        }
+        Some(((a[0] * x + a[1] * y + a[2]) / w, (b[0] * x + b[1] * y + b[2]) / w))
+    }
+
+    /// Root mean square distance between the transformed screen positions and
+    /// the target coordinates of `points`
+    pub fn rms_error(&self, points: &[ReferencePoint]) -> f64 {
+        if points.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = points
+            .iter()
+            .map(|point| match self.apply(point.screen_x as f64, point.screen_y as f64) {
+                Some((x, y)) => (x - point.x).powi(2) + (y - point.y).powi(2),
+                None => f64::INFINITY,
+            })
+            .sum();
+        (sum / points.len() as f64).sqrt()
+    }
+
+    fn multiply(&self, other: &Transform) -> Transform {
+        let mut matrix = [[0.0; 3]; 3];
+        for (row, result) in matrix.iter_mut().enumerate() {
+            for (column, value) in result.iter_mut().enumerate() {
+                *value = (0..3).map(|k| self.matrix[row][k] * other.matrix[k][column]).sum();
+            }
+        }
+        Transform { matrix }
+    }
+}
+
+/// Moves points to their centroid and scales them to an average distance of
+/// √2, which keeps the equations well conditioned for large pixel values
+#[derive(Clone, Copy, Debug)]
+struct Normalization {
+    center_x: f64,
+    center_y: f64,
+    scale: f64,
+}
+
+impl Normalization {
+    fn new(points: impl Iterator<Item = (f64, f64)> + Clone) -> Normalization {
+        let count = points.clone().count().max(1) as f64;
+        let center_x = points.clone().map(|(x, _)| x).sum::<f64>() / count;
+        let center_y = points.clone().map(|(_, y)| y).sum::<f64>() / count;
+        let distance = points.map(|(x, y)| (x - center_x).hypot(y - center_y)).sum::<f64>() / count;
+        let scale = if distance > 0.0 { std::f64::consts::SQRT_2 / distance } else { 1.0 };
+        Normalization { center_x, center_y, scale }
+    }
+
+    fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        ((x - self.center_x) * self.scale, (y - self.center_y) * self.scale)
+    }
+
+    fn matrix(&self) -> Transform {
+        let s = self.scale;
+        Transform { matrix: [[s, 0.0, -s * self.center_x], [0.0, s, -s * self.center_y], [0.0, 0.0, 1.0]] }
+    }
+
+    fn inverse_matrix(&self) -> Transform {
+        let s = 1.0 / self.scale;
+        Transform { matrix: [[s, 0.0, self.center_x], [0.0, s, self.center_y], [0.0, 0.0, 1.0]] }
+    }
+}
+
+/// Screen and target coordinates of a reference point
+type Pair = ((f64, f64), (f64, f64));
+
+/// Normalizations of the screen and target coordinates, and the normalized
+/// pairs
+fn normalize(points: &[ReferencePoint]) -> (Normalization, Normalization, Vec<Pair>) {
+    let screen = Normalization::new(points.iter().map(|point| (point.screen_x as f64, point.screen_y as f64)));
+    let target = Normalization::new(points.iter().map(|point| (point.x, point.y)));
+    let pairs = points
+        .iter()
+        .map(|point| (screen.apply(point.screen_x as f64, point.screen_y as f64), target.apply(point.x, point.y)))
+        .collect();
+    (screen, target, pairs)
+}
+
+/// Undoes the normalizations around a transform fitted to normalized pairs
+fn denormalize(screen: Normalization, target: Normalization, fitted: Transform) -> Transform {
+    let mut transform = target.inverse_matrix().multiply(&fitted).multiply(&screen.matrix());
+    let w = transform.matrix[2][2];
+    if w.abs() > f64::EPSILON {
+        for value in transform.matrix.iter_mut().flatten() {
+            *value /= w;
+        }
+    }
+    transform
+}
+
+/// Least-squares affine transform, exact for three points
+pub fn fit_affine(points: &[ReferencePoint]) -> Result<Transform, String> {
+    if points.len() < MIN_POINTS {
+        return Err(format!("An affine calibration needs at least {} reference points", MIN_POINTS));
+    }
+    let (screen, target, pairs) = normalize(points);
+    let rows_x: Vec<_> = pairs.iter().map(|&((x, y), (tx, _))| ([x, y, 1.0], tx)).collect();
+    let rows_y: Vec<_> = pairs.iter().map(|&((x, y), (_, ty))| ([x, y, 1.0], ty)).collect();
+    let (Some(a), Some(b)) = (least_squares(&rows_x), least_squares(&rows_y)) else {
+        return Err("The reference points must not lie on one line".into());
+    };
+    Ok(denormalize(screen, target, Transform { matrix: [a, b, [0.0, 0.0, 1.0]] }))
+}
+
+/// Least-squares perspective transform, exact for four points
+pub fn fit_perspective(points: &[ReferencePoint]) -> Result<Transform, String> {
+    if points.len() < PERSPECTIVE_POINTS {
+        return Err(format!("A perspective calibration needs at least {} reference points", PERSPECTIVE_POINTS));
+    }
+    let (screen, target, pairs) = normalize(points);
+    // tx = (h0 x + h1 y + h2) / (h6 x + h7 y + 1), linear after multiplying
+    // out the denominator; likewise ty with h3 to h5
+    let mut rows = Vec::with_capacity(pairs.len() * 2);
+    for &((x, y), (tx, ty)) in &pairs {
+        rows.push(([x, y, 1.0, 0.0, 0.0, 0.0, -x * tx, -y * tx], tx));
+        rows.push(([0.0, 0.0, 0.0, x, y, 1.0, -x * ty, -y * ty], ty));
+    }
+    let Some(h) = least_squares(&rows) else {
+        return Err("No three of the reference points may lie on one line".into());
+    };
+    let fitted = Transform { matrix: [[h[0], h[1], h[2]], [h[3], h[4], h[5]], [h[6], h[7], 1.0]] };
+    let transform = denormalize(screen, target, fitted);
+    // A reference point on the horizon line means the fit folded the plane
+    if points.iter().any(|point| transform.apply(point.screen_x as f64, point.screen_y as f64).is_none()) {
+        return Err("No three of the reference points may lie on one line".into());
+    }
+    Ok(transform)
+}
+
+/// Solves the normal equations of an overdetermined linear system, `None` if
+/// it has no unique solution
+fn least_squares<const N: usize>(rows: &[([f64; N], f64)]) -> Option<[f64; N]> {
+    let mut matrix = [[0.0; N]; N];
+    let mut rhs = [0.0; N];
+    for (coefficients, value) in rows {
+        for i in 0..N {
+            for j in 0..N {
+                matrix[i][j] += coefficients[i] * coefficients[j];
+            }
+            rhs[i] += coefficients[i] * value;
+        }
+    }
+    solve(matrix, rhs)
+}
+
+/// Gaussian elimination with partial pivoting
+fn solve<const N: usize>(mut matrix: [[f64; N]; N], mut rhs: [f64; N]) -> Option<[f64; N]> {
+    for column in 0..N {
+        let pivot = (column..N).max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))?;
+        if matrix[pivot][column].abs() < SINGULAR_EPSILON {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        rhs.swap(column, pivot);
+        let pivot_row = matrix[column];
+        for row in column + 1..N {
+            let factor = matrix[row][column] / pivot_row[column];
+            for (value, pivot_value) in matrix[row][column..].iter_mut().zip(&pivot_row[column..]) {
+                *value -= factor * pivot_value;
+            }
+            rhs[row] -= factor * rhs[column];
+        }
+    }
+    let mut solution = [0.0; N];
+    for row in (0..N).rev() {
+        let sum: f64 = (row + 1..N).map(|k| matrix[row][k] * solution[k]).sum();
+        solution[row] = (rhs[row] - sum) / matrix[row][row];
+    }
+    Some(solution)
+}
+
+/// Target coordinates entered in the calibration wizard, e.g. `12.5, -3` or
+/// `12.5 -3`
+pub fn parse_target(text: &str) -> Option<(f64, f64)> {
+    let mut parts = text.split(|c: char| c == ',' || c == ';' || c.is_whitespace()).filter(|part| !part.is_empty());
+    let x: f64 = parts.next()?.parse().ok()?;
+    let y: f64 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || !x.is_finite() || !y.is_finite() {
+        return None;
+    }
+    Some((x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: [(i32, i32); 6] = [(100, 100), (1800, 120), (1750, 1000), (150, 950), (960, 540), (400, 700)];
+
+    /// Reference points at `screens` with the target coordinates of `transform`
+    fn points(transform: &Transform, screens: &[(i32, i32)]) -> Vec<ReferencePoint> {
+        screens
+            .iter()
+            .map(|&(screen_x, screen_y)| {
+                let (x, y) = transform.apply(screen_x as f64, screen_y as f64).unwrap();
+                ReferencePoint { screen_x, screen_y, x, y }
+            })
+            .collect()
+    }
+
+    fn assert_matrix_eq(actual: &Transform, expected: &Transform, tolerance: f64) {
+        for (actual_row, expected_row) in actual.matrix.iter().zip(&expected.matrix) {
+            for (actual, expected) in actual_row.iter().zip(expected_row) {
+                assert!((actual - expected).abs() <= tolerance, "{} != {}", actual, expected);
+            }
+        }
+    }
+
+    /// Scaling by 0.5 and 2, rotation by 30° and translation by (-40, 25)
+    fn affine() -> Transform {
+        let (sin, cos) = 30f64.to_radians().sin_cos();
+        Transform { matrix: [[0.5 * cos, -2.0 * sin, -40.0], [0.5 * sin, 2.0 * cos, 25.0], [0.0, 0.0, 1.0]] }
+    }
+
+    fn perspective() -> Transform {
+        Transform { matrix: [[1.2, 0.1, 15.0], [-0.05, 0.9, 30.0], [0.0002, -0.0001, 1.0]] }
+    }
+
+    #[test]
+    fn affine_fits_recover_translation_rotation_and_scaling() {
+        let expected = affine();
+        let transform = fit_affine(&points(&expected, &SCREEN[..3])).unwrap();
+        assert_matrix_eq(&transform, &expected, 1e-9);
+
+        let translation = Transform { matrix: [[1.0, 0.0, -1920.0], [0.0, 1.0, 1080.0], [0.0, 0.0, 1.0]] };
+        let transform = fit_affine(&points(&translation, &SCREEN)).unwrap();
+        assert_matrix_eq(&transform, &translation, 1e-9);
+    }
+
+    #[test]
+    fn perspective_fits_recover_the_matrix() {
+        let expected = perspective();
+        let transform = fit_perspective(&points(&expected, &SCREEN[..4])).unwrap();
+        assert_matrix_eq(&transform, &expected, 1e-9);
+        let transform = fit_perspective(&points(&expected, &SCREEN)).unwrap();
+        assert_matrix_eq(&transform, &expected, 1e-9);
+        // Affine transforms are perspective transforms as well
+        let transform = fit_perspective(&points(&affine(), &SCREEN)).unwrap();
+        assert_matrix_eq(&transform, &affine(), 1e-9);
+    }
+
+    #[test]
+    fn least_squares_fits_bound_the_error_of_jittered_points() {
+        const JITTER: [(f64, f64); 6] = [(0.3, -0.2), (-0.25, 0.1), (0.1, 0.3), (-0.3, -0.15), (0.2, 0.05), (-0.05, -0.3)];
+        for (expected, fit) in [(affine(), fit_affine as fn(&[ReferencePoint]) -> _), (perspective(), fit_perspective)] {
+            let mut points = points(&expected, &SCREEN);
+            for (point, (dx, dy)) in points.iter_mut().zip(JITTER) {
+                point.x += dx;
+                point.y += dy;
+            }
+            let transform = fit(&points).unwrap();
+            let error = transform.rms_error(&points);
+            assert!(error > 0.0 && error < 0.35, "{}", error);
+            // Close to the transform without jitter everywhere on the screen
+            let (x, y) = transform.apply(960.0, 540.0).unwrap();
+            let (expected_x, expected_y) = expected.apply(960.0, 540.0).unwrap();
+            assert!((x - expected_x).hypot(y - expected_y) < 0.5);
+        }
+    }
+
+    #[test]
+    fn exact_fits_have_no_error() {
+        let points = points(&perspective(), &SCREEN[..4]);
+        assert!(fit_perspective(&points).unwrap().rms_error(&points) < 1e-9);
+        assert_eq!(fit_perspective(&points).unwrap().rms_error(&[]), 0.0);
+    }
+
+    #[test]
+    fn too_few_points_are_rejected() {
+        let points = points(&affine(), &SCREEN);
+        assert_eq!(fit_affine(&points[..2]), Err("An affine calibration needs at least 3 reference points".into()));
+        assert_eq!(fit_affine(&[]), Err("An affine calibration needs at least 3 reference points".into()));
+        assert_eq!(
+            fit_perspective(&points[..3]),
+            Err("A perspective calibration needs at least 4 reference points".into())
+        );
+    }
+
+    #[test]
+    fn collinear_points_are_rejected() {
+        let line = [(0, 0), (100, 50), (200, 100), (300, 150)];
+        assert_eq!(fit_affine(&points(&affine(), &line)), Err("The reference points must not lie on one line".into()));
+        assert_eq!(
+            fit_perspective(&points(&perspective(), &line)),
+            Err("No three of the reference points may lie on one line".into())
+        );
+        // Three of four on one line
+        let corner = [(0, 0), (100, 0), (200, 0), (0, 100)];
+        assert!(fit_perspective(&points(&perspective(), &corner)).is_err());
+    }
+
+    #[test]
+    fn auto_model_depends_on_the_number_of_points() {
+        let calibration = Calibration { points: points(&perspective(), &SCREEN[..4]), ..Default::default() };
+        assert_matrix_eq(&calibration.transform().unwrap(), &perspective(), 1e-9);
+        let calibration = Calibration { points: points(&affine(), &SCREEN[..3]), ..Default::default() };
+        assert_matrix_eq(&calibration.transform().unwrap(), &affine(), 1e-9);
+    }
+
+    #[test]
+    fn targets_parse_with_commas_or_spaces() {
+        assert_eq!(parse_target("12.5, -3"), Some((12.5, -3.0)));
+        assert_eq!(parse_target(" 12.5 -3 "), Some((12.5, -3.0)));
+        assert_eq!(parse_target("1;2"), Some((1.0, 2.0)));
+        assert_eq!(parse_target("1"), None);
+        assert_eq!(parse_target("1, 2, 3"), None);
+        assert_eq!(parse_target("inf, 2"), None);
+    }
+}
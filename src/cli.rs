@@ -0,0 +1,264 @@
+// Command line argument parsing
+
+use std::path::PathBuf;
+
+use crate::config::validate_override_key;
+
+/// Usage summary shown for invalid arguments
+pub const USAGE: &str = "Usage: mpr [--config <path>] [--set <key>=<value>]... [config show [--origin] | config check | palette import|export <file> | points export <file> | freeze [on|off]]";
+
+/// Parsed command line arguments
+#[derive(Debug, Default)]
+pub struct Args {
+    /// Explicit configuration file given with `--config <path>`
+    pub config: Option<PathBuf>,
+    /// Configuration overrides given with `--set <key>=<value>`
+    pub overrides: Vec<(String, String)>,
+    /// Command to run instead of starting the tray icon
+    pub command: Option<Command>,
+}
+
+/// Commands that print information and exit
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// `config show [--origin]`: print the effective configuration
+    ConfigShow { origin: bool },
+    /// `config check`: report settings that make the icon hard to use
+    ConfigCheck,
+    /// `palette import <file>`: replace the custom colors with a palette file
+    PaletteImport(PathBuf),
+    /// `palette export <file>`: write the custom colors to a palette file
+    PaletteExport(PathBuf),
+    /// `points export <file>`: write the captured points as CSV, JSON or Markdown
+    PointsExport(PathBuf),
+    /// `freeze [on|off]`: freeze or unfreeze the running instance, toggles without argument
+    Freeze(Option<bool>),
+}
+
+/// Parse command line arguments (without the program name)
+pub fn parse_args<I>(args: I) -> Result<Args, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            let value = args.next().ok_or("--config requires a path")?;
+            parsed.config = Some(PathBuf::from(value));
+        } else if let Some(value) = arg.strip_prefix("--config=") {
+            if value.is_empty() {
+                return Err("--config requires a path".into());
+            }
+            parsed.config = Some(PathBuf::from(value));
+        } else if arg == "--set" {
+            let value = args.next().ok_or("--set requires <key>=<value>")?;
+            parsed.overrides.push(parse_override(&value)?);
+        } else if let Some(value) = arg.strip_prefix("--set=") {
+            parsed.overrides.push(parse_override(value)?);
+        } else if arg == "config" && parsed.command.is_none() {
+            parsed.command = Some(parse_config_command(&mut args)?);
+        } else if arg == "palette" && parsed.command.is_none() {
+            parsed.command = Some(parse_palette_command(&mut args)?);
+        } else if arg == "points" && parsed.command.is_none() {
+            parsed.command = Some(parse_points_command(&mut args)?);
+        } else if arg == "freeze" && parsed.command.is_none() {
+            parsed.command = Some(parse_freeze_command(&mut args)?);
+        } else {
+            return Err(format!("Unknown argument: {}", arg));
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Parse the words following `config`
+fn parse_config_command<I>(args: &mut I) -> Result<Command, String>
+where
+    I: Iterator<Item = String>,
+{
+    match args.next().as_deref() {
+        Some("show") => {
+            let mut origin = false;
+            for arg in args.by_ref() {
+                match arg.as_str() {
+                    "--origin" => origin = true,
+                    _ => return Err(format!("Unknown argument for config show: {}", arg)),
+                }
+            }
+            Ok(Command::ConfigShow { origin })
+        }
+        Some("check") => match args.next() {
+            Some(arg) => Err(format!("Unknown argument for config check: {}", arg)),
+            None => Ok(Command::ConfigCheck),
+        },
+        Some(other) => Err(format!("Unknown config command: {}", other)),
+        None => Err("config requires a command: show, check".into()),
+    }
+}
+
+/// Parse the words following `palette`
+fn parse_palette_command<I>(args: &mut I) -> Result<Command, String>
+where
+    I: Iterator<Item = String>,
+{
+    let action = args.next().ok_or("palette requires a command: import, export")?;
+    let path = args.next().map(PathBuf::from).ok_or_else(|| format!("palette {} requires a file", action))?;
+    if let Some(arg) = args.next() {
+        return Err(format!("Unknown argument for palette {}: {}", action, arg));
+    }
+    match action.as_str() {
+        "import" => Ok(Command::PaletteImport(path)),
+        "export" => Ok(Command::PaletteExport(path)),
+        other => Err(format!("Unknown palette command: {}", other)),
+    }
+}
+
+/// Parse the words following `points`
+fn parse_points_command<I>(args: &mut I) -> Result<Command, String>
+where
+    I: Iterator<Item = String>,
+{
+    match args.next().as_deref() {
+        Some("export") => {
+            let path = args.next().map(PathBuf::from).ok_or("points export requires a file")?;
+            match args.next() {
+                Some(arg) => Err(format!("Unknown argument for points export: {}", arg)),
+                None => Ok(Command::PointsExport(path)),
+            }
+        }
+        Some(other) => Err(format!("Unknown points command: {}", other)),
+        None => Err("points requires a command: export".into()),
+    }
+}
+
+/// Parse the words following `freeze`
+fn parse_freeze_command<I>(args: &mut I) -> Result<Command, String>
+where
+    I: Iterator<Item = String>,
+{
+    let state = match args.next().as_deref() {
+        Some("on") => Some(true),
+        Some("off") => Some(false),
+        Some(other) => return Err(format!("Unknown argument for freeze: {}", other)),
+        None => None,
+    };
+    match args.next() {
+        Some(arg) => Err(format!("Unknown argument for freeze: {}", arg)),
+        None => Ok(Command::Freeze(state)),
+    }
+}
+
+/// Split `key=value` and check that the key names a known setting
+fn parse_override(value: &str) -> Result<(String, String), String> {
+    let (key, value) = value
+        .split_once('=')
+        .ok_or_else(|| format!("Expected <key>=<value>, got: {}", value))?;
+    let key = key.trim();
+    validate_override_key(key)?;
+    Ok((key.to_string(), value.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_start_the_tray_icon() {
+        let args = parse(&[]).unwrap();
+        assert_eq!(args.config, None);
+        assert!(args.overrides.is_empty());
+        assert_eq!(args.command, None);
+    }
+
+    #[test]
+    fn config_paths_are_taken_in_both_forms() {
+        assert_eq!(parse(&["--config", "a.toml"]).unwrap().config, Some(PathBuf::from("a.toml")));
+        assert_eq!(parse(&["--config=b.toml"]).unwrap().config, Some(PathBuf::from("b.toml")));
+        // The last one wins
+        assert_eq!(parse(&["--config=b.toml", "--config", "c.toml"]).unwrap().config, Some(PathBuf::from("c.toml")));
+    }
+
+    #[test]
+    fn config_paths_must_not_be_missing() {
+        assert_eq!(parse(&["--config"]).unwrap_err(), "--config requires a path");
+        assert_eq!(parse(&["--config="]).unwrap_err(), "--config requires a path");
+        assert_eq!(parse(&["--verbose"]).unwrap_err(), "Unknown argument: --verbose");
+    }
+
+    #[test]
+    fn overrides_are_taken_in_both_forms_and_trimmed() {
+        let args = parse(&["--set", "text_color = 0x00FF00", "--set=background_color=1"]).unwrap();
+        assert_eq!(
+            args.overrides,
+            [("text_color".to_string(), "0x00FF00".to_string()), ("background_color".to_string(), "1".to_string())]
+        );
+        // Only the first `=` separates the value
+        assert_eq!(parse(&["--set=text_color=a=b"]).unwrap().overrides[0].1, "a=b");
+    }
+
+    #[test]
+    fn overrides_need_a_known_key_and_a_value() {
+        assert_eq!(parse(&["--set"]).unwrap_err(), "--set requires <key>=<value>");
+        assert_eq!(parse(&["--set", "text_color"]).unwrap_err(), "Expected <key>=<value>, got: text_color");
+        assert_eq!(parse(&["--set=text_colour=1"]).unwrap_err(), "Unknown setting: text_colour");
+    }
+
+    #[test]
+    fn config_show_takes_an_origin_flag() {
+        assert_eq!(parse(&["config", "show"]).unwrap().command, Some(Command::ConfigShow { origin: false }));
+        let args = parse(&["--config", "a.toml", "config", "show", "--origin"]).unwrap();
+        assert_eq!(args.command, Some(Command::ConfigShow { origin: true }));
+        assert_eq!(args.config, Some(PathBuf::from("a.toml")));
+    }
+
+    #[test]
+    fn config_commands_reject_extra_arguments() {
+        assert_eq!(parse(&["config", "edit"]).unwrap_err(), "Unknown config command: edit");
+        assert_eq!(parse(&["config", "show", "--all"]).unwrap_err(), "Unknown argument for config show: --all");
+        // Only one command
+        assert_eq!(parse(&["config", "show", "config"]).unwrap_err(), "Unknown argument for config show: config");
+    }
+
+    #[test]
+    fn config_check_takes_no_arguments() {
+        assert_eq!(parse(&["config", "check"]).unwrap().command, Some(Command::ConfigCheck));
+        assert_eq!(parse(&["config", "check", "--origin"]).unwrap_err(), "Unknown argument for config check: --origin");
+        assert_eq!(parse(&["config"]).unwrap_err(), "config requires a command: show, check");
+    }
+
+    #[test]
+    fn palette_commands_take_one_file() {
+        assert_eq!(parse(&["palette", "import", "a.gpl"]).unwrap().command, Some(Command::PaletteImport(PathBuf::from("a.gpl"))));
+        assert_eq!(parse(&["palette", "export", "b.json"]).unwrap().command, Some(Command::PaletteExport(PathBuf::from("b.json"))));
+        assert_eq!(parse(&["palette"]).unwrap_err(), "palette requires a command: import, export");
+        assert_eq!(parse(&["palette", "import"]).unwrap_err(), "palette import requires a file");
+        assert_eq!(parse(&["palette", "merge", "a.gpl"]).unwrap_err(), "Unknown palette command: merge");
+        assert_eq!(parse(&["palette", "export", "a.gpl", "b.gpl"]).unwrap_err(), "Unknown argument for palette export: b.gpl");
+        assert_eq!(parse(&["config", "check", "palette"]).unwrap_err(), "Unknown argument for config check: palette");
+    }
+
+    #[test]
+    fn freeze_takes_an_optional_state() {
+        assert_eq!(parse(&["freeze"]).unwrap().command, Some(Command::Freeze(None)));
+        assert_eq!(parse(&["freeze", "on"]).unwrap().command, Some(Command::Freeze(Some(true))));
+        assert_eq!(parse(&["freeze", "off"]).unwrap().command, Some(Command::Freeze(Some(false))));
+        assert_eq!(parse(&["freeze", "toggle"]).unwrap_err(), "Unknown argument for freeze: toggle");
+        assert_eq!(parse(&["freeze", "on", "off"]).unwrap_err(), "Unknown argument for freeze: off");
+        assert_eq!(parse(&["freeze", "on", "freeze"]).unwrap_err(), "Unknown argument for freeze: freeze");
+    }
+
+    #[test]
+    fn points_export_takes_one_file() {
+        assert_eq!(parse(&["points", "export", "p.csv"]).unwrap().command, Some(Command::PointsExport(PathBuf::from("p.csv"))));
+        assert_eq!(parse(&["points"]).unwrap_err(), "points requires a command: export");
+        assert_eq!(parse(&["points", "export"]).unwrap_err(), "points export requires a file");
+        assert_eq!(parse(&["points", "import", "p.csv"]).unwrap_err(), "Unknown points command: import");
+        assert_eq!(parse(&["points", "export", "p.csv", "q.csv"]).unwrap_err(), "Unknown argument for points export: q.csv");
+    }
+}
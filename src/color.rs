@@ -0,0 +1,214 @@
+// Color helpers for COLORREF values stored as u32 (0x00BBGGRR)
+
+/// Contrast ratio below which text is considered hard to read (WCAG AA)
+pub const MIN_CONTRAST_RATIO: f64 = 4.5;
+
+/// Okabe-Ito palette, distinguishable with all common forms of color blindness
+pub const COLORBLIND_SAFE: [u32; 9] = [
+    rgb(0x00, 0x00, 0x00), // black
+    rgb(0xE6, 0x9F, 0x00), // orange
+    rgb(0x56, 0xB4, 0xE9), // sky blue
+    rgb(0x00, 0x9E, 0x73), // bluish green
+    rgb(0xF0, 0xE4, 0x42), // yellow
+    rgb(0x00, 0x72, 0xB2), // blue
+    rgb(0xD5, 0x5E, 0x00), // vermillion
+    rgb(0xCC, 0x79, 0xA7), // reddish purple
+    rgb(0xFF, 0xFF, 0xFF), // white
+];
+
+/// Builds a COLORREF value from its red, green and blue components
+pub const fn rgb(r: u8, g: u8, b: u8) -> u32 {
+    r as u32 | (g as u32) << 8 | (b as u32) << 16
+}
+
+/// Splits a COLORREF value into its red, green and blue components
+pub const fn components(color: u32) -> (u8, u8, u8) {
+    (color as u8, (color >> 8) as u8, (color >> 16) as u8)
+}
+
+/// Formats a COLORREF value as `#RRGGBB`
+pub fn to_hex(color: u32) -> String {
+    let (r, g, b) = components(color);
+    format!("#{:02X}{:02X}{:02X}", r, g, b)
+}
+
+/// Hue in degrees (0-359), saturation and lightness in percent (0-100) of a
+/// COLORREF value; grays have hue and saturation 0
+pub fn to_hsl(color: u32) -> (u32, u32, u32) {
+    let (r, g, b) = components(color);
+    let (r, g, b) = (r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let chroma = max - min;
+    if chroma == 0.0 {
+        return (0, 0, (lightness * 100.0).round() as u32);
+    }
+    let saturation = chroma / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    } * 60.0;
+    ((hue.round() as u32) % 360, (saturation * 100.0).round() as u32, (lightness * 100.0).round() as u32)
+}
+
+/// Parses `#RRGGBB` (the `#` is optional) into a COLORREF value
+pub fn from_hex(text: &str) -> Option<u32> {
+    let hex = text.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+    Some(rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Relative luminance as defined by WCAG 2.x, from 0.0 (black) to 1.0 (white)
+pub fn relative_luminance(color: u32) -> f64 {
+    let linear = |channel: u8| {
+        let c = channel as f64 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = components(color);
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// WCAG contrast ratio between two colors, from 1.0 (identical) to 21.0
+pub fn contrast_ratio(a: u32, b: u32) -> f64 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    let (lighter, darker) = if la > lb { (la, lb) } else { (lb, la) };
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+/// Result of checking a text color against its background
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContrastCheck {
+    pub ratio: f64,
+    /// Colorblind-safe text color to use instead, if the contrast is too low
+    pub suggestion: Option<u32>,
+}
+
+impl ContrastCheck {
+    pub fn is_sufficient(&self) -> bool {
+        self.suggestion.is_none()
+    }
+}
+
+/// Checks the contrast of `text` on `background` against `MIN_CONTRAST_RATIO`
+pub fn check_contrast(text: u32, background: u32) -> ContrastCheck {
+    let ratio = contrast_ratio(text, background);
+    let suggestion = (ratio < MIN_CONTRAST_RATIO).then(|| suggest_text_color(text, background));
+    ContrastCheck { ratio, suggestion }
+}
+
+/// Picks the colorblind-safe color closest to `text` that is readable on
+/// `background`, or the one with the highest contrast if none is
+pub fn suggest_text_color(text: u32, background: u32) -> u32 {
+    let readable = COLORBLIND_SAFE
+        .into_iter()
+        .filter(|&candidate| contrast_ratio(candidate, background) >= MIN_CONTRAST_RATIO)
+        .min_by_key(|&candidate| distance_squared(candidate, text));
+
+    readable.unwrap_or_else(|| {
+        COLORBLIND_SAFE
+            .into_iter()
+            .max_by(|&a, &b| contrast_ratio(a, background).total_cmp(&contrast_ratio(b, background)))
+            .unwrap_or(rgb(0xFF, 0xFF, 0xFF))
+    })
+}
+
+fn distance_squared(a: u32, b: u32) -> u32 {
+    let (ar, ag, ab) = components(a);
+    let (br, bg, bb) = components(b);
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(ar, br) + d(ag, bg) + d(ab, bb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: u32 = rgb(0x00, 0x00, 0x00);
+    const WHITE: u32 = rgb(0xFF, 0xFF, 0xFF);
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 0.01, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn contrast_ratios_match_wcag() {
+        assert_near(contrast_ratio(BLACK, WHITE), 21.0);
+        assert_near(contrast_ratio(WHITE, BLACK), 21.0);
+        assert_near(contrast_ratio(WHITE, WHITE), 1.0);
+        assert_near(contrast_ratio(rgb(0x12, 0x34, 0x56), rgb(0x12, 0x34, 0x56)), 1.0);
+        // Examples from the WCAG understanding documents
+        assert_near(contrast_ratio(rgb(0x76, 0x76, 0x76), WHITE), 4.54);
+        assert_near(contrast_ratio(rgb(0x77, 0x77, 0x77), WHITE), 4.48);
+    }
+
+    #[test]
+    fn the_aa_threshold_separates_sufficient_contrast() {
+        let check = check_contrast(rgb(0x76, 0x76, 0x76), WHITE);
+        assert!(check.is_sufficient());
+        assert_eq!(check.suggestion, None);
+
+        let check = check_contrast(rgb(0x77, 0x77, 0x77), WHITE);
+        assert!(!check.is_sufficient());
+        assert_near(check.ratio, 4.48);
+    }
+
+    #[test]
+    fn suggestions_are_readable_colorblind_safe_colors() {
+        // Light blue on white: the closest readable Okabe-Ito color is blue
+        let text = rgb(0x56, 0xB4, 0xE9);
+        let suggestion = check_contrast(text, WHITE).suggestion.unwrap();
+        assert_eq!(suggestion, rgb(0x00, 0x72, 0xB2));
+        assert!(contrast_ratio(suggestion, WHITE) >= MIN_CONTRAST_RATIO);
+
+        // Dark gray on black: yellow is closer than white
+        let suggestion = suggest_text_color(rgb(0x33, 0x33, 0x33), BLACK);
+        assert!(COLORBLIND_SAFE.contains(&suggestion));
+        assert!(contrast_ratio(suggestion, BLACK) >= MIN_CONTRAST_RATIO);
+    }
+
+    #[test]
+    fn every_gray_background_gets_a_readable_suggestion() {
+        // Black or white always reaches 4.5:1, so a readable color exists
+        for level in 0..=255 {
+            let background = rgb(level, level, level);
+            let suggestion = suggest_text_color(background, background);
+            assert!(contrast_ratio(suggestion, background) >= MIN_CONTRAST_RATIO, "{}", to_hex(background));
+        }
+    }
+
+    #[test]
+    fn hex_round_trips() {
+        let orange = rgb(0xE6, 0x9F, 0x00);
+        assert_eq!(to_hex(orange), "#E69F00");
+        assert_eq!(from_hex("#E69F00"), Some(orange));
+        assert_eq!(from_hex(" e69f00 "), Some(orange));
+        assert_eq!(from_hex("#E69F0"), None);
+        assert_eq!(from_hex("#E69F0G"), None);
+        assert_eq!(from_hex("#E69F00FF"), None);
+    }
+
+    #[test]
+    fn hsl_values_match_css() {
+        assert_eq!(to_hsl(BLACK), (0, 0, 0));
+        assert_eq!(to_hsl(WHITE), (0, 0, 100));
+        assert_eq!(to_hsl(rgb(0x80, 0x80, 0x80)), (0, 0, 50));
+        assert_eq!(to_hsl(rgb(0xFF, 0x00, 0x00)), (0, 100, 50));
+        assert_eq!(to_hsl(rgb(0x00, 0xFF, 0x00)), (120, 100, 50));
+        assert_eq!(to_hsl(rgb(0x00, 0x00, 0xFF)), (240, 100, 50));
+        // Hues just below 360 wrap around instead of showing 360
+        assert_eq!(to_hsl(rgb(0xFF, 0x00, 0x01)), (0, 100, 50));
+        assert_eq!(to_hsl(rgb(0xFF, 0x00, 0x80)), (330, 100, 50));
+        assert_eq!(to_hsl(rgb(0xE6, 0x9F, 0x00)), (41, 100, 45));
+    }
+}
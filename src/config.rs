@@ -0,0 +1,1124 @@
+// Configuration handling: file locations, layering, loading and saving
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+use crate::calibration::{self, Calibration};
+use crate::color::{check_contrast, to_hex};
+use crate::copy::{resolve_copy_format, DEFAULT_COPY_FORMAT};
+use crate::hotkey::{self, Action};
+use crate::i18n::Language;
+use crate::measure::{self, DEFAULT_MEASURE_FORMAT};
+use crate::palette::Palette;
+use crate::rectangle::{resolve_rectangle_format, DEFAULT_RECTANGLE_FORMAT};
+use crate::reference::Reference;
+use crate::theme::{self, SystemAppearance, Theme, ThemeColors};
+use crate::tooltip;
+
+/// Environment variable that overrides the configuration file path
+pub const CONFIG_ENV_VAR: &str = "MPR_CONFIG";
+
+/// Prefix of environment variables overriding single configuration values
+const ENV_PREFIX: &str = "MPR_";
+
+/// File name of the portable configuration next to the executable
+const PORTABLE_CONFIG_FILE: &str = "mpr.toml";
+
+/// Key in the system-wide file listing values users may not override
+const LOCKED_KEY: &str = "locked";
+
+/// Name of the profile made up of the top-level settings
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Lower bound for the update interval, keeps the timer from hogging the CPU
+pub const MIN_INTERVAL_MS: u32 = 15;
+
+/// What the tray icon shows
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplayMode {
+    /// Virtual-desktop coordinates as returned by the system
+    #[default]
+    Absolute,
+    /// Signed offset from the configured origin
+    Relative,
+    /// Offset from the top left corner of the monitor under the cursor
+    Monitor,
+    /// Virtual-desktop coordinates divided by the scaling of the monitor under
+    /// the cursor, as seen by programs that are not DPI aware
+    Logical,
+    /// Offset from the client area of the top-level window under the cursor
+    Client,
+    /// Offset from the frame of the top-level window under the cursor
+    Window,
+    /// Position in the coordinate system of the active calibration
+    Calibrated,
+    /// Position scaled from the monitor or a window to the reference resolution
+    Reference,
+    /// Swatch of the pixel color under the cursor instead of coordinates
+    Color,
+}
+
+/// Reference point of the relative display mode, in virtual-desktop coordinates
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Origin {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Unit of the coordinates shown in the tray icon
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    /// Physical screen pixels
+    #[default]
+    Pixels,
+    Millimeters,
+    /// Shown with one decimal place
+    Inches,
+    /// Typographic points, 1/72 inch
+    Points,
+}
+
+/// Manually set physical size of a monitor, for displays without or with a
+/// wrong size in their EDID
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonitorSize {
+    pub width_mm: u32,
+    pub height_mm: u32,
+}
+
+/// How the numbers in the tray icon are padded
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NumberFormat {
+    /// Always four digits: `0042`
+    #[default]
+    ZeroPadded,
+    /// Leading zeros left blank: `  42`
+    SpacePadded,
+}
+
+/// Appearance and behavior settings that can be switched as a whole
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub theme: Theme,
+    pub text_color: u32, // COLORREF as u32, used by the custom theme
+    pub background_color: u32, // COLORREF as u32, used by the custom theme
+    pub display_mode: DisplayMode,
+    pub number_format: NumberFormat,
+    pub unit: Unit,
+    pub interval_ms: u32,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile {
+            theme: Theme::Custom,
+            text_color: 0x00E6D8AD, // Default color from original code
+            background_color: 0x00000000, // Default transparent background
+            display_mode: DisplayMode::Absolute,
+            number_format: NumberFormat::ZeroPadded,
+            unit: Unit::Pixels,
+            interval_ms: 100,
+        }
+    }
+}
+
+/// Configuration structure
+///
+/// The top-level settings form the `default` profile; further profiles live
+/// in `[profiles.<name>]` tables.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    #[serde(flatten)]
+    pub base: Profile,
+    pub active_profile: String,
+    /// Language of the user interface
+    pub language: Language,
+    /// Template of the tray tooltip, empty for the built-in one
+    pub tooltip: String,
+    /// Built-in format name or template of copied positions
+    pub copy_format: String,
+    /// Template of copied measurements
+    pub measure_format: String,
+    /// Built-in format name or template of copied rectangles
+    pub rectangle_format: String,
+    /// Reference point of the relative display mode
+    pub origin: Origin,
+    /// Name of the calibration used by the calibrated display mode
+    pub calibration: String,
+    /// Reference points of the calibrations by name
+    pub calibrations: BTreeMap<String, Calibration>,
+    /// Resolution and scaling of the reference display mode
+    pub reference: Reference,
+    /// Physical size per monitor number or name, overriding the EDID
+    pub monitor_sizes: BTreeMap<String, MonitorSize>,
+    /// Global hotkey per action name, e.g. `copy_position = "Ctrl+Alt+C"`
+    pub hotkeys: BTreeMap<String, String>,
+    pub profiles: BTreeMap<String, Profile>,
+    /// Custom colors of the color pickers
+    pub palette: Palette,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            base: Profile::default(),
+            active_profile: DEFAULT_PROFILE.to_string(),
+            language: Language::default(),
+            tooltip: String::new(),
+            copy_format: DEFAULT_COPY_FORMAT.to_string(),
+            measure_format: DEFAULT_MEASURE_FORMAT.to_string(),
+            rectangle_format: DEFAULT_RECTANGLE_FORMAT.to_string(),
+            origin: Origin::default(),
+            calibration: String::new(),
+            calibrations: BTreeMap::new(),
+            reference: Reference::default(),
+            monitor_sizes: BTreeMap::new(),
+            hotkeys: BTreeMap::from([
+                (Action::CopyPosition.name().to_string(), "Ctrl+Alt+C".to_string()),
+                (Action::Freeze.name().to_string(), "Ctrl+Alt+F".to_string()),
+                (Action::CapturePoint.name().to_string(), "Ctrl+Alt+P".to_string()),
+                (Action::SetOrigin.name().to_string(), "Ctrl+Alt+O".to_string()),
+                (Action::Measure.name().to_string(), "Ctrl+Alt+D".to_string()),
+                (Action::MeasureRectangle.name().to_string(), "Ctrl+Alt+R".to_string()),
+            ]),
+            profiles: BTreeMap::new(),
+            palette: Palette::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Currently active profile; unknown names fall back to the default profile
+    pub fn profile(&self) -> &Profile {
+        self.profiles.get(&self.active_profile).unwrap_or(&self.base)
+    }
+
+    pub fn profile_mut(&mut self) -> &mut Profile {
+        match self.profiles.get_mut(&self.active_profile) {
+            Some(profile) => profile,
+            None => &mut self.base,
+        }
+    }
+
+    /// Names of all profiles, the default profile first
+    pub fn profile_names(&self) -> Vec<&str> {
+        let mut names = vec![DEFAULT_PROFILE];
+        names.extend(self.profiles.keys().map(String::as_str).filter(|name| *name != DEFAULT_PROFILE));
+        names
+    }
+
+    /// Switch to the named profile, returns `false` for unknown names
+    pub fn set_active_profile(&mut self, name: &str) -> bool {
+        if name != DEFAULT_PROFILE && !self.profiles.contains_key(name) {
+            return false;
+        }
+        self.active_profile = name.to_string();
+        true
+    }
+
+    /// Problems that make the configuration hard to use, one message each
+    pub fn check(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for name in self.profile_names() {
+            let profile = self.profiles.get(name).unwrap_or(&self.base);
+            problems.extend(check_profile_contrast(name, profile));
+        }
+        if !self.tooltip.is_empty() {
+            if let Err(message) = tooltip::parse_template(&self.tooltip) {
+                problems.push(format!("tooltip: {}", message));
+            }
+        }
+        if let Err(message) = resolve_copy_format(&self.copy_format) {
+            problems.push(format!("copy_format: {}", message));
+        }
+        if let Err(message) = measure::parse_template(&self.measure_format) {
+            problems.push(format!("measure_format: {}", message));
+        }
+        if let Err(message) = resolve_rectangle_format(&self.rectangle_format) {
+            problems.push(format!("rectangle_format: {}", message));
+        }
+        for (name, calibration) in &self.calibrations {
+            if let Err(message) = calibration.transform() {
+                problems.push(format!("calibrations.{}: {}", name, message));
+            }
+            if calibration.decimals > calibration::MAX_DECIMALS {
+                problems.push(format!("calibrations.{}: decimals must be at most {}", name, calibration::MAX_DECIMALS));
+            }
+        }
+        if !self.calibration.is_empty() && !self.calibrations.contains_key(&self.calibration) {
+            problems.push(format!("calibration: no calibration named \"{}\"", self.calibration));
+        }
+        if self.reference.width == 0 || self.reference.height == 0 {
+            problems.push("reference: width and height must both be set".to_string());
+        }
+        for (monitor, size) in &self.monitor_sizes {
+            if size.width_mm == 0 || size.height_mm == 0 {
+                problems.push(format!("monitor_sizes.{}: width_mm and height_mm must both be set", monitor));
+            }
+        }
+        problems.extend(hotkey::parse_bindings(&self.hotkeys).1);
+        problems
+    }
+
+    /// Dotted configuration key of `field` in the active profile
+    pub fn profile_key(&self, field: &str) -> String {
+        if self.profiles.contains_key(&self.active_profile) {
+            format!("profiles.{}.{}", self.active_profile, field)
+        } else {
+            field.to_string()
+        }
+    }
+}
+
+/// Contrast warnings for a profile, for every system appearance its theme can
+/// resolve to
+fn check_profile_contrast(name: &str, profile: &Profile) -> Vec<String> {
+    let custom = ThemeColors { text: profile.text_color, background: profile.background_color };
+    let appearances: &[(SystemAppearance, &str)] = if profile.theme.follows_system() {
+        &[
+            (SystemAppearance { apps_light: false, taskbar_light: false }, " (dark mode)"),
+            (SystemAppearance { apps_light: true, taskbar_light: true }, " (light mode)"),
+        ]
+    } else {
+        &[(SystemAppearance { apps_light: false, taskbar_light: false }, "")]
+    };
+
+    let mut problems = Vec::new();
+    for (appearance, suffix) in appearances {
+        let colors = theme::resolve(profile.theme, custom, *appearance);
+        let check = check_contrast(colors.text, colors.background);
+        if let Some(suggestion) = check.suggestion {
+            problems.push(format!(
+                "Profile \"{}\"{}: contrast {:.1}:1 between text {} and background {} is too low, try text color {}",
+                name,
+                suffix,
+                check.ratio,
+                to_hex(colors.text),
+                to_hex(colors.background),
+                to_hex(suggestion),
+            ));
+        }
+    }
+    problems
+}
+
+/// Source of a configuration value, from lowest to highest precedence
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Layer {
+    Default,
+    System,
+    User,
+    Environment,
+    CommandLine,
+}
+
+impl Layer {
+    pub fn name(self) -> &'static str {
+        match self {
+            Layer::Default => "default",
+            Layer::System => "system",
+            Layer::User => "user",
+            Layer::Environment => "environment",
+            Layer::CommandLine => "command line",
+        }
+    }
+}
+
+/// Effective configuration merged from all layers.
+///
+/// Keeps track of where each value came from and of the user layer on its
+/// own, so that saving never writes system, environment or command line
+/// values into the user file.
+#[derive(Debug)]
+pub struct LayeredConfig {
+    /// Effective configuration as last loaded or saved
+    effective: Config,
+    /// Layer of each value that does not come from the defaults, by dotted key
+    origins: BTreeMap<String, Layer>,
+    /// Keys locked by the system-wide file
+    locked: BTreeSet<String>,
+    /// Contents of the user file
+    user: Table,
+    user_path: Option<PathBuf>,
+}
+
+impl LayeredConfig {
+    /// Load all layers: defaults, system file, user file, `MPR_*` environment
+    /// variables and `--set key=value` overrides, in that order.
+    ///
+    /// A layer that would produce an invalid configuration is reported and
+    /// skipped as a whole.
+    pub fn load(user_path: Option<PathBuf>, overrides: &[(String, String)]) -> LayeredConfig {
+        let system = system_config_path().and_then(|path| read_table(&path));
+        let environment = environment_table(|name| env::var(name).ok());
+        LayeredConfig::from_layers(system, user_path, environment, overrides)
+    }
+
+    /// Merge the layers as `load` does, with the system file and environment
+    /// already read
+    fn from_layers(
+        system: Option<Table>,
+        user_path: Option<PathBuf>,
+        environment: Table,
+        overrides: &[(String, String)],
+    ) -> LayeredConfig {
+        let mut merged = to_table(&Config::default());
+        let mut origins = BTreeMap::new();
+        let mut locked = BTreeSet::new();
+
+        if let Some(mut system) = system {
+            if let Some(Value::Array(keys)) = system.remove(LOCKED_KEY) {
+                locked.extend(keys.iter().filter_map(|key| key.as_str().map(str::to_string)));
+            }
+            apply_layer(&mut merged, &mut origins, &BTreeSet::new(), system, Layer::System);
+        }
+        add_implied_locks(&mut locked);
+
+        let user = match &user_path {
+            Some(path) => read_table(path).unwrap_or_default(),
+            None => Table::new(),
+        };
+        apply_layer(&mut merged, &mut origins, &locked, user.clone(), Layer::User);
+        apply_layer(&mut merged, &mut origins, &locked, environment, Layer::Environment);
+
+        let mut command_line = Table::new();
+        for (key, raw) in overrides {
+            set_path(&mut command_line, key, parse_value(raw));
+        }
+        apply_layer(&mut merged, &mut origins, &locked, command_line, Layer::CommandLine);
+        lock_profile_fields(&mut merged, &locked);
+
+        let effective = from_table(merged).unwrap_or_default();
+        LayeredConfig { effective, origins, locked, user, user_path }
+    }
+
+    /// Effective configuration
+    pub fn config(&self) -> &Config {
+        &self.effective
+    }
+
+    /// Whether the system-wide file locks `key` (or one of its parents); a
+    /// setting in a profile is locked with the top-level setting of that name
+    pub fn is_locked(&self, key: &str) -> bool {
+        is_locked(&self.locked, key)
+    }
+
+    /// Persist the values of `config` that changed since the last load or
+    /// save to the user file. Locked values are not written.
+    pub fn save(&mut self, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+        let mut changes = Vec::new();
+        diff_tables(&to_table(&self.effective), &to_table(config), "", &mut changes);
+
+        for (key, value) in changes {
+            if self.is_locked(&key) {
+                eprintln!("Not saving locked setting {}", key);
+                continue;
+            }
+            match value {
+                Some(value) => {
+                    set_path(&mut self.user, &key, value);
+                    self.origins.insert(key, Layer::User);
+                }
+                None => {
+                    remove_path(&mut self.user, &key);
+                    self.origins.remove(&key);
+                }
+            }
+        }
+        self.effective = config.clone();
+
+        let path = self.user_path.as_ref().ok_or("No configuration location")?;
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        fs::write(path, toml::to_string_pretty(&self.user)?)?;
+        Ok(())
+    }
+
+    /// Layer that provided the effective value of `key`
+    pub fn origin(&self, key: &str) -> Layer {
+        // A value set as a whole table counts for all keys below it
+        let mut prefix = key;
+        loop {
+            if let Some(layer) = self.origins.get(prefix) {
+                return *layer;
+            }
+            match prefix.rfind('.') {
+                Some(index) => prefix = &prefix[..index],
+                None => return Layer::Default,
+            }
+        }
+    }
+
+    /// Render the effective configuration as `key = value` lines, optionally
+    /// annotated with the layer each value came from
+    pub fn describe(&self, with_origin: bool) -> String {
+        let mut values = Vec::new();
+        flatten(&to_table(&self.effective), "", &mut values);
+
+        let mut out = String::new();
+        for (key, value) in values {
+            let _ = write!(out, "{} = {}", key, value);
+            if with_origin {
+                let _ = write!(out, "  # {}", self.origin(&key).name());
+                if self.is_locked(&key) {
+                    out.push_str(", locked");
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Merge `layer` into `merged`, recording the origin of every value it sets
+fn apply_layer(
+    merged: &mut Table,
+    origins: &mut BTreeMap<String, Layer>,
+    locked: &BTreeSet<String>,
+    layer: Table,
+    source: Layer,
+) {
+    let mut candidate = merged.clone();
+    let mut candidate_origins = origins.clone();
+    merge_into(&mut candidate, layer, "", locked, source, &mut candidate_origins);
+
+    match from_table(candidate.clone()) {
+        Ok(_) => {
+            *merged = candidate;
+            *origins = candidate_origins;
+        }
+        Err(e) => eprintln!("Ignoring {} configuration: {}", source.name(), e),
+    }
+}
+
+fn merge_into(
+    target: &mut Table,
+    layer: Table,
+    prefix: &str,
+    locked: &BTreeSet<String>,
+    source: Layer,
+    origins: &mut BTreeMap<String, Layer>,
+) {
+    for (key, value) in layer {
+        let path = join_path(prefix, &key);
+        if is_locked(locked, &path) {
+            eprintln!("Ignoring {} value for locked setting {}", source.name(), path);
+            continue;
+        }
+        match (target.get_mut(&key), value) {
+            (Some(Value::Table(existing)), Value::Table(nested)) => {
+                merge_into(existing, nested, &path, locked, source, origins);
+            }
+            (_, mut value) => {
+                // A new table, such as a profile, may not bring locked values along
+                if let Value::Table(nested) = &mut value {
+                    remove_locked(nested, &path, locked, source);
+                }
+                // Values below a replaced key no longer come from lower layers
+                let nested = format!("{}.", path);
+                origins.retain(|key, _| !key.starts_with(&nested));
+                origins.insert(path, source);
+                target.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Collect changed values between two tables as dotted keys; `None` marks a
+/// removed value
+fn diff_tables(old: &Table, new: &Table, prefix: &str, changes: &mut Vec<(String, Option<Value>)>) {
+    for (key, value) in new {
+        let path = join_path(prefix, key);
+        match (old.get(key), value) {
+            (Some(Value::Table(old_nested)), Value::Table(new_nested)) => {
+                diff_tables(old_nested, new_nested, &path, changes);
+            }
+            (Some(old_value), value) if old_value == value => {}
+            (_, value) => changes.push((path, Some(value.clone()))),
+        }
+    }
+    for key in old.keys().filter(|key| !new.contains_key(*key)) {
+        changes.push((join_path(prefix, key), None));
+    }
+}
+
+/// Flatten a table into `(dotted key, value)` pairs for display
+fn flatten(table: &Table, prefix: &str, out: &mut Vec<(String, Value)>) {
+    for (key, value) in table {
+        let path = join_path(prefix, key);
+        match value {
+            Value::Table(nested) => flatten(nested, &path, out),
+            value => out.push((path, value.clone())),
+        }
+    }
+}
+
+fn is_locked(locked: &BTreeSet<String>, key: &str) -> bool {
+    let matches = |key: &str| locked.iter().any(|lock| key == lock || key.starts_with(&format!("{}.", lock)));
+    matches(key) || profile_field(key).is_some_and(matches)
+}
+
+/// Locks the theme along with a locked color: any theme but the custom one
+/// would replace the locked colors
+fn add_implied_locks(locked: &mut BTreeSet<String>) {
+    if is_locked(locked, "text_color") || is_locked(locked, "background_color") {
+        locked.insert("theme".to_string());
+    }
+}
+
+/// Key of a setting in a profile without the profile, `text_color` for
+/// `profiles.work.text_color`
+fn profile_field(key: &str) -> Option<&str> {
+    let (_, field) = key.strip_prefix("profiles.")?.split_once('.')?;
+    Some(field)
+}
+
+/// Removes the locked values from a table that is about to be added at `prefix`
+fn remove_locked(table: &mut Table, prefix: &str, locked: &BTreeSet<String>, source: Layer) {
+    table.retain(|key, value| {
+        let path = join_path(prefix, key);
+        if is_locked(locked, &path) {
+            eprintln!("Ignoring {} value for locked setting {}", source.name(), path);
+            return false;
+        }
+        if let Value::Table(nested) = value {
+            remove_locked(nested, &path, locked, source);
+        }
+        true
+    });
+}
+
+/// Gives every profile the top-level value of each locked profile setting, so
+/// that switching profiles cannot bypass a lock
+fn lock_profile_fields(merged: &mut Table, locked: &BTreeSet<String>) {
+    let fields: Vec<(String, Value)> = to_profile_table(&Profile::default())
+        .keys()
+        .filter(|field| is_locked(locked, field))
+        .filter_map(|field| Some((field.clone(), merged.get(field)?.clone())))
+        .collect();
+    let Some(Value::Table(profiles)) = merged.get_mut("profiles") else { return };
+    for (_, profile) in profiles.iter_mut() {
+        if let Value::Table(profile) = profile {
+            for (field, value) in &fields {
+                profile.insert(field.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn join_path(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// Set a dotted key in a table, creating intermediate tables as needed
+fn set_path(table: &mut Table, key: &str, value: Value) {
+    match key.split_once('.') {
+        Some((head, rest)) => {
+            let entry = table
+                .entry(head.to_string())
+                .or_insert_with(|| Value::Table(Table::new()));
+            if !entry.is_table() {
+                *entry = Value::Table(Table::new());
+            }
+            if let Value::Table(nested) = entry {
+                set_path(nested, rest, value);
+            }
+        }
+        None => {
+            table.insert(key.to_string(), value);
+        }
+    }
+}
+
+fn remove_path(table: &mut Table, key: &str) {
+    match key.split_once('.') {
+        Some((head, rest)) => {
+            if let Some(Value::Table(nested)) = table.get_mut(head) {
+                remove_path(nested, rest);
+            }
+        }
+        None => {
+            table.remove(key);
+        }
+    }
+}
+
+/// Parse an override value as TOML (`0x00FF00`, `true`, `"text"`), falling
+/// back to a plain string
+fn parse_value(raw: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+/// Values from `MPR_<KEY>` environment variables for all top-level settings,
+/// read with `var`
+fn environment_table(var: impl Fn(&str) -> Option<String>) -> Table {
+    let mut table = Table::new();
+    for (key, value) in to_table(&Config::default()) {
+        if value.is_table() {
+            continue;
+        }
+        let name = format!("{}{}", ENV_PREFIX, key.to_uppercase());
+        if let Some(raw) = var(&name) {
+            table.insert(key, parse_value(&raw));
+        }
+    }
+    table
+}
+
+/// Check that every `--set` key names a known setting
+pub fn validate_override_key(key: &str) -> Result<(), String> {
+    let top = key.split('.').next().unwrap_or_default();
+    if to_table(&Config::default()).contains_key(top) {
+        Ok(())
+    } else {
+        Err(format!("Unknown setting: {}", key))
+    }
+}
+
+fn to_table(config: &Config) -> Table {
+    match Value::try_from(config) {
+        Ok(Value::Table(table)) => table,
+        _ => Table::new(),
+    }
+}
+
+fn to_profile_table(profile: &Profile) -> Table {
+    match Value::try_from(profile) {
+        Ok(Value::Table(table)) => table,
+        _ => Table::new(),
+    }
+}
+
+fn from_table(table: Table) -> Result<Config, toml::de::Error> {
+    Value::Table(table).try_into()
+}
+
+/// Read a TOML file into a table; missing files are silently skipped
+fn read_table(path: &Path) -> Option<Table> {
+    let content = fs::read_to_string(path).ok()?;
+    match toml::from_str::<Table>(&content) {
+        Ok(table) => Some(table),
+        Err(e) => {
+            eprintln!("Failed to parse config file {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Resolve the configuration file path.
+///
+/// Lookup order: `--config <path>`, the `MPR_CONFIG` environment variable,
+/// a portable `mpr.toml` next to the executable and finally the platform
+/// default (`%APPDATA%\mpr` on Windows, `$XDG_CONFIG_HOME/mpr` elsewhere).
+/// Returns `None` if no location can be determined.
+pub fn resolve_config_path(cli_override: Option<&Path>) -> Option<PathBuf> {
+    let locations = ConfigLocations {
+        environment: non_empty_env_path(CONFIG_ENV_VAR),
+        portable: portable_config_path(),
+        default_dir: default_config_dir(),
+        legacy: legacy_config_path(),
+    };
+    locations.resolve(cli_override)
+}
+
+/// Candidate configuration locations, looked up from the environment
+#[derive(Debug, Default)]
+struct ConfigLocations {
+    /// Value of `MPR_CONFIG`
+    environment: Option<PathBuf>,
+    /// `mpr.toml` next to the executable, used if it exists
+    portable: Option<PathBuf>,
+    default_dir: Option<PathBuf>,
+    /// Configuration file of earlier versions, moved to the default location
+    legacy: Option<PathBuf>,
+}
+
+impl ConfigLocations {
+    fn resolve(self, cli_override: Option<&Path>) -> Option<PathBuf> {
+        if let Some(path) = cli_override {
+            return Some(path.to_path_buf());
+        }
+        if let Some(path) = self.environment {
+            return Some(path);
+        }
+        if let Some(path) = self.portable.filter(|path| path.is_file()) {
+            return Some(path);
+        }
+
+        let path = self.default_dir?.join("config.toml");
+        if let Some(legacy) = self.legacy {
+            migrate_legacy_config(&legacy, &path);
+        }
+        Some(path)
+    }
+}
+
+/// Machine-wide configuration file managed by administrators
+/// (`%ProgramData%\mpr` on Windows, `$XDG_CONFIG_DIRS/mpr` elsewhere)
+fn system_config_path() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        non_empty_env_path("ProgramData")?
+    } else {
+        env::var("XDG_CONFIG_DIRS")
+            .ok()
+            .and_then(|dirs| dirs.split(':').find(|dir| !dir.is_empty()).map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from("/etc/xdg"))
+    };
+    Some(base.join("mpr").join("config.toml"))
+}
+
+/// Path of the portable configuration file next to the running executable
+fn portable_config_path() -> Option<PathBuf> {
+    let exe = env::current_exe().ok()?;
+    Some(exe.parent()?.join(PORTABLE_CONFIG_FILE))
+}
+
+/// Platform-standard configuration directory for mpr
+fn default_config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        non_empty_env_path("APPDATA")?
+    } else {
+        non_empty_env_path("XDG_CONFIG_HOME")
+            .or_else(|| non_empty_env_path("HOME").map(|home| home.join(".config")))?
+    };
+    Some(base.join("mpr"))
+}
+
+/// Location used by earlier versions: `~/.mpr/config.toml`
+fn legacy_config_path() -> Option<PathBuf> {
+    let home = if cfg!(windows) {
+        non_empty_env_path("USERPROFILE")?
+    } else {
+        non_empty_env_path("HOME")?
+    };
+    Some(home.join(".mpr").join("config.toml"))
+}
+
+fn non_empty_env_path(name: &str) -> Option<PathBuf> {
+    env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Move a configuration from `legacy_path` to `new_path`, once.
+///
+/// Nothing happens if `new_path` already exists or there is no legacy file.
+fn migrate_legacy_config(legacy_path: &Path, new_path: &Path) {
+    if new_path.exists() || !legacy_path.is_file() {
+        return;
+    }
+
+    if let Some(parent) = new_path.parent() {
+        if let Err(e) = fs::create_dir_all(parent) {
+            eprintln!("Failed to create config directory: {}", e);
+            return;
+        }
+    }
+
+    // Copy first so a failure never loses the old configuration
+    if let Err(e) = fs::copy(legacy_path, new_path) {
+        eprintln!("Failed to migrate config from {}: {}", legacy_path.display(), e);
+        return;
+    }
+    let _ = fs::remove_file(legacy_path);
+    if let Some(legacy_dir) = legacy_path.parent() {
+        // Only succeeds if the directory is empty now
+        let _ = fs::remove_dir(legacy_dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locks(keys: &[&str]) -> BTreeSet<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    /// Empty directory below the system temp dir, removed when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let path = env::temp_dir().join(format!("mpr-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn locations(dir: &Path) -> ConfigLocations {
+        ConfigLocations {
+            environment: None,
+            portable: Some(dir.join("bin").join(PORTABLE_CONFIG_FILE)),
+            default_dir: Some(dir.join("appdata").join("mpr")),
+            legacy: Some(dir.join("home").join(".mpr").join("config.toml")),
+        }
+    }
+
+    #[test]
+    fn config_paths_prefer_the_command_line_then_the_environment() {
+        let dir = TempDir::new("paths");
+        fs::create_dir_all(dir.0.join("bin")).unwrap();
+        fs::write(dir.0.join("bin").join(PORTABLE_CONFIG_FILE), "").unwrap();
+        let cli = dir.0.join("cli.toml");
+        let environment = dir.0.join("env.toml");
+
+        let with_environment = || ConfigLocations { environment: Some(environment.clone()), ..locations(&dir.0) };
+        assert_eq!(with_environment().resolve(Some(&cli)), Some(cli.clone()));
+        assert_eq!(with_environment().resolve(None), Some(environment.clone()));
+        assert_eq!(locations(&dir.0).resolve(None), Some(dir.0.join("bin").join(PORTABLE_CONFIG_FILE)));
+    }
+
+    #[test]
+    fn config_paths_use_the_portable_file_only_if_it_exists() {
+        let dir = TempDir::new("portable");
+        assert_eq!(locations(&dir.0).resolve(None), Some(dir.0.join("appdata").join("mpr").join("config.toml")));
+        let no_default = ConfigLocations { default_dir: None, ..locations(&dir.0) };
+        assert_eq!(no_default.resolve(None), None);
+    }
+
+    #[test]
+    fn legacy_configs_move_to_the_default_location() {
+        let dir = TempDir::new("legacy");
+        let legacy = dir.0.join("home").join(".mpr").join("config.toml");
+        fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+        fs::write(&legacy, "interval_ms = 50\n").unwrap();
+
+        let path = locations(&dir.0).resolve(None).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "interval_ms = 50\n");
+        assert!(!legacy.exists());
+        // The emptied legacy directory is removed as well
+        assert!(!legacy.parent().unwrap().exists());
+    }
+
+    #[test]
+    fn legacy_configs_never_replace_existing_ones() {
+        let dir = TempDir::new("legacy-existing");
+        let legacy = dir.0.join("home").join(".mpr").join("config.toml");
+        fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+        fs::write(&legacy, "interval_ms = 50\n").unwrap();
+        fs::write(legacy.with_file_name("notes.txt"), "").unwrap();
+        let path = dir.0.join("appdata").join("mpr").join("config.toml");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "interval_ms = 100\n").unwrap();
+
+        assert_eq!(locations(&dir.0).resolve(None), Some(path.clone()));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "interval_ms = 100\n");
+        assert!(legacy.exists());
+
+        // Other files keep the legacy directory after a migration
+        fs::remove_file(&path).unwrap();
+        locations(&dir.0).resolve(None);
+        assert!(!legacy.exists());
+        assert!(legacy.with_file_name("notes.txt").exists());
+    }
+
+    fn overrides(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    fn layer(text: &str) -> Table {
+        toml::from_str(text).unwrap()
+    }
+
+    /// Effective value of a top-level setting
+    fn value(store: &LayeredConfig, key: &str) -> Value {
+        to_table(store.config())[key].clone()
+    }
+
+    /// Layers setting `text_color` to 2 (system), 3 (user), 4 (environment)
+    /// and 5 (command line), leaving out the last `skip` - 1 of them
+    fn color_layers(dir: &TempDir, skip: usize) -> LayeredConfig {
+        let user = dir.0.join("config.toml");
+        fs::write(&user, if skip < 4 { "text_color = 3\n" } else { "" }).unwrap();
+        let system = (skip < 5).then(|| layer("text_color = 2\nbackground_color = 7\n"));
+        let environment = if skip < 3 { layer("text_color = 4\n") } else { Table::new() };
+        let set = if skip < 2 { overrides(&[("text_color", "5")]) } else { Vec::new() };
+        LayeredConfig::from_layers(system, Some(user), environment, &set)
+    }
+
+    #[test]
+    fn later_layers_take_precedence() {
+        let dir = TempDir::new("layers");
+        let default = Value::Integer(to_table(&Config::default())["text_color"].as_integer().unwrap());
+        let expected = [
+            (Value::Integer(5), Layer::CommandLine),
+            (Value::Integer(4), Layer::Environment),
+            (Value::Integer(3), Layer::User),
+            (Value::Integer(2), Layer::System),
+            (default, Layer::Default),
+        ];
+        for (skip, (color, layer)) in (1..).zip(expected) {
+            let store = color_layers(&dir, skip);
+            assert_eq!(value(&store, "text_color"), color, "{:?}", layer);
+            assert_eq!(store.origin("text_color"), layer);
+        }
+        // Values of lower layers that are not overridden stay
+        let store = color_layers(&dir, 1);
+        assert_eq!(value(&store, "background_color"), Value::Integer(7));
+        assert_eq!(store.origin("background_color"), Layer::System);
+        assert_eq!(color_layers(&dir, 5).origin("background_color"), Layer::Default);
+    }
+
+    #[test]
+    fn invalid_layers_are_skipped_as_a_whole() {
+        let dir = TempDir::new("invalid-layer");
+        let user = dir.0.join("config.toml");
+        fs::write(&user, "text_color = \"blue\"\nbackground_color = 1\n").unwrap();
+        let store = LayeredConfig::from_layers(None, Some(user), layer("text_color = 4\n"), &[]);
+        assert_eq!(value(&store, "text_color"), Value::Integer(4));
+        assert_eq!(store.origin("text_color"), Layer::Environment);
+        assert_eq!(store.origin("background_color"), Layer::Default);
+    }
+
+    #[test]
+    fn show_lists_values_with_their_origin_and_locks() {
+        let system = layer("locked = [\"text_color\"]\ntext_color = 2\n");
+        let set = overrides(&[("text_color", "5"), ("background_color", "0x0000FF")]);
+        let store = LayeredConfig::from_layers(Some(system), None, Table::new(), &set);
+        let lines: Vec<_> = store.describe(true).lines().map(str::to_string).collect();
+        assert!(lines.contains(&"text_color = 2  # system, locked".to_string()), "{:#?}", lines);
+        assert!(lines.contains(&"background_color = 255  # command line".to_string()), "{:#?}", lines);
+        assert!(store.describe(false).lines().any(|line| line == "text_color = 2"));
+    }
+
+    #[test]
+    fn environment_variables_set_top_level_values() {
+        let table = environment_table(|name| match name {
+            "MPR_TEXT_COLOR" => Some("0x00FF00".to_string()),
+            // Not valid TOML, kept as a string
+            "MPR_BACKGROUND_COLOR" => Some("green".to_string()),
+            _ => None,
+        });
+        assert_eq!(table, layer("text_color = 0x00FF00\nbackground_color = \"green\"\n"));
+    }
+
+    #[test]
+    fn override_keys_must_name_settings() {
+        assert_eq!(validate_override_key("text_color"), Ok(()));
+        assert_eq!(validate_override_key("text_colour"), Err("Unknown setting: text_colour".into()));
+    }
+
+    #[test]
+    fn saving_writes_only_changed_values_to_the_user_file() {
+        let dir = TempDir::new("save");
+        let user = dir.0.join("nested").join("config.toml");
+        let mut store = LayeredConfig::from_layers(Some(layer("text_color = 2\n")), Some(user.clone()), Table::new(), &[]);
+        let mut config = to_table(store.config());
+        config.insert("background_color".to_string(), Value::Integer(9));
+        store.save(&from_table(config).unwrap()).unwrap();
+        assert_eq!(fs::read_to_string(&user).unwrap(), "background_color = 9\n");
+        assert_eq!(store.origin("background_color"), Layer::User);
+        assert_eq!(store.origin("text_color"), Layer::System);
+    }
+
+    #[test]
+    fn hotkeys_take_their_origin_from_the_layer_setting_them() {
+        let set = overrides(&[("hotkeys.copy_position", "\"Ctrl+Shift+C\"")]);
+        let system = layer("[hotkeys]\nopen_settings = \"Ctrl+Alt+S\"\n");
+        let store = LayeredConfig::from_layers(Some(system), None, Table::new(), &set);
+        assert_eq!(store.config().hotkeys["copy_position"], "Ctrl+Shift+C");
+        assert_eq!(store.origin("hotkeys.copy_position"), Layer::CommandLine);
+        assert_eq!(store.origin("hotkeys.open_settings"), Layer::System);
+        assert_eq!(store.origin("text_color"), Layer::Default);
+        // Tables cannot be set from the environment
+        assert!(environment_table(|name| (name == "MPR_HOTKEYS").then(|| "none".to_string())).is_empty());
+    }
+
+    #[test]
+    fn locks_apply_to_profile_settings() {
+        let locked = locks(&["text_color", "hotkeys"]);
+        assert!(is_locked(&locked, "text_color"));
+        assert!(is_locked(&locked, "profiles.work.text_color"));
+        assert!(is_locked(&locked, "hotkeys.freeze"));
+        assert!(!is_locked(&locked, "profiles.work.background_color"));
+        assert!(!is_locked(&locked, "profiles.text_color"));
+        assert!(!is_locked(&locked, "text_color_extra"));
+    }
+
+    #[test]
+    fn locked_colors_lock_the_theme() {
+        let mut locked = locks(&["background_color"]);
+        add_implied_locks(&mut locked);
+        assert!(is_locked(&locked, "theme"));
+        assert!(is_locked(&locked, "profiles.work.theme"));
+
+        let mut locked = locks(&["interval_ms"]);
+        add_implied_locks(&mut locked);
+        assert!(!is_locked(&locked, "theme"));
+    }
+
+    #[test]
+    fn new_profiles_drop_locked_values() {
+        let locked = locks(&["text_color"]);
+        let mut merged = to_table(&Config::default());
+        let mut origins = BTreeMap::new();
+        let user = layer("[profiles.work]\ntext_color = 0x0000FF\nbackground_color = 0x00FF00\n");
+        apply_layer(&mut merged, &mut origins, &locked, user, Layer::User);
+
+        let config = from_table(merged).unwrap();
+        let work = &config.profiles["work"];
+        assert_eq!(work.text_color, Profile::default().text_color);
+        assert_eq!(work.background_color, 0x00FF00);
+    }
+
+    #[test]
+    fn existing_profiles_drop_locked_values() {
+        let locked = locks(&["text_color"]);
+        let mut merged = to_table(&Config::default());
+        let mut origins = BTreeMap::new();
+        apply_layer(&mut merged, &mut origins, &BTreeSet::new(), layer("[profiles.work]\nbackground_color = 0x00FF00\n"), Layer::System);
+        apply_layer(&mut merged, &mut origins, &locked, layer("[profiles.work]\ntext_color = 0x0000FF\n"), Layer::User);
+
+        let config = from_table(merged).unwrap();
+        assert_eq!(config.profiles["work"].text_color, Profile::default().text_color);
+    }
+
+    #[test]
+    fn profiles_take_locked_top_level_values() {
+        let locked = locks(&["text_color", "background_color"]);
+        let mut merged = to_table(&Config::default());
+        let mut origins = BTreeMap::new();
+        let system = layer("text_color = 0x123456\nbackground_color = 0x654321\n[profiles.work]\ntext_color = 0x0000FF\n");
+        apply_layer(&mut merged, &mut origins, &BTreeSet::new(), system, Layer::System);
+        apply_layer(&mut merged, &mut origins, &locked, layer("[profiles.home]\ninterval_ms = 50\n"), Layer::User);
+        lock_profile_fields(&mut merged, &locked);
+
+        let config = from_table(merged).unwrap();
+        for name in ["work", "home"] {
+            assert_eq!(config.profiles[name].text_color, 0x123456, "{}", name);
+            assert_eq!(config.profiles[name].background_color, 0x654321, "{}", name);
+        }
+        assert_eq!(config.profiles["home"].interval_ms, 50);
+    }
+}
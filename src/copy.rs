@@ -0,0 +1,102 @@
+// Copying positions as text: built-in formats and custom templates
+
+use crate::position::PLACEHOLDERS;
+use crate::template::Template;
+
+/// Built-in copy format
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CopyFormat {
+    /// Name used in the configuration
+    pub name: &'static str,
+    /// Menu label
+    pub label: &'static str,
+    pub template: &'static str,
+}
+
+/// Copy format used when none is configured
+pub const DEFAULT_COPY_FORMAT: &str = "plain";
+
+/// Built-in copy formats in menu order
+pub const COPY_FORMATS: &[CopyFormat] = &[
+    CopyFormat { name: "plain", label: "x,y", template: "{x},{y}" },
+    // Pastes into two spreadsheet cells
+    CopyFormat { name: "tab", label: "x<Tab>y", template: "{x}\t{y}" },
+    // Coordinates of programs that are not DPI aware
+    CopyFormat { name: "logical", label: "Logical x,y (DPI-scaled)", template: "{logical_x},{logical_y}" },
+    // Coordinates of games and kiosk applications with a fixed design resolution
+    CopyFormat { name: "reference", label: "Reference resolution x,y", template: "{ref_x},{ref_y}" },
+    CopyFormat { name: "json", label: "JSON", template: "{{\"x\": {x}, \"y\": {y}}}" },
+    CopyFormat { name: "python", label: "Python (x, y)", template: "({x}, {y})" },
+    CopyFormat { name: "pyautogui", label: "pyautogui.moveTo(x, y)", template: "pyautogui.moveTo({x}, {y})" },
+    CopyFormat { name: "xdotool", label: "xdotool mousemove x y", template: "xdotool mousemove {x} {y}" },
+    CopyFormat { name: "autohotkey", label: "AutoHotkey Click x y", template: "Click {x} {y}" },
+    CopyFormat { name: "css", label: "CSS left/top", template: "left: {x}px; top: {y}px;" },
+    // Color of the pixel under the cursor
+    CopyFormat { name: "hex", label: "Color #RRGGBB", template: "{color}" },
+    CopyFormat { name: "rgb", label: "Color rgb(r, g, b)", template: "{rgb}" },
+    CopyFormat { name: "hsl", label: "Color hsl(h, s%, l%)", template: "{hsl}" },
+];
+
+/// Template of a configured copy format: the name of a built-in format or a
+/// custom template containing at least one placeholder
+pub fn resolve_copy_format(format: &str) -> Result<Template, String> {
+    resolve_format(format, "copy format", COPY_FORMATS, PLACEHOLDERS)
+}
+
+/// Template of `format`, the name of one of `builtins` or a custom template
+/// with `placeholders`; `kind` names the setting in errors, e.g. `copy format`
+pub fn resolve_format(format: &str, kind: &str, builtins: &[CopyFormat], placeholders: &[&str]) -> Result<Template, String> {
+    if let Some(builtin) = builtins.iter().find(|builtin| builtin.name == format) {
+        return Template::parse(builtin.template, placeholders);
+    }
+    if !format.contains('{') {
+        let names: Vec<_> = builtins.iter().map(|builtin| builtin.name).collect();
+        return Err(format!(
+            "Unknown {} \"{}\", expected one of {} or a template like \"{}\"",
+            kind,
+            format,
+            names.join(", "),
+            builtins.first().map_or("", |builtin| builtin.template),
+        ));
+    }
+    Template::parse(format, placeholders)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_formats_parse() {
+        for format in COPY_FORMATS {
+            assert!(Template::parse(format.template, PLACEHOLDERS).is_ok(), "{}", format.name);
+            assert!(resolve_copy_format(format.name).is_ok(), "{}", format.name);
+        }
+    }
+
+    #[test]
+    fn default_format_is_built_in() {
+        assert!(COPY_FORMATS.iter().any(|format| format.name == DEFAULT_COPY_FORMAT));
+    }
+
+    #[test]
+    fn names_resolve_to_their_template() {
+        let template = resolve_copy_format("json").unwrap();
+        assert_eq!(template.render(|name| name.to_uppercase()), "{\"x\": X, \"y\": Y}");
+    }
+
+    #[test]
+    fn custom_templates_are_parsed() {
+        let template = resolve_copy_format("{x};{y}").unwrap();
+        assert_eq!(template.render(|name| name.to_string()), "x;y");
+        assert!(resolve_copy_format("{nope}").is_err());
+    }
+
+    #[test]
+    fn unknown_names_list_the_built_in_formats() {
+        let error = resolve_copy_format("xml").unwrap_err();
+        assert!(error.starts_with("Unknown copy format \"xml\""), "{}", error);
+        assert!(error.contains("plain, tab,"), "{}", error);
+        assert!(error.ends_with("a template like \"{x},{y}\""), "{}", error);
+    }
+}
@@ -0,0 +1,77 @@
+// Conversion between physical pixels and logical (DPI-scaled) units
+
+/// DPI of a monitor at 100% scaling, where logical units equal pixels
+pub const BASE_DPI: u32 = 96;
+
+/// Scaling of a monitor in percent, e.g. 144 DPI is 150%
+pub fn scale_percent(dpi: u32) -> u32 {
+    div_round(dpi as i64 * 100, BASE_DPI as i64) as u32
+}
+
+/// Logical units of a physical coordinate on a monitor with `dpi` whose edge
+/// is at `origin`. Windows keeps the monitor edges where they are and scales
+/// only the offset from them. An unknown DPI of 0 counts as 100%.
+pub fn to_logical(physical: i32, origin: i32, dpi: u32) -> i32 {
+    let dpi = if dpi == 0 { BASE_DPI } else { dpi };
+    let offset = div_round((physical as i64 - origin as i64) * BASE_DPI as i64, dpi as i64);
+    (origin as i64 + offset) as i32
+}
+
+/// Division rounding halves away from zero, so that negative coordinates
+/// convert symmetrically to positive ones
+fn div_round(numerator: i64, denominator: i64) -> i64 {
+    let half = denominator / 2;
+    if numerator < 0 {
+        (numerator - half) / denominator
+    } else {
+        (numerator + half) / denominator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// DPI at 100%, 125%, 150%, 175% and 200% scaling
+    const DPIS: [u32; 5] = [96, 120, 144, 168, 192];
+
+    #[test]
+    fn scale_percent_of_the_common_scalings() {
+        assert_eq!(DPIS.map(scale_percent), [100, 125, 150, 175, 200]);
+    }
+
+    #[test]
+    fn primary_monitor_offsets_are_scaled() {
+        assert_eq!(DPIS.map(|dpi| to_logical(1000, 0, dpi)), [1000, 800, 667, 571, 500]);
+    }
+
+    #[test]
+    fn offsets_are_scaled_from_the_monitor_origin() {
+        // Monitor to the right of a 1920 px primary
+        assert_eq!(DPIS.map(|dpi| to_logical(2920, 1920, dpi)), [2920, 2720, 2587, 2491, 2420]);
+        // The monitor edge itself stays in place
+        assert_eq!(DPIS.map(|dpi| to_logical(1920, 1920, dpi)), [1920; 5]);
+    }
+
+    #[test]
+    fn monitors_left_of_and_above_the_primary_keep_their_origin() {
+        // 3840 px wide monitor to the left of the primary
+        assert_eq!(DPIS.map(|dpi| to_logical(-1000, -3840, dpi)), [-1000, -1568, -1947, -2217, -2420]);
+        assert_eq!(DPIS.map(|dpi| to_logical(-1, -3840, dpi)), [-1, -769, -1281, -1646, -1920]);
+        // Monitor above the primary
+        assert_eq!(DPIS.map(|dpi| to_logical(-1080, -2160, dpi)), [-1080, -1296, -1440, -1543, -1620]);
+    }
+
+    #[test]
+    fn negative_offsets_round_like_positive_ones() {
+        assert_eq!(to_logical(-3, 0, 192), -2);
+        assert_eq!(to_logical(3, 0, 192), 2);
+        assert_eq!(to_logical(-100, 0, 144), -67);
+    }
+
+    #[test]
+    fn unknown_dpi_keeps_pixels() {
+        assert_eq!(to_logical(10, 0, 0), 10);
+        assert_eq!(to_logical(-1000, -3840, 0), -1000);
+    }
+}
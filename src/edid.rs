@@ -0,0 +1,208 @@
+// EDID (Extended Display Identification Data) parsing: monitor name, physical
+// size and native resolution
+
+/// Length of the base EDID block
+pub const EDID_BLOCK_LEN: usize = 128;
+
+const HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+
+/// Offsets of the four 18-byte descriptors in the base block
+const DESCRIPTOR_OFFSETS: [usize; 4] = [54, 72, 90, 108];
+const DESCRIPTOR_LEN: usize = 18;
+
+/// Display descriptor tag of the monitor name
+const MONITOR_NAME_TAG: u8 = 0xFC;
+
+/// Largest difference in millimetres accepted between the detailed and the
+/// centimetre size before the detailed size is considered wrong
+const SIZE_TOLERANCE_MM: u32 = 10;
+
+/// What MPR uses from an EDID
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Edid {
+    /// Three-letter PNP manufacturer ID, e.g. `DEL`
+    pub manufacturer: String,
+    pub product_code: u16,
+    /// Name from the monitor name descriptor
+    pub name: Option<String>,
+    /// Width and height of the visible image in millimetres; `None` for
+    /// projectors and other displays without a fixed size
+    pub size_mm: Option<(u32, u32)>,
+    /// Resolution of the preferred (first) detailed timing
+    pub native_resolution: Option<(u32, u32)>,
+}
+
+impl Edid {
+    /// Model name, or manufacturer ID and product code for monitors without
+    /// one, e.g. `DEL 40F5`
+    pub fn model(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("{} {:04X}", self.manufacturer, self.product_code),
+        }
+    }
+}
+
+/// Parses the base block of an EDID; extension blocks are ignored
+pub fn parse_edid(data: &[u8]) -> Result<Edid, String> {
+    if data.len() < EDID_BLOCK_LEN {
+        return Err(format!("EDID too short: {} bytes, expected at least {}", data.len(), EDID_BLOCK_LEN));
+    }
+    let block = &data[..EDID_BLOCK_LEN];
+    if block[..8] != HEADER {
+        return Err("Missing EDID header".into());
+    }
+    if block.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) != 0 {
+        return Err("EDID checksum mismatch".into());
+    }
+
+    // Three 5-bit letters, 1 is 'A'
+    let id = u16::from_be_bytes([block[8], block[9]]);
+    let manufacturer = [10, 5, 0]
+        .iter()
+        .map(|shift| char::from(b'A' - 1 + ((id >> shift) & 0x1F) as u8))
+        .collect();
+
+    let mut edid = Edid {
+        manufacturer,
+        product_code: u16::from_le_bytes([block[10], block[11]]),
+        ..Default::default()
+    };
+
+    let mut detailed_size = None;
+    for offset in DESCRIPTOR_OFFSETS {
+        let descriptor = &block[offset..offset + DESCRIPTOR_LEN];
+        if descriptor[0] != 0 || descriptor[1] != 0 {
+            // Detailed timing; the first one is the preferred mode
+            if edid.native_resolution.is_none() {
+                let width = descriptor[2] as u32 | ((descriptor[4] as u32 & 0xF0) << 4);
+                let height = descriptor[5] as u32 | ((descriptor[7] as u32 & 0xF0) << 4);
+                edid.native_resolution = Some((width, height));
+                let width_mm = descriptor[12] as u32 | ((descriptor[14] as u32 & 0xF0) << 4);
+                let height_mm = descriptor[13] as u32 | ((descriptor[14] as u32 & 0x0F) << 8);
+                detailed_size = (width_mm > 0 && height_mm > 0).then_some((width_mm, height_mm));
+            }
+        } else if descriptor[3] == MONITOR_NAME_TAG {
+            // Up to 13 characters, ended by a line feed and padded with spaces
+            let text: String = descriptor[5..].iter().take_while(|&&byte| byte != 0x0A).map(|&byte| char::from(byte)).collect();
+            let name = text.trim();
+            if !name.is_empty() {
+                edid.name = Some(name.to_string());
+            }
+        }
+    }
+
+    // Bytes 21 and 22 hold the size in whole centimetres; some monitors put
+    // centimetres or nonsense into the more precise detailed timing as well
+    let basic_size = (block[21] > 0 && block[22] > 0).then(|| (block[21] as u32 * 10, block[22] as u32 * 10));
+    edid.size_mm = match (detailed_size, basic_size) {
+        (Some(detailed), Some(basic))
+            if detailed.0.abs_diff(basic.0) > SIZE_TOLERANCE_MM || detailed.1.abs_diff(basic.1) > SIZE_TOLERANCE_MM =>
+        {
+            Some(basic)
+        }
+        (Some(detailed), _) => Some(detailed),
+        (None, basic) => basic,
+    };
+    Ok(edid)
+}
+
+/// Registry key below `HKEY_LOCAL_MACHINE` with the `EDID` value of a monitor,
+/// from its device interface name such as
+/// `\\?\DISPLAY#DEL40F5#5&1a2b3c4&0&UID4353#{e6f07b5f-ee97-4a90-b076-33f57bf4eaa7}`
+pub fn edid_registry_key(device_interface: &str) -> Option<String> {
+    let mut parts = device_interface.split('#');
+    if !parts.next()?.ends_with("DISPLAY") {
+        return None;
+    }
+    let model = parts.next().filter(|part| !part.is_empty())?;
+    let instance = parts.next().filter(|part| !part.is_empty())?;
+    Some(format!("SYSTEM\\CurrentControlSet\\Enum\\DISPLAY\\{}\\{}\\Device Parameters", model, instance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fixture from `tests/fixtures/edid`: a hex dump of a 128-byte base block
+    fn fixture(hex: &str) -> Vec<u8> {
+        let data: Vec<u8> = hex.split_whitespace().map(|byte| u8::from_str_radix(byte, 16).unwrap()).collect();
+        assert_eq!(data.len(), EDID_BLOCK_LEN);
+        data
+    }
+
+    fn dell_u2415() -> Vec<u8> {
+        fixture(include_str!("../tests/fixtures/edid/dell_u2415.hex"))
+    }
+
+    #[test]
+    fn monitors_with_a_valid_checksum_parse() {
+        let edid = parse_edid(&dell_u2415()).unwrap();
+        assert_eq!(edid.manufacturer, "DEL");
+        assert_eq!(edid.product_code, 0xA0B4);
+        assert_eq!(edid.name.as_deref(), Some("DELL U2415"));
+        assert_eq!(edid.model(), "DELL U2415");
+        assert_eq!(edid.size_mm, Some((518, 324)));
+        assert_eq!(edid.native_resolution, Some((1920, 1200)));
+    }
+
+    #[test]
+    fn extension_blocks_are_ignored() {
+        let mut data = dell_u2415();
+        data.extend([0x02; EDID_BLOCK_LEN]);
+        assert_eq!(parse_edid(&data), parse_edid(&dell_u2415()));
+    }
+
+    #[test]
+    fn checksum_mismatches_are_rejected() {
+        let data = fixture(include_str!("../tests/fixtures/edid/bad_checksum.hex"));
+        assert_eq!(parse_edid(&data), Err("EDID checksum mismatch".into()));
+    }
+
+    #[test]
+    fn short_blocks_and_missing_headers_are_rejected() {
+        let data = dell_u2415();
+        assert_eq!(parse_edid(&data[..127]), Err("EDID too short: 127 bytes, expected at least 128".into()));
+        let mut data = data;
+        data[0] = 0xFF;
+        data[127] = data[127].wrapping_sub(0xFF);
+        assert_eq!(parse_edid(&data), Err("Missing EDID header".into()));
+    }
+
+    #[test]
+    fn projectors_have_no_size() {
+        let edid = parse_edid(&fixture(include_str!("../tests/fixtures/edid/projector.hex"))).unwrap();
+        assert_eq!(edid.model(), "EPSON PJ");
+        assert_eq!(edid.size_mm, None);
+        assert_eq!(edid.native_resolution, Some((1280, 800)));
+    }
+
+    #[test]
+    fn detailed_sizes_in_centimetres_fall_back_to_the_basic_size() {
+        let edid = parse_edid(&fixture(include_str!("../tests/fixtures/edid/size_in_centimetres.hex"))).unwrap();
+        assert_eq!(edid.size_mm, Some((600, 340)));
+        // Only the height is wrong: 598 x 34
+        let edid = parse_edid(&fixture(include_str!("../tests/fixtures/edid/height_in_centimetres.hex"))).unwrap();
+        assert_eq!(edid.size_mm, Some((600, 340)));
+    }
+
+    #[test]
+    fn monitors_without_a_name_use_the_product_code() {
+        let edid = parse_edid(&fixture(include_str!("../tests/fixtures/edid/no_name.hex"))).unwrap();
+        assert_eq!(edid.name, None);
+        assert_eq!(edid.model(), "GSM 5B7F");
+        assert_eq!(edid.size_mm, Some((597, 336)));
+        assert_eq!(edid.native_resolution, Some((2560, 1440)));
+    }
+
+    #[test]
+    fn registry_keys_come_from_the_device_interface() {
+        let key = edid_registry_key(r"\\?\DISPLAY#DEL40F5#5&1a2b3c4&0&UID4353#{e6f07b5f-ee97-4a90-b076-33f57bf4eaa7}");
+        assert_eq!(
+            key.as_deref(),
+            Some(r"SYSTEM\CurrentControlSet\Enum\DISPLAY\DEL40F5\5&1a2b3c4&0&UID4353\Device Parameters")
+        );
+        assert_eq!(edid_registry_key(r"\\?\PCI#VEN_10DE#3&11583659&0&08"), None);
+        assert_eq!(edid_registry_key(r"\\?\DISPLAY##"), None);
+    }
+}
@@ -0,0 +1,355 @@
+// Global hotkey bindings written as text, such as "Ctrl+Alt+C"
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Actions that can be bound to a global hotkey
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    CopyPosition,
+    Freeze,
+    CapturePoint,
+    SetOrigin,
+    NextDisplayMode,
+    Measure,
+    MeasureRectangle,
+    OpenSettings,
+}
+
+impl Action {
+    pub const ALL: &'static [Action] = &[
+        Action::CopyPosition,
+        Action::Freeze,
+        Action::CapturePoint,
+        Action::SetOrigin,
+        Action::NextDisplayMode,
+        Action::Measure,
+        Action::MeasureRectangle,
+        Action::OpenSettings,
+    ];
+
+    /// Key of the action in the `[hotkeys]` table
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::CopyPosition => "copy_position",
+            Action::Freeze => "freeze",
+            Action::CapturePoint => "capture_point",
+            Action::SetOrigin => "set_origin",
+            Action::NextDisplayMode => "next_display_mode",
+            Action::Measure => "measure",
+            Action::MeasureRectangle => "measure_rectangle",
+            Action::OpenSettings => "open_settings",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().copied().find(|action| action.name() == name)
+    }
+}
+
+/// Modifier keys, with the bits of the Windows `MOD_*` flags
+pub const MOD_ALT: u32 = 0x1;
+pub const MOD_CONTROL: u32 = 0x2;
+pub const MOD_SHIFT: u32 = 0x4;
+pub const MOD_WIN: u32 = 0x8;
+
+/// Modifier names in display order; the first name of each is the canonical one
+const MODIFIERS: &[(u32, &[&str])] = &[
+    (MOD_CONTROL, &["Ctrl", "Control", "Strg"]),
+    (MOD_ALT, &["Alt"]),
+    (MOD_SHIFT, &["Shift"]),
+    (MOD_WIN, &["Win", "Windows", "Super", "Meta"]),
+];
+
+/// Named keys and their virtual-key codes; letters, digits and F1-F24 are handled separately
+const NAMED_KEYS: &[(&str, u32)] = &[
+    ("Space", 0x20),
+    ("Enter", 0x0D),
+    ("Tab", 0x09),
+    ("Backspace", 0x08),
+    ("Esc", 0x1B),
+    ("Insert", 0x2D),
+    ("Delete", 0x2E),
+    ("Home", 0x24),
+    ("End", 0x23),
+    ("PageUp", 0x21),
+    ("PageDown", 0x22),
+    ("Left", 0x25),
+    ("Up", 0x26),
+    ("Right", 0x27),
+    ("Down", 0x28),
+    ("PrintScreen", 0x2C),
+    ("Pause", 0x13),
+];
+
+/// Aliases accepted for named keys
+const KEY_ALIASES: &[(&str, &str)] = &[
+    ("Return", "Enter"),
+    ("Escape", "Esc"),
+    ("Ins", "Insert"),
+    ("Del", "Delete"),
+    ("PgUp", "PageUp"),
+    ("PgDn", "PageDown"),
+    ("PrtSc", "PrintScreen"),
+];
+
+/// A key with its modifiers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Hotkey {
+    /// `MOD_*` bits
+    pub modifiers: u32,
+    /// Windows virtual-key code
+    pub key: u32,
+}
+
+impl Hotkey {
+    /// Parses a binding like `Ctrl+Alt+C`; names are case-insensitive
+    pub fn parse(text: &str) -> Result<Hotkey, String> {
+        let parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        if parts.iter().all(|part| part.is_empty()) {
+            return Err("Empty hotkey".into());
+        }
+        if parts.iter().any(|part| part.is_empty()) {
+            return Err(format!("Missing key name in \"{}\"", text));
+        }
+
+        let (key_name, modifier_names) = parts.split_last().unwrap_or((&"", &[]));
+        let mut modifiers = 0;
+        for name in modifier_names {
+            let Some(modifier) = find_modifier(name) else {
+                return Err(if key_code(name).is_some() {
+                    format!("Only one key besides the modifiers is allowed in \"{}\"", text)
+                } else {
+                    format!("Unknown modifier \"{}\" in \"{}\", expected Ctrl, Alt, Shift or Win", name, text)
+                });
+            };
+            if modifiers & modifier != 0 {
+                return Err(format!("Modifier \"{}\" is given twice in \"{}\"", name, text));
+            }
+            modifiers |= modifier;
+        }
+
+        let Some(key) = key_code(key_name) else {
+            return Err(if find_modifier(key_name).is_some() {
+                format!("\"{}\" has no key besides the modifiers", text)
+            } else {
+                format!("Unknown key \"{}\" in \"{}\"", key_name, text)
+            });
+        };
+
+        let hotkey = Hotkey { modifiers, key };
+        // Without Ctrl, Alt or Win the key could no longer be typed in other programs
+        if modifiers & !MOD_SHIFT == 0 && !hotkey.is_function_key() {
+            return Err(format!("\"{}\" needs Ctrl, Alt or Win; only F1-F24, Pause and PrintScreen work alone", text));
+        }
+        Ok(hotkey)
+    }
+
+    fn is_function_key(&self) -> bool {
+        (0x70..=0x87).contains(&self.key) || self.key == 0x13 || self.key == 0x2C
+    }
+}
+
+impl fmt::Display for Hotkey {
+    /// Canonical form, e.g. `Ctrl+Shift+F5`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (modifier, names) in MODIFIERS {
+            if self.modifiers & modifier != 0 {
+                write!(f, "{}+", names[0])?;
+            }
+        }
+        write!(f, "{}", key_name(self.key))
+    }
+}
+
+fn find_modifier(name: &str) -> Option<u32> {
+    MODIFIERS
+        .iter()
+        .find(|(_, names)| names.iter().any(|candidate| candidate.eq_ignore_ascii_case(name)))
+        .map(|(modifier, _)| *modifier)
+}
+
+/// Virtual-key code of a key name
+fn key_code(name: &str) -> Option<u32> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        // Letters and digits use their uppercase ASCII code
+        return c.is_ascii_alphanumeric().then(|| c.to_ascii_uppercase() as u32);
+    }
+    if let Some(number) = name.strip_prefix(['F', 'f']).and_then(|number| number.parse::<u32>().ok()) {
+        return (1..=24).contains(&number).then_some(0x70 + number - 1);
+    }
+    let name = KEY_ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(name))
+        .map_or(name, |(_, target)| target);
+    NAMED_KEYS.iter().find(|(candidate, _)| candidate.eq_ignore_ascii_case(name)).map(|(_, code)| *code)
+}
+
+fn key_name(code: u32) -> String {
+    match code {
+        0x30..=0x39 | 0x41..=0x5A => char::from(code as u8).to_string(),
+        0x70..=0x87 => format!("F{}", code - 0x70 + 1),
+        _ => NAMED_KEYS
+            .iter()
+            .find(|(_, candidate)| *candidate == code)
+            .map_or_else(|| format!("0x{:02X}", code), |(name, _)| name.to_string()),
+    }
+}
+
+/// Parses the `[hotkeys]` table. Returns the valid bindings and one message per
+/// unknown action, invalid binding or hotkey bound to several actions;
+/// conflicting bindings are left out. Empty bindings are unbound.
+pub fn parse_bindings(bindings: &BTreeMap<String, String>) -> (Vec<(Action, Hotkey)>, Vec<String>) {
+    let mut parsed = Vec::new();
+    let mut problems = Vec::new();
+    for (name, text) in bindings {
+        let Some(action) = Action::from_name(name) else {
+            let names: Vec<_> = Action::ALL.iter().map(|action| action.name()).collect();
+            problems.push(format!("hotkeys.{}: unknown action, expected one of: {}", name, names.join(", ")));
+            continue;
+        };
+        if text.trim().is_empty() {
+            continue;
+        }
+        match Hotkey::parse(text) {
+            Ok(hotkey) => parsed.push((action, hotkey)),
+            Err(message) => problems.push(format!("hotkeys.{}: {}", name, message)),
+        }
+    }
+
+    // One message per hotkey, however many actions share it
+    let mut conflicts = Vec::new();
+    for (_, hotkey) in &parsed {
+        if conflicts.contains(hotkey) {
+            continue;
+        }
+        let names: Vec<_> = parsed.iter().filter(|(_, other)| other == hotkey).map(|(action, _)| action.name()).collect();
+        if let [first @ .., last] = names.as_slice() {
+            if !first.is_empty() {
+                problems.push(format!("hotkeys: {} is bound to {} and {}", hotkey, first.join(", "), last));
+                conflicts.push(*hotkey);
+            }
+        }
+    }
+    parsed.retain(|(_, hotkey)| !conflicts.contains(hotkey));
+    (parsed, problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hotkey(modifiers: u32, key: char) -> Hotkey {
+        Hotkey { modifiers, key: key as u32 }
+    }
+
+    fn error(text: &str) -> String {
+        Hotkey::parse(text).unwrap_err()
+    }
+
+    fn bindings(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(action, text)| (action.to_string(), text.to_string())).collect()
+    }
+
+    #[test]
+    fn parses_modifiers_and_key() {
+        assert_eq!(Hotkey::parse("Ctrl+Alt+C"), Ok(hotkey(MOD_CONTROL | MOD_ALT, 'C')));
+        assert_eq!(Hotkey::parse(" Win + Shift + 7 "), Ok(hotkey(MOD_WIN | MOD_SHIFT, '7')));
+    }
+
+    #[test]
+    fn names_are_case_insensitive() {
+        assert_eq!(Hotkey::parse("ctrl+ALT+c"), Hotkey::parse("Ctrl+Alt+C"));
+        assert_eq!(Hotkey::parse("alt+f5"), Hotkey::parse("Alt+F5"));
+        assert_eq!(Hotkey::parse("ctrl+pgup"), Hotkey::parse("Ctrl+PageUp"));
+    }
+
+    #[test]
+    fn aliases_map_to_the_canonical_names() {
+        assert_eq!(Hotkey::parse("Strg+Alt+C"), Hotkey::parse("Ctrl+Alt+C"));
+        assert_eq!(Hotkey::parse("Control+Escape"), Hotkey::parse("Ctrl+Esc"));
+        assert_eq!(Hotkey::parse("Super+Del"), Hotkey::parse("Win+Delete"));
+        assert_eq!(Hotkey::parse("Meta+Shift+Return").map(|hotkey| hotkey.to_string()), Ok("Shift+Win+Enter".to_string()));
+    }
+
+    #[test]
+    fn display_is_canonical() {
+        assert_eq!(Hotkey::parse("shift+alt+ctrl+prtsc").unwrap().to_string(), "Ctrl+Alt+Shift+PrintScreen");
+        assert_eq!(Hotkey::parse("F24").unwrap().to_string(), "F24");
+    }
+
+    #[test]
+    fn duplicate_modifiers_are_rejected() {
+        assert!(error("Ctrl+Control+C").starts_with("Modifier \"Control\" is given twice"));
+        assert!(error("Alt+alt+C").contains("given twice"));
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        assert_eq!(error("Ctrl+Foo"), "Unknown key \"Foo\" in \"Ctrl+Foo\"");
+        assert!(error("Hyper+C").starts_with("Unknown modifier \"Hyper\""));
+        assert!(error("Ctrl+F25").starts_with("Unknown key \"F25\""));
+        assert!(error("Ctrl+Ä").starts_with("Unknown key"));
+    }
+
+    #[test]
+    fn modifiers_alone_are_rejected() {
+        assert_eq!(error("Ctrl+Alt"), "\"Ctrl+Alt\" has no key besides the modifiers");
+        assert!(error("Shift").contains("no key besides the modifiers"));
+    }
+
+    #[test]
+    fn more_than_one_key_is_rejected() {
+        assert!(error("Ctrl+A+B").starts_with("Only one key besides the modifiers"));
+    }
+
+    #[test]
+    fn empty_parts_are_rejected() {
+        assert_eq!(error(""), "Empty hotkey");
+        assert_eq!(error(" + "), "Empty hotkey");
+        assert!(error("Ctrl++C").starts_with("Missing key name"));
+    }
+
+    #[test]
+    fn typing_keys_need_ctrl_alt_or_win() {
+        assert!(error("C").contains("needs Ctrl, Alt or Win"));
+        assert!(error("Shift+C").contains("needs Ctrl, Alt or Win"));
+        assert!(error("Space").contains("needs Ctrl, Alt or Win"));
+    }
+
+    #[test]
+    fn function_keys_work_alone() {
+        assert!(Hotkey::parse("F1").is_ok());
+        assert!(Hotkey::parse("Shift+F12").is_ok());
+        assert!(Hotkey::parse("Pause").is_ok());
+        assert!(Hotkey::parse("PrintScreen").is_ok());
+    }
+
+    #[test]
+    fn bindings_report_unknown_actions_and_skip_empty_ones() {
+        let (parsed, problems) = parse_bindings(&bindings(&[("freeze", ""), ("copy_position", "Ctrl+Alt+C"), ("jump", "Ctrl+J")]));
+        assert_eq!(parsed, vec![(Action::CopyPosition, hotkey(MOD_CONTROL | MOD_ALT, 'C'))]);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("hotkeys.jump: unknown action"));
+    }
+
+    #[test]
+    fn conflicts_are_reported_once_and_left_out() {
+        let (parsed, problems) = parse_bindings(&bindings(&[
+            ("copy_position", "Ctrl+Alt+X"),
+            ("freeze", "ctrl+alt+x"),
+            ("measure", "Alt+Ctrl+X"),
+            ("set_origin", "Ctrl+Alt+O"),
+        ]));
+        assert_eq!(parsed, vec![(Action::SetOrigin, hotkey(MOD_CONTROL | MOD_ALT, 'O'))]);
+        assert_eq!(problems, vec!["hotkeys: Ctrl+Alt+X is bound to copy_position, freeze and measure".to_string()]);
+    }
+
+    #[test]
+    fn two_way_conflicts_name_both_actions() {
+        let (parsed, problems) = parse_bindings(&bindings(&[("freeze", "F9"), ("measure", "F9")]));
+        assert!(parsed.is_empty());
+        assert_eq!(problems, vec!["hotkeys: F9 is bound to freeze and measure".to_string()]);
+    }
+}
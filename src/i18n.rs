@@ -0,0 +1,521 @@
+// Translated user interface strings
+
+use serde::{Deserialize, Serialize};
+
+/// Language setting in the configuration
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    /// Language of the Windows user interface
+    #[default]
+    #[serde(rename = "system")]
+    System,
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "de")]
+    German,
+}
+
+/// Language the user interface is shown in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    English,
+    German,
+}
+
+impl Locale {
+    /// Number of supported languages, the length of every translation entry
+    pub const COUNT: usize = 2;
+
+    /// Locale for a Windows language identifier; unsupported languages fall back to English
+    pub fn from_language_id(language_id: u16) -> Locale {
+        // The low 10 bits hold the primary language
+        const LANG_GERMAN: u16 = 0x07;
+        match language_id & 0x3FF {
+            LANG_GERMAN => Locale::German,
+            _ => Locale::English,
+        }
+    }
+}
+
+impl Language {
+    /// Locale to use, `system` being the locale of the user interface
+    pub fn resolve(self, system: Locale) -> Locale {
+        match self {
+            Language::System => system,
+            Language::English => Locale::English,
+            Language::German => Locale::German,
+        }
+    }
+}
+
+/// Declares `Message` and, for tests, the list of all its keys
+macro_rules! messages {
+    ($($name:ident,)*) => {
+        /// Keys of all translated strings
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum Message {
+            $($name,)*
+        }
+
+        #[cfg(test)]
+        impl Message {
+            const ALL: &'static [Message] = &[$(Message::$name,)*];
+        }
+    };
+}
+
+messages! {
+    DefaultTooltip,
+    MenuProfile,
+    MenuTheme,
+    MenuDisplayMode,
+    MenuSetOrigin,
+    RelativeTo,
+    LogicalPosition,
+    WindowPosition,
+    PhysicalPosition,
+    CalibratedPosition,
+    ReferencePosition,
+    ReferenceOutside,
+    ColorValues,
+    MenuCalibration,
+    MenuNewCalibration,
+    CalibrationTitle,
+    CalibrationName,
+    CalibrationInstructions,
+    CalibrationClickPoint,
+    CalibrationTarget,
+    CalibrationInvalidTarget,
+    CalibrationAddPoint,
+    CalibrationSaved,
+    CalibrationFailed,
+    MenuCopyPosition,
+    MenuCopyFormat,
+    MenuFreeze,
+    Frozen,
+    MenuCapturePoint,
+    MenuRecentPoints,
+    MenuMeasure,
+    MenuMeasureRectangle,
+    MenuCopyMeasurement,
+    MenuRectangleFormat,
+    MeasuringFrom,
+    Measured,
+    MeasurementValues,
+    RectangleValues,
+    NoPoints,
+    MenuCopyPoint,
+    MenuEditPointLabel,
+    MenuDeletePoint,
+    MenuExportPoints,
+    MenuClearPoints,
+    PointLabelTitle,
+    PointLabel,
+    CsvFiles,
+    JsonFiles,
+    MarkdownFiles,
+    MenuSettings,
+    MenuExit,
+    SettingsTitle,
+    GroupAppearance,
+    GroupDisplay,
+    GroupGeneral,
+    GroupHotkeys,
+    FieldTheme,
+    FieldTextColor,
+    FieldBackgroundColor,
+    FieldDisplayMode,
+    FieldNumberFormat,
+    FieldUnit,
+    FieldInterval,
+    FieldLanguage,
+    FieldTooltip,
+    FieldCopyFormat,
+    FieldMeasureFormat,
+    FieldRectangleFormat,
+    FieldOriginX,
+    FieldOriginY,
+    HotkeyCopyPosition,
+    HotkeyFreeze,
+    HotkeyCapturePoint,
+    HotkeySetOrigin,
+    HotkeyNextDisplayMode,
+    HotkeyMeasure,
+    HotkeyMeasureRectangle,
+    HotkeyOpenSettings,
+    LanguageSystem,
+    LanguageEnglish,
+    LanguageGerman,
+    ThemeCustom,
+    ThemeClassic,
+    ThemeHighContrast,
+    ThemeSolarizedDark,
+    ThemeSolarizedLight,
+    ThemeTaskbar,
+    ThemeAuto,
+    DisplayAbsolute,
+    DisplayRelative,
+    DisplayMonitor,
+    DisplayLogical,
+    DisplayClient,
+    DisplayWindow,
+    DisplayCalibrated,
+    DisplayReference,
+    DisplayColor,
+    NumberZeroPadded,
+    NumberSpacePadded,
+    UnitPixels,
+    UnitMillimeters,
+    UnitInches,
+    UnitPoints,
+    PreviewDescription,
+    UseSuggestedColor,
+    CustomColors,
+    ImportPalette,
+    ExportPalette,
+    ResetToDefaults,
+    Apply,
+    Ok,
+    Cancel,
+    GimpPaletteFiles,
+    JsonPaletteFiles,
+    LowContrast,
+    SettingLocked,
+    MustBeColor,
+    MustBeOneOf,
+    MustBeBetween,
+}
+
+impl Message {
+    /// Text in the given locale
+    pub fn text(self, locale: Locale) -> &'static str {
+        self.translations()[locale as usize]
+    }
+
+    /// Translations in `Locale` order. The exhaustive match and the array
+    /// length make a missing key or language a compile error.
+    /// `&` marks access keys, `{name}` placeholders are filled in by `tr_args`.
+    fn translations(self) -> [&'static str; Locale::COUNT] {
+        match self {
+            Message::DefaultTooltip => [
+                "X: {x}  Y: {y}\n{monitor} ({monitor_index}): {monitor_x}, {monitor_y}\nScale: {scale}  Color: {color}",
+                "X: {x}  Y: {y}\n{monitor} ({monitor_index}): {monitor_x}, {monitor_y}\nSkalierung: {scale}  Farbe: {color}",
+            ],
+            Message::MenuProfile => ["Profile", "Profil"],
+            Message::MenuTheme => ["Theme", "Design"],
+            Message::MenuDisplayMode => ["Display Mode", "Anzeigemodus"],
+            Message::MenuSetOrigin => ["Set &Origin Here", "&Ursprung hier setzen"],
+            Message::RelativeTo => ["Relative to {origin}: {x}, {y}", "Relativ zu {origin}: {x}, {y}"],
+            Message::WindowPosition => ["\"{title}\" ({class}): {x}, {y}", "\"{title}\" ({class}): {x}, {y}"],
+            Message::PhysicalPosition => ["Position: {x}, {y}", "Position: {x}, {y}"],
+            Message::CalibratedPosition => ["Calibration \"{name}\": {x}, {y}", "Kalibrierung \"{name}\": {x}, {y}"],
+            Message::ReferencePosition => ["{width}x{height}: {x}, {y}", "{width}x{height}: {x}, {y}"],
+            Message::ReferenceOutside => [
+                "{width}x{height}: {x}, {y} (outside the image)",
+                "{width}x{height}: {x}, {y} (außerhalb des Bildes)",
+            ],
+            Message::ColorValues => [
+                "Color {hex}  RGB {r}, {g}, {b}\nHSL {hue}°, {saturation}%, {lightness}%",
+                "Farbe {hex}  RGB {r}, {g}, {b}\nHSL {hue}°, {saturation}%, {lightness}%",
+            ],
+            Message::MenuCalibration => ["Calibration", "Kalibrierung"],
+            Message::MenuNewCalibration => ["&New Calibration...", "&Neue Kalibrierung..."],
+            Message::CalibrationTitle => ["Calibration", "Kalibrierung"],
+            Message::CalibrationName => ["&Name of the calibration:", "&Name der Kalibrierung:"],
+            Message::CalibrationInstructions => [
+                "Click the reference points one after another and enter the coordinates each of them should have. 3 points are enough for shifted, rotated or scaled coordinates, 4 or more also correct perspective distortion. Right-click to cancel.",
+                "Klicken Sie nacheinander auf die Referenzpunkte und geben Sie jeweils die Koordinaten ein, die der Punkt haben soll. 3 Punkte genügen für verschobene, gedrehte oder skalierte Koordinaten, ab 4 Punkten wird auch perspektivische Verzerrung ausgeglichen. Rechtsklick bricht ab.",
+            ],
+            Message::CalibrationClickPoint => [
+                "Calibration: click reference point {number}, right-click to cancel",
+                "Kalibrierung: Referenzpunkt {number} anklicken, Rechtsklick bricht ab",
+            ],
+            Message::CalibrationTarget => [
+                "&Coordinates of point {number} at {x}, {y} (e.g. 10, 20.5):",
+                "&Koordinaten von Punkt {number} bei {x}, {y} (z. B. 10, 20.5):",
+            ],
+            Message::CalibrationInvalidTarget => [
+                "Enter two numbers separated by a comma, with a dot as decimal separator, e.g. 10, 20.5.",
+                "Geben Sie zwei durch Komma getrennte Zahlen mit Punkt als Dezimaltrennzeichen ein, z. B. 10, 20.5.",
+            ],
+            Message::CalibrationAddPoint => [
+                "{count} reference points entered. Add another one? More points average out inaccurate clicks.",
+                "{count} Referenzpunkte eingegeben. Einen weiteren hinzufügen? Mehr Punkte gleichen ungenaue Klicks aus.",
+            ],
+            Message::CalibrationSaved => [
+                "Calibration \"{name}\" saved. Average deviation at the reference points: {error}.",
+                "Kalibrierung \"{name}\" gespeichert. Mittlere Abweichung an den Referenzpunkten: {error}.",
+            ],
+            Message::CalibrationFailed => ["The calibration could not be computed: {error}", "Die Kalibrierung konnte nicht berechnet werden: {error}"],
+            Message::LogicalPosition => [
+                "Logical: {x}, {y}  Physical: {physical_x}, {physical_y}",
+                "Logisch: {x}, {y}  Physisch: {physical_x}, {physical_y}",
+            ],
+            Message::MenuCopyPosition => ["&Copy Position", "Position &kopieren"],
+            Message::MenuCopyFormat => ["Copy &Format", "Kopier&format"],
+            Message::MenuFreeze => ["Free&ze Display", "Anzeige &einfrieren"],
+            Message::Frozen => ["Frozen", "Eingefroren"],
+            Message::MenuCapturePoint => ["Capture &Point", "&Punkt erfassen"],
+            Message::MenuRecentPoints => ["&Recent Points", "&Letzte Punkte"],
+            Message::MenuMeasure => ["&Measure Distance", "Abstand &messen"],
+            Message::MenuMeasureRectangle => ["Measure Rec&tangle", "Rech&teck messen"],
+            Message::MenuCopyMeasurement => ["Copy Mea&surement", "Messun&g kopieren"],
+            Message::MenuRectangleFormat => ["Rectangle Form&at", "Rechteckfo&rmat"],
+            Message::MeasuringFrom => ["Measuring from {x}, {y}", "Messung ab {x}, {y}"],
+            Message::Measured => ["Measured {x1}, {y1} to {x2}, {y2}", "Gemessen von {x1}, {y1} bis {x2}, {y2}"],
+            Message::MeasurementValues => [
+                "dx {dx}  dy {dy}  Distance {distance}  Angle {angle}°",
+                "dx {dx}  dy {dy}  Abstand {distance}  Winkel {angle}°",
+            ],
+            Message::RectangleValues => [
+                "{width} x {height}  Area {area}  Aspect {aspect}",
+                "{width} x {height}  Fläche {area}  Seitenverhältnis {aspect}",
+            ],
+            Message::NoPoints => ["(none)", "(keine)"],
+            Message::MenuCopyPoint => ["&Copy", "&Kopieren"],
+            Message::MenuEditPointLabel => ["&Label...", "&Beschriftung..."],
+            Message::MenuDeletePoint => ["&Delete", "&Löschen"],
+            Message::MenuExportPoints => ["&Export...", "&Exportieren..."],
+            Message::MenuClearPoints => ["Clear &All", "&Alle löschen"],
+            Message::PointLabelTitle => ["Point Label", "Punktbeschriftung"],
+            Message::PointLabel => ["&Label for {x}, {y}:", "&Beschriftung für {x}, {y}:"],
+            Message::CsvFiles => ["CSV (*.csv)", "CSV (*.csv)"],
+            Message::JsonFiles => ["JSON (*.json)", "JSON (*.json)"],
+            Message::MarkdownFiles => ["Markdown Table (*.md)", "Markdown-Tabelle (*.md)"],
+            Message::MenuSettings => ["Settings...", "Einstellungen..."],
+            Message::MenuExit => ["Exit", "Beenden"],
+            Message::SettingsTitle => ["Settings", "Einstellungen"],
+            Message::GroupAppearance => ["Appearance", "Darstellung"],
+            Message::GroupDisplay => ["Display", "Anzeige"],
+            Message::GroupGeneral => ["General", "Allgemein"],
+            Message::GroupHotkeys => ["Hotkeys", "Tastenkürzel"],
+            Message::FieldTheme => ["&Theme:", "&Design:"],
+            Message::FieldTextColor => ["Text &Color:", "&Textfarbe:"],
+            Message::FieldBackgroundColor => ["&Background Color:", "&Hintergrundfarbe:"],
+            Message::FieldDisplayMode => ["&Display Mode:", "A&nzeigemodus:"],
+            Message::FieldNumberFormat => ["&Number Format:", "&Zahlenformat:"],
+            Message::FieldUnit => ["&Unit:", "&Einheit:"],
+            Message::FieldInterval => ["Update Inter&val (ms):", "Akt&ualisierungsintervall (ms):"],
+            Message::FieldLanguage => ["&Language:", "Sp&rache:"],
+            Message::FieldTooltip => ["T&ooltip:", "T&ooltip:"],
+            Message::FieldCopyFormat => ["Copy &Format:", "Kopier&format:"],
+            Message::FieldMeasureFormat => ["&Measure Format:", "&Messformat:"],
+            Message::FieldRectangleFormat => ["Rectan&gle Format:", "Re&chteckformat:"],
+            Message::FieldOriginX => ["Origin &X:", "Ursprung &X:"],
+            Message::FieldOriginY => ["Origin &Y:", "Ursprung &Y:"],
+            Message::HotkeyCopyPosition => ["Copy Position:", "Position kopieren:"],
+            Message::HotkeyFreeze => ["Freeze Display:", "Anzeige einfrieren:"],
+            Message::HotkeyCapturePoint => ["Capture Point:", "Punkt erfassen:"],
+            Message::HotkeySetOrigin => ["Set Origin:", "Ursprung setzen:"],
+            Message::HotkeyNextDisplayMode => ["Next Display Mode:", "Nächster Anzeigemodus:"],
+            Message::HotkeyMeasure => ["Measure Distance:", "Abstand messen:"],
+            Message::HotkeyMeasureRectangle => ["Measure Rectangle:", "Rechteck messen:"],
+            Message::HotkeyOpenSettings => ["Open Settings:", "Einstellungen öffnen:"],
+            Message::LanguageSystem => ["System language", "Systemsprache"],
+            Message::LanguageEnglish => ["English", "English"],
+            Message::LanguageGerman => ["Deutsch", "Deutsch"],
+            Message::ThemeCustom => ["Custom", "Benutzerdefiniert"],
+            Message::ThemeClassic => ["Classic", "Klassisch"],
+            Message::ThemeHighContrast => ["High Contrast", "Hoher Kontrast"],
+            Message::ThemeSolarizedDark => ["Solarized Dark", "Solarized Dunkel"],
+            Message::ThemeSolarizedLight => ["Solarized Light", "Solarized Hell"],
+            Message::ThemeTaskbar => ["Match Taskbar", "Wie Taskleiste"],
+            Message::ThemeAuto => ["Auto (Light/Dark)", "Automatisch (Hell/Dunkel)"],
+            Message::DisplayAbsolute => ["Screen coordinates", "Bildschirmkoordinaten"],
+            Message::DisplayRelative => ["Relative to origin", "Relativ zum Ursprung"],
+            Message::DisplayMonitor => ["Relative to monitor", "Relativ zum Monitor"],
+            Message::DisplayLogical => ["Logical (DPI-scaled)", "Logisch (DPI-skaliert)"],
+            Message::DisplayClient => ["Relative to window content", "Relativ zum Fensterinhalt"],
+            Message::DisplayWindow => ["Relative to window frame", "Relativ zum Fensterrahmen"],
+            Message::DisplayCalibrated => ["Calibrated", "Kalibriert"],
+            Message::DisplayReference => ["Reference resolution", "Referenzauflösung"],
+            Message::DisplayColor => ["Pixel color", "Pixelfarbe"],
+            Message::NumberZeroPadded => ["Leading zeros (0042)", "Führende Nullen (0042)"],
+            Message::NumberSpacePadded => ["No leading zeros (42)", "Ohne führende Nullen (42)"],
+            Message::UnitPixels => ["Pixels", "Pixel"],
+            Message::UnitMillimeters => ["Millimeters (mm)", "Millimeter (mm)"],
+            Message::UnitInches => ["Inches (in)", "Zoll (in)"],
+            Message::UnitPoints => ["Points (pt, 1/72 in)", "Punkt (pt, 1/72 Zoll)"],
+            Message::PreviewDescription => [
+                "Preview of the tray icon with the changed settings. Changes take effect when applied.",
+                "Vorschau des Tray-Icons mit den geänderten Einstellungen. Änderungen werden beim Übernehmen wirksam.",
+            ],
+            Message::UseSuggestedColor => ["Use &Suggested Color", "&Vorgeschlagene Farbe verwenden"],
+            Message::CustomColors => ["Custom Colors:", "Eigene Farben:"],
+            Message::ImportPalette => ["&Import...", "&Importieren..."],
+            Message::ExportPalette => ["&Export...", "Ex&portieren..."],
+            Message::ResetToDefaults => ["&Reset to Defaults", "&Standardwerte"],
+            Message::Apply => ["&Apply", "Ü&bernehmen"],
+            Message::Ok => ["OK", "OK"],
+            Message::Cancel => ["Cancel", "Abbrechen"],
+            Message::GimpPaletteFiles => ["GIMP Palette (*.gpl)", "GIMP-Palette (*.gpl)"],
+            Message::JsonPaletteFiles => ["JSON Palette (*.json)", "JSON-Palette (*.json)"],
+            Message::LowContrast => [
+                "Low contrast ({ratio}:1) makes the icon hard to read. Suggested text color: {color}",
+                "Geringer Kontrast ({ratio}:1) macht das Icon schwer lesbar. Vorgeschlagene Textfarbe: {color}",
+            ],
+            Message::SettingLocked => ["{name} is locked by the administrator", "{name} wurde vom Administrator gesperrt"],
+            Message::MustBeColor => ["{name} must be a color", "{name} muss eine Farbe sein"],
+            Message::MustBeOneOf => ["{name} must be one of: {values}", "{name} muss einer dieser Werte sein: {values}"],
+            Message::MustBeBetween => ["{name} must be between {min} and {max}", "{name} muss zwischen {min} und {max} liegen"],
+        }
+    }
+}
+
+/// Locale of the user interface, set at startup and when the language setting changes
+static mut LOCALE: Locale = Locale::English;
+
+pub unsafe fn set_locale(locale: Locale) {
+    LOCALE = locale;
+}
+
+/// Text of `message` in the current locale
+pub fn tr(message: Message) -> &'static str {
+    message.text(unsafe { LOCALE })
+}
+
+/// Text of `message` in the current locale with its `{name}` placeholders filled
+/// in. Values are inserted in a single pass, so braces in them stay as they are.
+pub fn tr_args(message: Message, args: &[(&str, &str)]) -> String {
+    let mut text = String::new();
+    let mut rest = tr(message);
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let arg = after.find('}').and_then(|end| {
+            let (_, value) = args.iter().find(|(name, _)| *name == &after[..end])?;
+            Some((end, value))
+        });
+        match arg {
+            Some((end, value)) => {
+                text.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::schema::FIELDS;
+
+    const LOCALES: [Locale; Locale::COUNT] = [Locale::English, Locale::German];
+
+    /// Names of the `{name}` placeholders in `text`
+    fn placeholders(text: &str) -> BTreeSet<&str> {
+        text.split('{').skip(1).filter_map(|part| part.split_once('}')).map(|(name, _)| name).collect()
+    }
+
+    /// Lowercase access key marked with `&`, ignoring `&&`
+    fn access_key(text: &str) -> Option<char> {
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '&' {
+                match chars.next() {
+                    Some('&') => continue,
+                    key => return key.map(|key| key.to_lowercase().next().unwrap_or(key)),
+                }
+            }
+        }
+        None
+    }
+
+    fn assert_unique_access_keys(window: &str, messages: &[Message]) {
+        for locale in LOCALES {
+            let mut seen = Vec::new();
+            for &message in messages {
+                let text = message.text(locale);
+                if let Some(key) = access_key(text) {
+                    if let Some((other, _)) = seen.iter().find(|(_, seen_key)| *seen_key == key) {
+                        panic!("{} ({:?}): \"{}\" and \"{}\" share the access key {}", window, locale, other, text, key);
+                    }
+                    seen.push((text, key));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn translations_use_the_same_placeholders() {
+        for &message in Message::ALL {
+            let english = placeholders(message.text(Locale::English));
+            for locale in LOCALES {
+                assert_eq!(placeholders(message.text(locale)), english, "{:?} in {:?}", message, locale);
+            }
+        }
+    }
+
+    #[test]
+    fn settings_window_access_keys_are_unique() {
+        let mut messages: Vec<Message> = FIELDS.iter().map(|field| field.label).collect();
+        messages.extend([
+            Message::UseSuggestedColor,
+            Message::ImportPalette,
+            Message::ExportPalette,
+            Message::ResetToDefaults,
+            Message::Apply,
+            Message::Cancel,
+        ]);
+        assert_unique_access_keys("settings window", &messages);
+    }
+
+    #[test]
+    fn tray_menu_access_keys_are_unique() {
+        assert_unique_access_keys(
+            "tray menu",
+            &[
+                Message::MenuProfile,
+                Message::MenuTheme,
+                Message::MenuDisplayMode,
+                Message::MenuSetOrigin,
+                Message::MenuCalibration,
+                Message::MenuFreeze,
+                Message::MenuCopyPosition,
+                Message::MenuCopyFormat,
+                Message::MenuCapturePoint,
+                Message::MenuRecentPoints,
+                Message::MenuMeasure,
+                Message::MenuMeasureRectangle,
+                Message::MenuCopyMeasurement,
+                Message::MenuRectangleFormat,
+                Message::MenuSettings,
+                Message::MenuExit,
+            ],
+        );
+    }
+
+    #[test]
+    fn points_menu_access_keys_are_unique() {
+        assert_unique_access_keys(
+            "points menu",
+            &[Message::MenuCopyPoint, Message::MenuEditPointLabel, Message::MenuDeletePoint, Message::MenuExportPoints, Message::MenuClearPoints],
+        );
+    }
+
+    #[test]
+    fn arguments_are_filled_in_once() {
+        let text = tr_args(Message::MustBeBetween, &[("name", "{min} {max}"), ("min", "1"), ("max", "{min}")]);
+        assert_eq!(text, "{min} {max} must be between 1 and {min}");
+    }
+
+    #[test]
+    fn placeholders_without_arguments_are_kept() {
+        assert_eq!(tr_args(Message::MustBeOneOf, &[("name", "Theme")]), "Theme must be one of: {values}");
+    }
+
+    #[test]
+    fn access_keys_skip_escaped_ampersands() {
+        assert_eq!(access_key("Save && &Exit"), Some('e'));
+        assert_eq!(access_key("Tom && Jerry"), None);
+    }
+}
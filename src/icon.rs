@@ -0,0 +1,189 @@
+// Pixel layout of the tray icon, independent of the drawing API
+
+use crate::config::NumberFormat;
+use crate::pipeline::DisplayedPosition;
+use crate::units;
+
+/// Width and height of the tray icon in pixels
+pub const ICON_SIZE: i32 = 24;
+
+/// Distance of the color swatch from the icon edges; the frame drawn by
+/// `render_swatch` runs just outside it
+pub const SWATCH_INSET: i32 = 2;
+
+/// A 5x7 pixel bitmap font for digits 0-9
+const FONT: [[[u8; 5]; 7]; 10] = [
+    // 0
+    [[0,1,1,1,0], [1,0,0,0,1], [1,0,0,0,1], [1,0,0,0,1], [1,0,0,0,1], [1,0,0,0,1], [0,1,1,1,0]],
+    // 1
+    [[0,0,1,0,0], [0,1,1,0,0], [0,0,1,0,0], [0,0,1,0,0], [0,0,1,0,0], [0,0,1,0,0], [0,1,1,1,0]],
+    // 2
+    [[0,1,1,1,0], [1,0,0,0,1], [0,0,0,0,1], [0,0,1,1,0], [0,1,0,0,0], [1,0,0,0,0], [1,1,1,1,1]],
+    // 3
+    [[1,1,1,1,0], [0,0,0,0,1], [0,0,0,1,0], [0,0,1,1,0], [0,0,0,0,1], [1,0,0,0,1], [0,1,1,1,0]],
+    // 4
+    [[0,0,0,1,0], [0,0,1,1,0], [0,1,0,1,0], [1,0,0,1,0], [1,1,1,1,1], [0,0,0,1,0], [0,0,0,1,0]],
+    // 5
+    [[1,1,1,1,1], [1,0,0,0,0], [1,1,1,1,0], [0,0,0,0,1], [0,0,0,0,1], [1,0,0,0,1], [0,1,1,1,0]],
+    // 6
+    [[0,0,1,1,0], [0,1,0,0,0], [1,0,0,0,0], [1,1,1,1,0], [1,0,0,0,1], [1,0,0,0,1], [0,1,1,1,0]],
+    // 7
+    [[1,1,1,1,1], [0,0,0,0,1], [0,0,0,1,0], [0,0,1,0,0], [0,0,1,0,0], [0,0,1,0,0], [0,0,1,0,0]],
+    // 8
+    [[0,1,1,1,0], [1,0,0,0,1], [1,0,0,0,1], [0,1,1,1,0], [1,0,0,0,1], [1,0,0,0,1], [0,1,1,1,0]],
+    // 9
+    [[0,1,1,1,0], [1,0,0,0,1], [1,0,0,0,1], [0,1,1,1,1], [0,0,0,0,1], [0,0,1,0,0], [0,1,1,0,0]],
+];
+
+/// Pixels of the icon that are drawn in the text color
+#[derive(Clone, PartialEq, Eq)]
+pub struct IconMask {
+    pixels: [[bool; ICON_SIZE as usize]; ICON_SIZE as usize],
+}
+
+impl IconMask {
+    fn new() -> IconMask {
+        IconMask { pixels: [[false; ICON_SIZE as usize]; ICON_SIZE as usize] }
+    }
+
+    fn set(&mut self, x: i32, y: i32) {
+        if (0..ICON_SIZE).contains(&x) && (0..ICON_SIZE).contains(&y) {
+            self.pixels[y as usize][x as usize] = true;
+        }
+    }
+
+    /// Adds lines along the top and bottom edge, shown while the display is frozen
+    pub fn mark_frozen(&mut self) {
+        for x in 0..ICON_SIZE {
+            self.set(x, 0);
+            self.set(x, ICON_SIZE - 1);
+        }
+    }
+
+    /// Adds one dot per monitor number between the two lines, up to eight
+    pub fn mark_monitor_index(&mut self, index: usize) {
+        for i in 0..index.min(8) as i32 {
+            let x = 1 + i * 3;
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                self.set(x + dx, 11 + dy);
+            }
+        }
+    }
+
+    /// Adds a ruler with end ticks between the two lines, shown while measuring;
+    /// the lines then show dx and dy
+    pub fn mark_measuring(&mut self) {
+        for x in 1..ICON_SIZE - 1 {
+            self.set(x, 11);
+        }
+        for y in 10..=12 {
+            self.set(1, y);
+            self.set(ICON_SIZE - 2, y);
+        }
+    }
+
+    /// Adds a flat box between the two lines, shown while measuring a
+    /// rectangle; the lines then show its width and height
+    pub fn mark_rectangle(&mut self) {
+        for x in 1..ICON_SIZE - 1 {
+            self.set(x, 10);
+            self.set(x, 12);
+        }
+        self.set(1, 11);
+        self.set(ICON_SIZE - 2, 11);
+    }
+
+    /// Coordinates of all set pixels, row by row
+    pub fn points(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.pixels.iter().enumerate().flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, &pixel)| pixel)
+                .map(move |(x, _)| (x as i32, y as i32))
+        })
+    }
+}
+
+/// Minus sign in the same 5x7 grid as the digits
+const MINUS: [[u8; 5]; 7] = [[0,0,0,0,0], [0,0,0,0,0], [0,0,0,0,0], [1,1,1,1,1], [0,0,0,0,0], [0,0,0,0,0], [0,0,0,0,0]];
+const DECIMAL_POINT: [[u8; 5]; 7] = [[0,0,0,0,0], [0,0,0,0,0], [0,0,0,0,0], [0,0,0,0,0], [0,0,0,0,0], [0,1,1,0,0], [0,1,1,0,0]];
+
+/// Icon pixels framing the color swatch of the color display mode, which is
+/// filled in separately
+pub fn render_swatch() -> IconMask {
+    let mut mask = IconMask::new();
+    let (first, last) = (SWATCH_INSET - 1, ICON_SIZE - SWATCH_INSET);
+    for i in first..=last {
+        mask.set(i, first);
+        mask.set(i, last);
+        mask.set(first, i);
+        mask.set(last, i);
+    }
+    mask
+}
+
+/// Icon pixels for a position shown by the coordinate pipeline
+pub fn render_position(position: &DisplayedPosition, number_format: NumberFormat) -> IconMask {
+    let mut mask = render_coordinates(position.x, position.y, position.decimals, number_format);
+    if let Some(index) = position.monitor_index {
+        mask.mark_monitor_index(index);
+    }
+    mask
+}
+
+/// Lays out X on the top line and Y on the bottom line, four characters each.
+/// Larger numbers show their last four digits; negative numbers take one
+/// place for the minus sign and show their last three digits.
+fn render_coordinates(x_pos: i32, y_pos: i32, decimals: u32, number_format: NumberFormat) -> IconMask {
+    let mut mask = IconMask::new();
+    let y_positions = [3, 14];
+
+    for (row_idx, number) in [x_pos, y_pos].into_iter().enumerate() {
+        let start_y = y_positions[row_idx];
+        for (i, c) in icon_text(number, decimals, number_format).chars().enumerate() {
+            let glyph = match c {
+                '-' => MINUS,
+                '.' => DECIMAL_POINT,
+                c => match c.to_digit(10) {
+                    Some(digit) => FONT[digit as usize],
+                    None => continue,
+                },
+            };
+            let start_x = 1 + i as i32 * 6;
+            for (y, row) in glyph.iter().enumerate() {
+                for (x, &pixel) in row.iter().enumerate() {
+                    if pixel == 1 {
+                        mask.set(start_x + x as i32, start_y + y as i32);
+                    }
+                }
+            }
+        }
+    }
+    mask
+}
+
+/// Four characters for one line of the icon, e.g. `0042`, `  42`, `-042` or
+/// ` -42`; values with decimals like ` 4.5` drop as many of them as needed
+fn icon_text(number: i32, decimals: u32, number_format: NumberFormat) -> String {
+    let rounded = |dropped: u32| (number as f64 / 10f64.powi(dropped as i32)).round() as i32;
+    for shown in (1..=decimals).rev() {
+        let text = units::format_fixed(rounded(decimals - shown), shown);
+        if text.len() <= 4 {
+            return format!("{:>4}", text);
+        }
+    }
+    let number = rounded(decimals);
+    let text = if number < 0 {
+        format!("-{:0width$}", number.unsigned_abs() % 1000, width = zero_width(number_format, 3))
+    } else {
+        format!("{:0width$}", number % 10000, width = zero_width(number_format, 4))
+    };
+    format!("{:>4}", text)
+}
+
+fn zero_width(number_format: NumberFormat, digits: usize) -> usize {
+    match number_format {
+        NumberFormat::ZeroPadded => digits,
+        NumberFormat::SpacePadded => 1,
+    }
+}
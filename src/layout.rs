@@ -0,0 +1,276 @@
+// Virtual desktop layout: monitor rectangles, work areas, DPI and physical
+// sizes, independent of the system API they are queried with
+
+use std::collections::BTreeMap;
+
+use crate::config::MonitorSize;
+
+/// Rectangle in virtual-desktop coordinates; `right` and `bottom` are exclusive
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Rect {
+    pub fn width(&self) -> i32 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> i32 {
+        self.bottom - self.top
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (self.left..self.right).contains(&x) && (self.top..self.bottom).contains(&y)
+    }
+
+    /// Squared distance from `(x, y)` to the nearest point of the rectangle, 0 inside
+    fn distance_squared(&self, x: i32, y: i32) -> i64 {
+        let dx = (self.left as i64 - x as i64).max(x as i64 - (self.right as i64 - 1)).max(0);
+        let dy = (self.top as i64 - y as i64).max(y as i64 - (self.bottom as i64 - 1)).max(0);
+        dx * dx + dy * dy
+    }
+}
+
+/// One monitor of the desktop
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Monitor {
+    pub name: String,
+    pub rect: Rect,
+    /// Part of `rect` not covered by the taskbar and docked toolbars
+    pub work_area: Rect,
+    pub dpi: u32,
+    /// Width and height of the visible image in millimetres, from the EDID or
+    /// the configuration
+    pub size_mm: Option<(u32, u32)>,
+    /// Preferred resolution from the EDID
+    pub native_resolution: Option<(u32, u32)>,
+}
+
+impl Monitor {
+    /// Width and height of one pixel in millimetres at the current resolution
+    pub fn pixel_size_mm(&self) -> Option<(f64, f64)> {
+        let (width_mm, height_mm) = self.size_mm?;
+        if self.rect.width() <= 0 || self.rect.height() <= 0 {
+            return None;
+        }
+        Some((width_mm as f64 / self.rect.width() as f64, height_mm as f64 / self.rect.height() as f64))
+    }
+}
+
+/// All monitors in the system's enumeration order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DesktopLayout {
+    pub monitors: Vec<Monitor>,
+}
+
+/// A position described relative to the monitor it is on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MonitorPosition<'a> {
+    /// 1-based position of the monitor in the enumeration order
+    pub index: usize,
+    pub monitor: &'a Monitor,
+    /// Offset from the top left corner of the monitor
+    pub x: i32,
+    pub y: i32,
+    /// Offset from the top left corner of the work area; negative over a
+    /// taskbar at the top or left edge
+    pub work_x: i32,
+    pub work_y: i32,
+}
+
+impl DesktopLayout {
+    /// Index of the monitor containing `(x, y)`, or of the nearest one for
+    /// positions in gaps between monitors
+    pub fn monitor_index_at(&self, x: i32, y: i32) -> Option<usize> {
+        self.monitors
+            .iter()
+            .position(|monitor| monitor.rect.contains(x, y))
+            .or_else(|| {
+                self.monitors
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, monitor)| monitor.rect.distance_squared(x, y))
+                    .map(|(index, _)| index)
+            })
+    }
+
+    /// Replaces the physical sizes of the monitors configured by 1-based number
+    /// or by name; numbers take precedence
+    pub fn apply_size_overrides(&mut self, sizes: &BTreeMap<String, MonitorSize>) {
+        for (index, monitor) in self.monitors.iter_mut().enumerate() {
+            let size = sizes.get(&(index + 1).to_string()).or_else(|| sizes.get(&monitor.name));
+            if let Some(size) = size.filter(|size| size.width_mm > 0 && size.height_mm > 0) {
+                monitor.size_mm = Some((size.width_mm, size.height_mm));
+            }
+        }
+    }
+
+    /// `(x, y)` relative to the monitor it is on; `None` without monitors
+    pub fn locate(&self, x: i32, y: i32) -> Option<MonitorPosition<'_>> {
+        let index = self.monitor_index_at(x, y)?;
+        let monitor = &self.monitors[index];
+        Some(MonitorPosition {
+            index: index + 1,
+            monitor,
+            x: x - monitor.rect.left,
+            y: y - monitor.rect.top,
+            work_x: x - monitor.work_area.left,
+            work_y: y - monitor.work_area.top,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> Rect {
+        Rect { left, top, right, bottom }
+    }
+
+    fn monitor(name: &str, rect: Rect, work_area: Rect) -> Monitor {
+        Monitor { name: name.to_string(), rect, work_area, dpi: 96, ..Default::default() }
+    }
+
+    /// Primary monitor with the taskbar at the bottom, one to its left with
+    /// the taskbar on the left edge, lower down, and one above the primary
+    /// with the taskbar at the top
+    fn layout() -> DesktopLayout {
+        DesktopLayout {
+            monitors: vec![
+                monitor("Primary", rect(0, 0, 1920, 1080), rect(0, 0, 1920, 1040)),
+                monitor("Left", rect(-1280, 200, 0, 1224), rect(-1220, 200, 0, 1224)),
+                monitor("Top", rect(0, -1080, 1920, 0), rect(0, -1040, 1920, 0)),
+            ],
+        }
+    }
+
+    #[test]
+    fn positions_on_a_monitor_find_it() {
+        let layout = layout();
+        assert_eq!(layout.monitor_index_at(0, 0), Some(0));
+        assert_eq!(layout.monitor_index_at(1919, 1079), Some(0));
+        assert_eq!(layout.monitor_index_at(-1, 200), Some(1));
+        assert_eq!(layout.monitor_index_at(500, -1), Some(2));
+    }
+
+    #[test]
+    fn positions_in_gaps_find_the_nearest_monitor() {
+        let layout = layout();
+        // Above the left monitor: 10 px from the primary, 100 px from the left one
+        assert_eq!(layout.monitor_index_at(-10, 100), Some(0));
+        // Further left the left monitor is nearer
+        assert_eq!(layout.monitor_index_at(-600, 100), Some(1));
+        // Past the right edges
+        assert_eq!(layout.monitor_index_at(1920, 500), Some(0));
+        assert_eq!(layout.monitor_index_at(2500, -500), Some(2));
+    }
+
+    #[test]
+    fn empty_layouts_locate_nothing() {
+        assert_eq!(DesktopLayout::default().monitor_index_at(0, 0), None);
+        assert_eq!(DesktopLayout::default().locate(0, 0), None);
+    }
+
+    #[test]
+    fn locate_gives_offsets_from_the_monitor_and_work_area() {
+        let layout = layout();
+        let position = layout.locate(100, 50).unwrap();
+        assert_eq!((position.index, position.monitor.name.as_str()), (1, "Primary"));
+        assert_eq!((position.x, position.y, position.work_x, position.work_y), (100, 50, 100, 50));
+
+        let position = layout.locate(-1270, 300).unwrap();
+        assert_eq!(position.index, 2);
+        assert_eq!((position.x, position.y), (10, 100));
+        // Over the taskbar on the left edge
+        assert_eq!((position.work_x, position.work_y), (-50, 100));
+
+        let position = layout.locate(100, -1060).unwrap();
+        assert_eq!(position.index, 3);
+        assert_eq!((position.x, position.y), (100, 20));
+        // Over the taskbar at the top edge
+        assert_eq!((position.work_x, position.work_y), (100, -20));
+    }
+
+    #[test]
+    fn locate_uses_the_nearest_monitor_in_gaps() {
+        let layout = layout();
+        let position = layout.locate(-600, 100).unwrap();
+        assert_eq!(position.index, 2);
+        assert_eq!((position.x, position.y), (680, -100));
+    }
+
+    #[test]
+    fn size_overrides_prefer_numbers_to_names() {
+        let mut layout = layout();
+        layout.monitors[0].size_mm = Some((600, 340));
+        let sizes = BTreeMap::from([
+            ("1".to_string(), MonitorSize { width_mm: 527, height_mm: 296 }),
+            ("Primary".to_string(), MonitorSize { width_mm: 1, height_mm: 1 }),
+            ("Left".to_string(), MonitorSize { width_mm: 376, height_mm: 301 }),
+            // Incomplete sizes are ignored
+            ("3".to_string(), MonitorSize { width_mm: 500, height_mm: 0 }),
+        ]);
+        layout.apply_size_overrides(&sizes);
+        assert_eq!(layout.monitors[0].size_mm, Some((527, 296)));
+        assert_eq!(layout.monitors[1].size_mm, Some((376, 301)));
+        assert_eq!(layout.monitors[2].size_mm, None);
+    }
+
+    #[test]
+    fn pixel_size_needs_a_physical_size() {
+        let mut layout = layout();
+        assert_eq!(layout.monitors[0].pixel_size_mm(), None);
+        layout.monitors[0].size_mm = Some((480, 270));
+        assert_eq!(layout.monitors[0].pixel_size_mm(), Some((0.25, 0.25)));
+    }
+
+    /// 1920 x 1080 primary at 96 DPI and a 3840 x 2160 monitor at 144 DPI to
+    /// its right, aligned at the top
+    fn mixed_dpi_layout() -> DesktopLayout {
+        let high = rect(1920, 0, 5760, 2160);
+        DesktopLayout {
+            monitors: vec![
+                monitor("Primary", rect(0, 0, 1920, 1080), rect(0, 0, 1920, 1040)),
+                Monitor { dpi: 144, ..monitor("4K", high, rect(1920, 0, 5760, 2100)) },
+            ],
+        }
+    }
+
+    #[test]
+    fn mixed_dpi_monitors_meet_at_a_seam() {
+        let layout = mixed_dpi_layout();
+        let left = layout.locate(1919, 500).unwrap();
+        assert_eq!((left.index, left.monitor.dpi, left.x), (1, 96, 1919));
+        let right = layout.locate(1920, 500).unwrap();
+        assert_eq!((right.index, right.monitor.dpi, right.x, right.y), (2, 144, 0, 500));
+        // Below the primary only the high-DPI monitor continues
+        let below = layout.locate(2000, 2000).unwrap();
+        assert_eq!((below.index, below.x, below.y), (2, 80, 2000));
+    }
+
+    #[test]
+    fn gaps_below_the_smaller_monitor_use_the_nearest_one() {
+        let layout = mixed_dpi_layout();
+        // 421 px below the primary, 920 px left of the 4K monitor
+        assert_eq!(layout.monitor_index_at(1000, 1500), Some(0));
+        // 421 px below the primary, 20 px left of the 4K monitor
+        let position = layout.locate(1900, 1500).unwrap();
+        assert_eq!((position.index, position.monitor.dpi), (2, 144));
+        assert_eq!((position.x, position.y), (-20, 1500));
+    }
+
+    #[test]
+    fn pixel_sizes_follow_the_resolution_of_each_monitor() {
+        let mut layout = mixed_dpi_layout();
+        layout.monitors[0].size_mm = Some((480, 270));
+        layout.monitors[1].size_mm = Some((600, 340));
+        assert_eq!(layout.monitors[0].pixel_size_mm(), Some((0.25, 0.25)));
+        let (width_mm, height_mm) = layout.monitors[1].pixel_size_mm().unwrap();
+        assert!((width_mm - 0.15625).abs() < 1e-9 && (height_mm - 340.0 / 2160.0).abs() < 1e-9);
+    }
+}
@@ -1,323 +1,1604 @@
 #![windows_subsystem = "windows"]
 #![allow(static_mut_refs)] // Diese Zeile unterdrückt die Warnungen für `static mut`
 
+mod calibration;
+mod cli;
+mod color;
+mod config;
+mod copy;
+mod dpi;
+mod edid;
+mod hotkey;
+mod i18n;
+mod icon;
+mod layout;
+mod measure;
+mod palette;
+mod pipeline;
+mod points;
+mod position;
+mod prompt;
+mod rectangle;
+mod reference;
+mod schema;
+mod settings;
+mod template;
+mod theme;
+mod tooltip;
+mod units;
+mod window;
+#[cfg(target_os = "linux")]
+mod x11_backend;
+
 // Import of necessary standard libraries
+use std::cell::OnceCell;
 use std::ffi::c_void;
 use std::mem::size_of;
+use std::path::{Path, PathBuf};
 use std::ptr::null_mut;
-use std::fs;
-use std::path::PathBuf;
-use std::env;
 
 // Import of Windows-specific functions and structures
-use windows::core::w;
+use windows::core::{w, HSTRING, PCWSTR, PWSTR};
 use windows::Win32::Foundation::*;
+use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_EXTENDED_FRAME_BOUNDS};
 use windows::Win32::Graphics::Gdi::*;
+use windows::Win32::Globalization::GetUserDefaultUILanguage;
+use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+use windows::Win32::System::DataExchange::{CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData};
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
-use windows::Win32::UI::Controls::Dialogs::*;
+use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+use windows::Win32::System::Registry::{RegGetValueW, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, RRF_RT_REG_BINARY, RRF_RT_REG_DWORD};
+use windows::Win32::System::SystemInformation::GetLocalTime;
+use windows::Win32::UI::Controls::Dialogs::{GetSaveFileNameW, OFN_OVERWRITEPROMPT, OFN_PATHMUSTEXIST, OPENFILENAMEW};
+use windows::Win32::UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
+use windows::Win32::UI::Input::KeyboardAndMouse::{RegisterHotKey, UnregisterHotKey, HOT_KEY_MODIFIERS, MOD_NOREPEAT};
 use windows::Win32::UI::Shell::*;
 use windows::Win32::UI::WindowsAndMessaging::*;
 
-// Serde imports for configuration
-use serde::{Deserialize, Serialize};
+use calibration::{parse_target, ActiveCalibration, Wizard};
+use cli::Command;
+use color::{components, to_hex, to_hsl};
+use config::{resolve_config_path, Config, DisplayMode, LayeredConfig, NumberFormat, Origin, Profile, Unit, MIN_INTERVAL_MS};
+use copy::{resolve_copy_format, COPY_FORMATS, DEFAULT_COPY_FORMAT};
+use edid::{edid_registry_key, parse_edid, Edid};
+use hotkey::{Action, Hotkey};
+use i18n::{tr, tr_args, Locale, Message};
+use icon::{IconMask, ICON_SIZE};
+use layout::{DesktopLayout, Monitor, Rect};
+use measure::{Measure, MeasureState, Measurement, Shape, DEFAULT_MEASURE_FORMAT};
+use palette::{export_palette, import_palette};
+use pipeline::DisplayedPosition;
+use rectangle::{resolve_rectangle_format, DEFAULT_RECTANGLE_FORMAT, RECTANGLE_FORMATS};
+use reference::ReferenceSource;
+use points::{export_points, load_points, save_points, CapturedPoint, RECENT_POINTS};
+use schema::{Choice, Control, Field, Group, FIELDS};
+use template::Template;
+use position::{PositionDetails, COLOR_PLACEHOLDERS, REFERENCE_PLACEHOLDERS, WINDOW_PLACEHOLDERS};
+use theme::{SystemAppearance, ThemeColors};
+use window::WindowInfo;
+
+/// Timer driving the icon updates
+const UPDATE_TIMER_ID: usize = 1;
+/// One-shot timer writing the configuration after changes settled
+const SAVE_TIMER_ID: usize = 2;
+const SAVE_DELAY_MS: u32 = 500;
+
+/// Clipboard format of UTF-16 text
+const CF_UNICODETEXT: u32 = 13;
+
+/// Window class of the main window, also used to find a running instance
+const WINDOW_CLASS: PCWSTR = w!("MPR");
+
+/// Custom message ID for tray icon events
+const TRAY_MESSAGE: u32 = WM_USER + 1;
+/// Sent by `mpr freeze`: WPARAM is one of the `FREEZE_*` values, the result
+/// is 1 if the display is frozen afterwards
+const FREEZE_MESSAGE: u32 = WM_USER + 2;
+const FREEZE_TOGGLE: usize = 0;
+const FREEZE_ON: usize = 1;
+const FREEZE_OFF: usize = 2;
+/// Posted by the mouse hook of the calibration wizard: WPARAM is one of the
+/// `CALIBRATION_*` values, LPARAM the screen position as two 16-bit values
+const CALIBRATION_MESSAGE: u32 = WM_USER + 3;
+const CALIBRATION_CLICK: usize = 0;
+const CALIBRATION_CANCEL: usize = 1;
+
+/// Menu item IDs for context menu
+const MENU_ID_EXIT: u32 = 1001;
+const MENU_ID_SETTINGS: u32 = 1002;
+const MENU_ID_COPY: u32 = 1003;
+const MENU_ID_FREEZE: u32 = 1004;
+const MENU_ID_CAPTURE_POINT: u32 = 1005;
+const MENU_ID_EXPORT_POINTS: u32 = 1006;
+const MENU_ID_CLEAR_POINTS: u32 = 1007;
+const MENU_ID_SET_ORIGIN: u32 = 1008;
+const MENU_ID_NEW_CALIBRATION: u32 = 1009;
+const MENU_ID_MEASURE: u32 = 1010;
+const MENU_ID_COPY_MEASUREMENT: u32 = 1011;
+const MENU_ID_MEASURE_RECTANGLE: u32 = 1012;
+/// First menu item ID of the profile submenu, one ID per profile
+const MENU_ID_PROFILE_FIRST: u32 = 1100;
+const MENU_ID_PROFILE_LAST: u32 = 1199;
+/// First menu item ID of the theme submenu, in schema choice order
+const MENU_ID_THEME_FIRST: u32 = 1200;
+const MENU_ID_THEME_LAST: u32 = 1299;
+/// First menu item ID of the copy format submenu, in `COPY_FORMATS` order
+const MENU_ID_COPY_FORMAT_FIRST: u32 = 1300;
+const MENU_ID_COPY_FORMAT_LAST: u32 = 1399;
+/// First menu item ID of the "Recent Points" submenu, `POINT_COMMANDS` IDs per
+/// listed point, newest point first
+const MENU_ID_POINT_FIRST: u32 = 1400;
+const MENU_ID_POINT_LAST: u32 = 1499;
+const POINT_COMMANDS: u32 = 3;
+const POINT_COMMAND_COPY: u32 = 0;
+const POINT_COMMAND_LABEL: u32 = 1;
+const POINT_COMMAND_DELETE: u32 = 2;
+/// First menu item ID of the display mode submenu, in schema choice order
+const MENU_ID_DISPLAY_MODE_FIRST: u32 = 1500;
+const MENU_ID_DISPLAY_MODE_LAST: u32 = 1599;
+/// First menu item ID of the calibration submenu, one ID per calibration in name order
+const MENU_ID_CALIBRATION_FIRST: u32 = 1600;
+const MENU_ID_CALIBRATION_LAST: u32 = 1699;
+/// First menu item ID of the rectangle format submenu, in `RECTANGLE_FORMATS` order
+const MENU_ID_RECTANGLE_FORMAT_FIRST: u32 = 1700;
+const MENU_ID_RECTANGLE_FORMAT_LAST: u32 = 1799;
+
+/// Global variables
+static mut CURRENT_ICON: HICON = HICON(null_mut());
+static mut MAIN_HWND: HWND = HWND(null_mut());
+static mut CURRENT_TEXT_COLOR: COLORREF = COLORREF(0x00E6D8AD);
+static mut CURRENT_BACKGROUND_COLOR: COLORREF = COLORREF(0x00000000);
+static mut CONFIG: Option<Config> = None;
+static mut CONFIG_STORE: Option<LayeredConfig> = None;
+static mut CONFIG_DIRTY: bool = false;
+static mut SYSTEM_APPEARANCE: SystemAppearance = SystemAppearance { apps_light: false, taskbar_light: false };
+static mut TOOLTIP_TEMPLATE: Option<Template> = None;
+/// What the tray icon shows, to skip updates that would not change it
+static mut TRAY_CONTENT: Option<TrayContent> = None;
+/// Registered global hotkeys; the hotkey ID is the index in `Action::ALL` plus one
+static mut HOTKEYS: Vec<(Action, Hotkey)> = Vec::new();
+/// Last cursor position outside the taskbar, copied from the tray icon
+static mut LAST_POSITION: POINT = POINT { x: 0, y: 0 };
+/// Position shown in the tray icon
+static mut DISPLAYED_POSITION: POINT = POINT { x: 0, y: 0 };
+/// Whether the icon keeps showing `DISPLAYED_POSITION` instead of following the cursor
+static mut FROZEN: bool = false;
+/// Captured points in capture order and the file they are kept in
+static mut POINTS: Vec<CapturedPoint> = Vec::new();
+static mut POINTS_PATH: Option<PathBuf> = None;
+/// Monitor layout of the desktop, queried again when displays or their settings change
+static mut LAYOUT: Option<DesktopLayout> = None;
+/// Fitted transform of the configured calibration
+static mut CALIBRATION: Option<ActiveCalibration> = None;
+/// Running calibration wizard, and its mouse hook while it waits for a click
+static mut CALIBRATION_WIZARD: Option<Wizard> = None;
+static mut CALIBRATION_HOOK: Option<HHOOK> = None;
+/// Window last found for the configured reference window title, kept while it
+/// exists and still matches instead of enumerating all windows on every update
+static mut REFERENCE_HWND: Option<HWND> = None;
+
+/// Measure mode, advanced by the measure hotkey
+static mut MEASURE: Measure = Measure { state: MeasureState::Idle, last: None };
 
-/// Configuration structure
-#[derive(Debug, Serialize, Deserialize)]
-struct Config {
-    text_color: u32, // COLORREF as u32
-    background_color: u32, // COLORREF as u32
+/// Schedules persisting the current configuration; changes in quick
+/// succession are written to the user file once
+unsafe fn save_current_config() {
+    CONFIG_DIRTY = true;
+    if MAIN_HWND.is_invalid() || SetTimer(Some(MAIN_HWND), SAVE_TIMER_ID, SAVE_DELAY_MS, None) == 0 {
+        flush_config();
+    }
+}
+
+/// Writes pending configuration changes to the user file
+unsafe fn flush_config() {
+    if !MAIN_HWND.is_invalid() {
+        let _ = KillTimer(Some(MAIN_HWND), SAVE_TIMER_ID);
+    }
+    if !CONFIG_DIRTY {
+        return;
+    }
+    CONFIG_DIRTY = false;
+    if let (Some(config), Some(store)) = (&CONFIG, &mut CONFIG_STORE) {
+        if let Err(e) = store.save(config) {
+            eprintln!("Failed to save config: {}", e);
+        }
+    }
+}
+
+/// Whether an administrator locked the given setting in the system-wide file
+unsafe fn is_setting_locked(key: &str) -> bool {
+    CONFIG_STORE.as_ref().is_some_and(|store| store.is_locked(key))
+}
+
+/// Helper functions
+#[inline] unsafe fn get_current_icon() -> HICON { CURRENT_ICON }
+#[inline] unsafe fn set_current_icon(icon: HICON) { CURRENT_ICON = icon; }
+#[inline] unsafe fn get_current_text_color() -> COLORREF { CURRENT_TEXT_COLOR }
+#[inline] unsafe fn get_current_background_color() -> COLORREF { CURRENT_BACKGROUND_COLOR }
+
+/// Replaces the active profile with one edited in the UI, saves and applies it.
+/// Nothing changes if a locked setting differs.
+unsafe fn apply_profile(profile: Profile) -> Result<(), String> {
+    let Some(config) = &CONFIG else { return Ok(()) };
+    let mut edited = config.clone();
+    *edited.profile_mut() = profile;
+    apply_settings(edited)
+}
+
+/// Takes over the schema settings of a configuration edited in the UI, saves
+/// and applies them. Nothing changes if a locked setting differs.
+unsafe fn apply_settings(edited: Config) -> Result<(), String> {
+    let Some(config) = &mut CONFIG else { return Ok(()) };
+    let changed: Vec<&Field> = FIELDS.iter().filter(|field| field.get(config) != field.get(&edited)).collect();
+    if let Some(field) = changed.iter().find(|field| is_setting_locked(&field.config_key(config))) {
+        return Err(tr_args(Message::SettingLocked, &[("name", &field.name())]));
+    }
+    if changed.is_empty() {
+        return Ok(());
+    }
+    for field in &changed {
+        if let Some(value) = field.get(&edited) {
+            field.set(config, value)?;
+        }
+    }
+    save_current_config();
+
+    let language_changed = changed.iter().any(|field| field.key == "language");
+    if language_changed {
+        i18n::set_locale(config.language.resolve(Locale::from_language_id(GetUserDefaultUILanguage())));
+    }
+    // The built-in tooltip template depends on the language
+    if language_changed || changed.iter().any(|field| field.key == "tooltip") {
+        TOOLTIP_TEMPLATE = Some(load_tooltip_template(config));
+    }
+    if changed.iter().any(|field| field.group == Group::Hotkeys) {
+        unregister_hotkeys(MAIN_HWND);
+        register_hotkeys(MAIN_HWND);
+    }
+    let _ = apply_active_profile(MAIN_HWND);
+    Ok(())
+}
+
+/// Reads the light/dark settings of the current user from the registry
+unsafe fn query_system_appearance() -> SystemAppearance {
+    let read_flag = |name| {
+        let mut value: u32 = 0;
+        let mut size = size_of::<u32>() as u32;
+        let result = RegGetValueW(
+            HKEY_CURRENT_USER,
+            w!("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize"),
+            name,
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut value as *mut u32 as *mut c_void),
+            Some(&mut size),
+        );
+        // Windows defaults to light when the value is missing
+        result != ERROR_SUCCESS || value != 0
+    };
+
+    SystemAppearance {
+        apps_light: read_flag(w!("AppsUseLightTheme")),
+        taskbar_light: read_flag(w!("SystemUsesLightTheme")),
+    }
+}
+
+/// Colors of a profile's theme with the current system appearance
+unsafe fn resolve_profile_colors(profile: &Profile) -> ThemeColors {
+    let custom = ThemeColors { text: profile.text_color, background: profile.background_color };
+    theme::resolve(profile.theme, custom, SYSTEM_APPEARANCE)
+}
+
+/// Current number padding of the tray icon
+unsafe fn get_current_number_format() -> NumberFormat {
+    CONFIG.as_ref().map(|config| config.profile().number_format).unwrap_or_default()
+}
+
+/// Applies the active profile: colors and update interval.
+/// Returns `false` if the update timer could not be (re)started.
+unsafe fn apply_active_profile(hwnd: HWND) -> bool {
+    let Some(config) = &CONFIG else { return false };
+    let profile = config.profile();
+
+    let colors = resolve_profile_colors(profile);
+    CURRENT_TEXT_COLOR = COLORREF(colors.text);
+    CURRENT_BACKGROUND_COLOR = COLORREF(colors.background);
+    if FROZEN {
+        // Keeps the timer stopped, only the colors change
+        update_tray_icon(hwnd, DISPLAYED_POSITION);
+        return true;
+    }
+    let timer = SetTimer(Some(hwnd), UPDATE_TIMER_ID, profile.interval_ms.max(MIN_INTERVAL_MS), None);
+    timer != 0
+}
+
+/// Freezes or unfreezes the tray icon at the displayed position
+unsafe fn set_frozen(hwnd: HWND, frozen: bool) {
+    if FROZEN == frozen {
+        return;
+    }
+    FROZEN = frozen;
+    if frozen {
+        let _ = KillTimer(Some(hwnd), UPDATE_TIMER_ID);
+    } else {
+        let _ = apply_active_profile(hwnd);
+    }
+    update_tray_icon(hwnd, DISPLAYED_POSITION);
+}
+
+/// Values about a cursor position read for one update of the tray icon. Each
+/// is queried on first use, so the icon and the tooltip share one query.
+struct Sample {
+    pt: POINT,
+    window: OnceCell<Option<WindowInfo>>,
+    reference_window: OnceCell<Option<WindowInfo>>,
+    color: OnceCell<Option<u32>>,
+}
+
+impl Sample {
+    fn new(pt: POINT) -> Sample {
+        Sample { pt, window: OnceCell::new(), reference_window: OnceCell::new(), color: OnceCell::new() }
+    }
+
+    /// Top-level window under the cursor
+    unsafe fn window(&self) -> Option<&WindowInfo> {
+        self.window.get_or_init(|| query_window_at(self.pt)).as_ref()
+    }
+
+    /// Window scaled by the reference display mode, see `reference_window`
+    unsafe fn reference_window(&self) -> Option<&WindowInfo> {
+        self.reference_window.get_or_init(|| reference_window(self.pt)).as_ref()
+    }
+
+    /// Color of the pixel under the cursor; reading it makes DWM copy the screen back
+    unsafe fn color(&self) -> Option<u32> {
+        *self.color.get_or_init(|| read_pixel_color(self.pt))
+    }
+}
+
+/// Icon and tooltip shown in the tray
+#[derive(PartialEq)]
+struct TrayContent {
+    mask: IconMask,
+    colors: ThemeColors,
+    swatch: Option<u32>,
+    tooltip: [u16; tooltip::TOOLTIP_BUFFER_LEN],
+}
+
+/// Redraws the tray icon and tooltip for `pt`; the tray is only updated when
+/// either of them changed
+unsafe fn update_tray_icon(hwnd: HWND, pt: POINT) {
+    DISPLAYED_POSITION = pt;
+    let sample = Sample::new(pt);
+    let measurement = MEASURE.current((pt.x, pt.y));
+    let coordinates = CONFIG.as_ref().map(|config| display_coordinates(config.profile(), &sample));
+    let position = match (measurement, coordinates) {
+        (Some(measurement), _) => measurement_position(measurement),
+        (None, Some(coordinates)) => coordinates,
+        (None, None) => DisplayedPosition { x: pt.x, y: pt.y, ..Default::default() },
+    };
+    // Measurements take the icon over from the swatch
+    let swatch = match (measurement, &CONFIG) {
+        (None, Some(config)) => swatch_color(config.profile(), &sample),
+        _ => None,
+    };
+    let content = TrayContent {
+        mask: icon_mask(&position, swatch),
+        colors: current_colors(),
+        swatch,
+        tooltip: tooltip_for_position(&sample, coordinates.as_ref()),
+    };
+
+    let previous = TRAY_CONTENT.as_ref();
+    if previous == Some(&content) {
+        return;
+    }
+    let icon_changed = previous.is_none_or(|previous| (&previous.mask, previous.colors, previous.swatch) != (&content.mask, content.colors, content.swatch));
+    let mut flags = NIF_TIP;
+    if icon_changed {
+        let Ok(new_icon) = create_icon(&content.mask, content.colors, content.swatch) else { return };
+        let current_icon = get_current_icon();
+        if !current_icon.is_invalid() {
+            let _ = DestroyIcon(current_icon);
+        }
+        set_current_icon(new_icon);
+        flags |= NIF_ICON;
+    }
+
+    let nid = NOTIFYICONDATAW {
+        cbSize: size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: hwnd, uID: 1,
+        uFlags: flags,
+        hIcon: get_current_icon(),
+        szTip: content.tooltip,
+        ..Default::default()
+    };
+    if Shell_NotifyIconW(NIM_MODIFY, &nid).as_bool() {
+        TRAY_CONTENT = Some(content);
+    }
+}
+
+/// What the icon shows while measuring: the offsets of a distance, or the
+/// size of a rectangle
+fn measurement_position(measurement: Measurement) -> DisplayedPosition {
+    let (x, y) = match measurement.shape {
+        Shape::Line => (measurement.dx(), measurement.dy()),
+        Shape::Rectangle => {
+            let rectangle = measurement.rectangle();
+            (rectangle.width as i32, rectangle.height as i32)
+        }
+    };
+    DisplayedPosition { x, y, ..Default::default() }
+}
+
+/// Context of the coordinate pipeline from the configuration
+unsafe fn pipeline_context() -> pipeline::Context<'static> {
+    pipeline::Context {
+        origin: CONFIG.as_ref().map(|config| config.origin).unwrap_or_default(),
+        layout: desktop_layout(),
+        window: None,
+        calibration: CALIBRATION.as_ref(),
+        reference: CONFIG.as_ref().map(|config| &config.reference),
+    }
+}
+
+/// Window a display mode measures from, queried only for the modes that need one
+unsafe fn window_for_mode(mode: DisplayMode, sample: &Sample) -> Option<&WindowInfo> {
+    match mode {
+        DisplayMode::Client | DisplayMode::Window => sample.window(),
+        DisplayMode::Reference => sample.reference_window(),
+        _ => None,
+    }
+}
+
+/// Window scaled by the reference mode: the configured one, or the one under
+/// the cursor; `None` when the mode scales monitors
+unsafe fn reference_window(pt: POINT) -> Option<WindowInfo> {
+    let reference = &CONFIG.as_ref()?.reference;
+    if reference.source != ReferenceSource::Window {
+        return None;
+    }
+    if reference.window_title.is_empty() {
+        query_window_at(pt)
+    } else {
+        query_window(reference_window_by_title(&reference.window_title)?)
+    }
+}
+
+/// `find_window_by_title`, reusing the window found last time while it is
+/// still a visible window with a matching title
+unsafe fn reference_window_by_title(pattern: &str) -> Option<HWND> {
+    let cached = REFERENCE_HWND.filter(|&hwnd| {
+        IsWindow(Some(hwnd)).as_bool()
+            && IsWindowVisible(hwnd).as_bool()
+            && window::title_matches(&window_title(hwnd), pattern)
+    });
+    REFERENCE_HWND = cached.or_else(|| find_window_by_title(pattern));
+    REFERENCE_HWND
+}
+
+/// Coordinates shown in the icon for the cursor position of `sample` with the
+/// display mode of `profile`
+unsafe fn display_coordinates(profile: &Profile, sample: &Sample) -> DisplayedPosition {
+    let context = pipeline::Context { window: window_for_mode(profile.display_mode, sample), ..pipeline_context() };
+    pipeline::display_coordinates(profile.display_mode, profile.unit, sample.pt.x, sample.pt.y, &context)
+}
+
+/// Makes `pt` the origin and switches to the relative display mode, unless
+/// an administrator locked it
+unsafe fn set_origin(hwnd: HWND, pt: POINT) {
+    if is_setting_locked("origin") {
+        return;
+    }
+    let Some(config) = &mut CONFIG else { return };
+    config.origin = Origin { x: pt.x, y: pt.y };
+    save_current_config();
+
+    let mut profile = config.profile().clone();
+    if profile.display_mode == DisplayMode::Absolute {
+        profile.display_mode = DisplayMode::Relative;
+        if apply_profile(profile).is_ok() {
+            settings::reload_settings_window();
+        }
+    }
+    update_tray_icon(hwnd, DISPLAYED_POSITION);
+}
+
+/// Fits the configured calibration; an unusable one is reported and ignored
+unsafe fn update_calibration() {
+    CALIBRATION = None;
+    let Some(config) = &CONFIG else { return };
+    let Some(calibration) = config.calibrations.get(&config.calibration) else { return };
+    match ActiveCalibration::new(&config.calibration, calibration) {
+        Ok(active) => CALIBRATION = Some(active),
+        Err(e) => eprintln!("Ignoring calibration \"{}\": {}", config.calibration, e),
+    }
+}
+
+/// Builds the "Calibration" submenu: the stored calibrations and the wizard
+unsafe fn create_calibration_menu() -> Result<HMENU, windows::core::Error> {
+    let hmenu = CreatePopupMenu()?;
+    let Some(config) = &CONFIG else { return Ok(hmenu) };
+
+    let locked = is_setting_locked("calibration");
+    for (index, name) in config.calibrations.keys().enumerate() {
+        let id = MENU_ID_CALIBRATION_FIRST + index as u32;
+        if id > MENU_ID_CALIBRATION_LAST {
+            break;
+        }
+        let mut flags = MF_STRING;
+        if *name == config.calibration {
+            flags |= MF_CHECKED;
+        }
+        if locked {
+            flags |= MF_GRAYED;
+        }
+        let _ = AppendMenuW(hmenu, flags, id as usize, &HSTRING::from(name.replace('&', "&&")));
+    }
+    if !config.calibrations.is_empty() {
+        let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, None);
+    }
+    let new_flags = if is_setting_locked("calibrations") || CALIBRATION_WIZARD.is_some() { MF_STRING | MF_GRAYED } else { MF_STRING };
+    let _ = AppendMenuW(hmenu, new_flags, MENU_ID_NEW_CALIBRATION as usize, &HSTRING::from(tr(Message::MenuNewCalibration)));
+    Ok(hmenu)
+}
+
+/// Makes the calibration at `index` in name order the active one and shows it
+unsafe fn select_calibration(hwnd: HWND, index: usize) {
+    if is_setting_locked("calibration") {
+        return;
+    }
+    let Some(config) = &mut CONFIG else { return };
+    let Some(name) = config.calibrations.keys().nth(index).cloned() else { return };
+    config.calibration = name;
+    save_current_config();
+    update_calibration();
+    show_calibrated(hwnd);
+}
+
+/// Switches the active profile to the calibrated display mode
+unsafe fn show_calibrated(hwnd: HWND) {
+    let Some(config) = &CONFIG else { return };
+    let mut profile = config.profile().clone();
+    profile.display_mode = DisplayMode::Calibrated;
+    if apply_profile(profile).is_ok() {
+        settings::reload_settings_window();
+    }
+    update_tray_icon(hwnd, DISPLAYED_POSITION);
+}
+
+/// Starts the calibration wizard: asks for a name, explains the steps and
+/// waits for the first reference point
+unsafe fn start_calibration(hwnd: HWND) {
+    if is_setting_locked("calibrations") || CALIBRATION_WIZARD.is_some() {
+        return;
+    }
+    let initial = CONFIG.as_ref().map(|config| config.calibration.clone()).unwrap_or_default();
+    let title = tr(Message::CalibrationTitle);
+    let Some(name) = prompt::prompt_text(title, tr(Message::CalibrationName), &initial) else { return };
+    if name.trim().is_empty() {
+        return;
+    }
+    let instructions = HSTRING::from(tr(Message::CalibrationInstructions));
+    if MessageBoxW(Some(hwnd), &instructions, &HSTRING::from(title), MB_OKCANCEL | MB_ICONINFORMATION) != IDOK {
+        return;
+    }
+    CALIBRATION_WIZARD = Some(Wizard::new(name.trim()));
+    wait_for_calibration_click();
+}
+
+/// Installs the mouse hook that turns the next click into a reference point
+unsafe fn wait_for_calibration_click() {
+    let hinstance = GetModuleHandleW(None).ok().map(HINSTANCE::from);
+    match SetWindowsHookExW(WH_MOUSE_LL, Some(calibration_mouse_hook), hinstance, 0) {
+        Ok(hook) => CALIBRATION_HOOK = Some(hook),
+        Err(e) => {
+            eprintln!("Failed to install the calibration mouse hook: {}", e);
+            CALIBRATION_WIZARD = None;
+        }
+    }
+}
+
+unsafe fn remove_calibration_hook() {
+    if let Some(hook) = CALIBRATION_HOOK.take() {
+        let _ = UnhookWindowsHookEx(hook);
+    }
+}
+
+/// Low-level mouse hook of the calibration wizard. A left click marks a
+/// reference point and a right click cancels; the window under the cursor
+/// gets neither. Reported on button release, so that no window is left with
+/// a release without a press.
+unsafe extern "system" fn calibration_mouse_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 {
+        let info = &*(lparam.0 as *const MSLLHOOKSTRUCT);
+        let command = match wparam.0 as u32 {
+            WM_LBUTTONUP => Some(CALIBRATION_CLICK),
+            WM_RBUTTONUP => Some(CALIBRATION_CANCEL),
+            WM_LBUTTONDOWN | WM_RBUTTONDOWN => None,
+            _ => return CallNextHookEx(None, code, wparam, lparam),
+        };
+        if let Some(command) = command {
+            let position = (info.pt.x as u16 as u32 | (info.pt.y as u16 as u32) << 16) as isize;
+            let _ = PostMessageW(Some(MAIN_HWND), CALIBRATION_MESSAGE, WPARAM(command), LPARAM(position));
+        }
+        return LRESULT(1);
+    }
+    CallNextHookEx(None, code, wparam, lparam)
+}
+
+/// Asks for the target coordinates of a clicked reference point, then waits
+/// for the next one or finishes the calibration
+unsafe fn add_calibration_point(hwnd: HWND, pt: POINT) {
+    let Some(number) = CALIBRATION_WIZARD.as_ref().map(|wizard| wizard.points.len() + 1) else { return };
+    let title = tr(Message::CalibrationTitle);
+    let label = tr_args(
+        Message::CalibrationTarget,
+        &[("number", &number.to_string()), ("x", &pt.x.to_string()), ("y", &pt.y.to_string())],
+    );
+    let mut text = String::new();
+    let target = loop {
+        let Some(input) = prompt::prompt_text(title, &label, &text) else {
+            // Skips the point, the wizard goes on with the next click
+            wait_for_calibration_click();
+            return;
+        };
+        match parse_target(&input) {
+            Some(target) => break target,
+            None => {
+                let message = HSTRING::from(tr(Message::CalibrationInvalidTarget));
+                MessageBoxW(Some(hwnd), &message, &HSTRING::from(title), MB_OK | MB_ICONWARNING);
+                text = input;
+            }
+        }
+    };
+
+    let Some(wizard) = &mut CALIBRATION_WIZARD else { return };
+    wizard.add(pt.x, pt.y, target);
+    if wizard.can_finish() {
+        let question = HSTRING::from(tr_args(Message::CalibrationAddPoint, &[("count", &wizard.points.len().to_string())]));
+        if MessageBoxW(Some(hwnd), &question, &HSTRING::from(title), MB_YESNO | MB_ICONQUESTION) == IDNO {
+            finish_calibration(hwnd);
+            return;
+        }
+    }
+    wait_for_calibration_click();
+}
+
+/// Fits and stores the calibration of the wizard and makes it the active one
+unsafe fn finish_calibration(hwnd: HWND) {
+    let Some(wizard) = CALIBRATION_WIZARD.take() else { return };
+    let title = HSTRING::from(tr(Message::CalibrationTitle));
+    let (calibration, transform) = match wizard.finish() {
+        Ok(result) => result,
+        Err(error) => {
+            let message = HSTRING::from(tr_args(Message::CalibrationFailed, &[("error", &error)]));
+            MessageBoxW(Some(hwnd), &message, &title, MB_OK | MB_ICONERROR);
+            return;
+        }
+    };
+    let error = format!("{:.2}", transform.rms_error(&calibration.points));
+
+    let Some(config) = &mut CONFIG else { return };
+    config.calibrations.insert(wizard.name.clone(), calibration);
+    let activate = !is_setting_locked("calibration");
+    if activate {
+        config.calibration = wizard.name.clone();
+    }
+    save_current_config();
+    update_calibration();
+    if activate {
+        show_calibrated(hwnd);
+    }
+    let message = HSTRING::from(tr_args(Message::CalibrationSaved, &[("name", &wizard.name), ("error", &error)]));
+    MessageBoxW(Some(hwnd), &message, &title, MB_OK | MB_ICONINFORMATION);
+}
+
+/// Position copied from the tray icon: the frozen one, otherwise the last one
+/// before the cursor moved to the taskbar
+unsafe fn tray_copy_position() -> POINT {
+    if FROZEN { DISPLAYED_POSITION } else { LAST_POSITION }
+}
+
+/// Switches to the profile at `index` in `Config::profile_names` and saves the choice
+unsafe fn switch_profile(hwnd: HWND, index: usize) {
+    if is_setting_locked("active_profile") {
+        return;
+    }
+    let Some(config) = &mut CONFIG else { return };
+    let Some(name) = config.profile_names().get(index).map(|name| name.to_string()) else { return };
+    if config.set_active_profile(&name) {
+        save_current_config();
+        let _ = apply_active_profile(hwnd);
+        settings::reload_settings_window();
+    }
+}
+
+/// Builds the "Profile" submenu with the active profile checked
+unsafe fn create_profile_menu() -> Result<HMENU, windows::core::Error> {
+    let hmenu = CreatePopupMenu()?;
+    let Some(config) = &CONFIG else { return Ok(hmenu) };
+
+    let locked = is_setting_locked("active_profile");
+    for (index, name) in config.profile_names().into_iter().enumerate() {
+        let id = MENU_ID_PROFILE_FIRST + index as u32;
+        if id > MENU_ID_PROFILE_LAST {
+            break;
+        }
+        let mut flags = MF_STRING;
+        if name == config.active_profile || (index == 0 && !config.profiles.contains_key(&config.active_profile)) {
+            flags |= MF_CHECKED;
+        }
+        if locked {
+            flags |= MF_GRAYED;
+        }
+        let _ = AppendMenuW(hmenu, flags, id as usize, &HSTRING::from(name));
+    }
+    Ok(hmenu)
+}
+
+/// Builds a submenu from the choices of a profile setting in the settings
+/// schema, such as "Theme", with the current value checked
+unsafe fn create_choice_menu(key: &str, first_id: u32) -> Result<HMENU, windows::core::Error> {
+    let hmenu = CreatePopupMenu()?;
+    let Some(config) = &CONFIG else { return Ok(hmenu) };
+    let Some((field, choices)) = choice_field(key) else { return Ok(hmenu) };
+
+    let current = field.get(config);
+    let locked = is_setting_locked(&field.config_key(config));
+    for (index, choice) in choices.iter().enumerate() {
+        let mut flags = MF_STRING;
+        if current.as_ref().and_then(|value| value.as_str()) == Some(choice.value) {
+            flags |= MF_CHECKED;
+        }
+        if locked {
+            flags |= MF_GRAYED;
+        }
+        let _ = AppendMenuW(hmenu, flags, (first_id + index as u32) as usize, &HSTRING::from(tr(choice.label)));
+    }
+    Ok(hmenu)
+}
+
+/// A setting with a fixed set of values, and its choices
+fn choice_field(key: &str) -> Option<(&'static Field, &'static [Choice])> {
+    let field = FIELDS.iter().find(|field| field.key == key)?;
+    match field.control {
+        Control::Choice(choices) => Some((field, choices)),
+        _ => None,
+    }
+}
+
+/// Sets a choice setting to the choice at `index`
+unsafe fn set_choice(key: &str, index: usize) {
+    let (Some(config), Some((field, choices))) = (&CONFIG, choice_field(key)) else { return };
+    let Some(choice) = choices.get(index) else { return };
+    let mut edited = config.clone();
+    if field.set(&mut edited, toml::Value::String(choice.value.to_string())).is_ok() {
+        let _ = apply_settings(edited);
+        settings::reload_settings_window();
+    }
+}
+
+/// Switches the active profile to the display mode after the current one
+unsafe fn next_display_mode() {
+    let (Some(config), Some((field, choices))) = (&CONFIG, choice_field("display_mode")) else { return };
+    let current = field.get(config);
+    let index = choices
+        .iter()
+        .position(|choice| current.as_ref().and_then(|value| value.as_str()) == Some(choice.value))
+        .map_or(0, |index| (index + 1) % choices.len());
+    set_choice("display_mode", index);
+}
+
+/// Builds the "Copy Format" submenu from the built-in formats
+unsafe fn create_copy_format_menu() -> Result<HMENU, windows::core::Error> {
+    let hmenu = CreatePopupMenu()?;
+    let Some(config) = &CONFIG else { return Ok(hmenu) };
+
+    let locked = is_setting_locked("copy_format");
+    for (index, format) in COPY_FORMATS.iter().enumerate() {
+        let mut flags = MF_STRING;
+        if config.copy_format == format.name {
+            flags |= MF_CHECKED;
+        }
+        if locked {
+            flags |= MF_GRAYED;
+        }
+        let _ = AppendMenuW(hmenu, flags, (MENU_ID_COPY_FORMAT_FIRST + index as u32) as usize, &HSTRING::from(format.label));
+    }
+    Ok(hmenu)
+}
+
+/// Switches to the built-in copy format at `index` in `COPY_FORMATS` and saves the choice
+unsafe fn set_copy_format(index: usize) {
+    if is_setting_locked("copy_format") {
+        return;
+    }
+    let (Some(config), Some(format)) = (&mut CONFIG, COPY_FORMATS.get(index)) else { return };
+    if config.copy_format != format.name {
+        config.copy_format = format.name.to_string();
+        save_current_config();
+    }
+}
+
+/// Copies a position to the clipboard in the configured format
+unsafe fn copy_position(hwnd: HWND, pt: POINT) {
+    let format = CONFIG.as_ref().map_or(DEFAULT_COPY_FORMAT, |config| config.copy_format.as_str());
+    let template = match resolve_copy_format(format) {
+        Ok(template) => template,
+        Err(e) => {
+            eprintln!("Invalid copy format: {}", e);
+            match resolve_copy_format(DEFAULT_COPY_FORMAT) {
+                Ok(template) => template,
+                Err(_) => return,
+            }
+        }
+    };
+    let details = query_position_details(&Sample::new(pt), &template);
+    if let Err(e) = set_clipboard_text(hwnd, &template.render(|name| details.value(name))) {
+        eprintln!("Failed to copy to the clipboard: {}", e);
+    }
+}
+
+/// Anchors a measurement of `shape` at `pt`, or finishes or ends the current one
+unsafe fn measure_at(hwnd: HWND, pt: POINT, shape: Shape) {
+    MEASURE.press((pt.x, pt.y), shape);
+    update_tray_icon(hwnd, pt);
+}
+
+/// Copies the last finished measurement in the configured format of its shape
+unsafe fn copy_measurement(hwnd: HWND, measurement: Measurement) {
+    let text = match measurement.shape {
+        Shape::Line => {
+            let format = CONFIG.as_ref().map_or(DEFAULT_MEASURE_FORMAT, |config| config.measure_format.as_str());
+            let template = match measure::parse_template(format) {
+                Ok(template) => template,
+                Err(e) => {
+                    eprintln!("Invalid measure format: {}", e);
+                    match measure::parse_template(DEFAULT_MEASURE_FORMAT) {
+                        Ok(template) => template,
+                        Err(_) => return,
+                    }
+                }
+            };
+            template.render(|name| measurement.value(name))
+        }
+        Shape::Rectangle => {
+            let format = CONFIG.as_ref().map_or(DEFAULT_RECTANGLE_FORMAT, |config| config.rectangle_format.as_str());
+            let template = match resolve_rectangle_format(format) {
+                Ok(template) => template,
+                Err(e) => {
+                    eprintln!("Invalid rectangle format: {}", e);
+                    match resolve_rectangle_format(DEFAULT_RECTANGLE_FORMAT) {
+                        Ok(template) => template,
+                        Err(_) => return,
+                    }
+                }
+            };
+            let rectangle = measurement.rectangle();
+            template.render(|name| rectangle.value(name))
+        }
+    };
+    if let Err(e) = set_clipboard_text(hwnd, &text) {
+        eprintln!("Failed to copy to the clipboard: {}", e);
+    }
+}
+
+/// Builds the "Rectangle Format" submenu with the built-in rectangle formats
+unsafe fn create_rectangle_format_menu() -> Result<HMENU, windows::core::Error> {
+    let hmenu = CreatePopupMenu()?;
+    let Some(config) = &CONFIG else { return Ok(hmenu) };
+
+    let locked = is_setting_locked("rectangle_format");
+    for (index, format) in RECTANGLE_FORMATS.iter().enumerate() {
+        let mut flags = MF_STRING;
+        if config.rectangle_format == format.name {
+            flags |= MF_CHECKED;
+        }
+        if locked {
+            flags |= MF_GRAYED;
+        }
+        let _ = AppendMenuW(hmenu, flags, (MENU_ID_RECTANGLE_FORMAT_FIRST + index as u32) as usize, &HSTRING::from(format.label));
+    }
+    Ok(hmenu)
+}
+
+/// Switches to the built-in rectangle format at `index` in `RECTANGLE_FORMATS` and saves the choice
+unsafe fn set_rectangle_format(index: usize) {
+    if is_setting_locked("rectangle_format") {
+        return;
+    }
+    let (Some(config), Some(format)) = (&mut CONFIG, RECTANGLE_FORMATS.get(index)) else { return };
+    if config.rectangle_format != format.name {
+        config.rectangle_format = format.name.to_string();
+        save_current_config();
+    }
+}
+
+/// Replaces the clipboard content with `text`
+unsafe fn set_clipboard_text(hwnd: HWND, text: &str) -> Result<(), windows::core::Error> {
+    let units: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+    let memory = GlobalAlloc(GMEM_MOVEABLE, units.len() * size_of::<u16>())?;
+    let target = GlobalLock(memory) as *mut u16;
+    if target.is_null() {
+        let _ = GlobalFree(Some(memory));
+        return Err(windows::core::Error::from_win32());
+    }
+    std::ptr::copy_nonoverlapping(units.as_ptr(), target, units.len());
+    let _ = GlobalUnlock(memory);
+
+    if let Err(e) = OpenClipboard(Some(hwnd)) {
+        let _ = GlobalFree(Some(memory));
+        return Err(e);
+    }
+    let _ = EmptyClipboard();
+    // The clipboard owns the memory once SetClipboardData succeeds
+    let result = SetClipboardData(CF_UNICODETEXT, Some(HANDLE(memory.0)));
+    if result.is_err() {
+        let _ = GlobalFree(Some(memory));
+    }
+    let _ = CloseClipboard();
+    result.map(|_| ())
+}
+
+/// Current local time as `YYYY-MM-DD HH:MM:SS`
+unsafe fn local_time() -> String {
+    let time = GetLocalTime();
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        time.wYear, time.wMonth, time.wDay, time.wHour, time.wMinute, time.wSecond,
+    )
+}
+
+/// Adds `pt` to the captured points
+unsafe fn capture_point(pt: POINT) {
+    POINTS.push(CapturedPoint { x: pt.x, y: pt.y, time: local_time(), label: String::new() });
+    save_points_file();
+}
+
+unsafe fn save_points_file() {
+    if let Some(path) = &POINTS_PATH {
+        if let Err(e) = save_points(&POINTS, path) {
+            eprintln!("Failed to save points: {}", e);
+        }
+    }
+}
+
+/// Builds the "Recent Points" submenu: one submenu per point, newest first,
+/// followed by export and clear
+unsafe fn create_points_menu() -> Result<HMENU, windows::core::Error> {
+    let hmenu = CreatePopupMenu()?;
+    if POINTS.is_empty() {
+        let _ = AppendMenuW(hmenu, MF_STRING | MF_GRAYED, 0, &HSTRING::from(tr(Message::NoPoints)));
+        return Ok(hmenu);
+    }
+
+    for (slot, point) in POINTS.iter().rev().take(RECENT_POINTS).enumerate() {
+        let first = MENU_ID_POINT_FIRST + slot as u32 * POINT_COMMANDS;
+        if first + POINT_COMMANDS - 1 > MENU_ID_POINT_LAST {
+            break;
+        }
+        let point_menu = CreatePopupMenu()?;
+        for (command, message) in [
+            (POINT_COMMAND_COPY, Message::MenuCopyPoint),
+            (POINT_COMMAND_LABEL, Message::MenuEditPointLabel),
+            (POINT_COMMAND_DELETE, Message::MenuDeletePoint),
+        ] {
+            let _ = AppendMenuW(point_menu, MF_STRING, (first + command) as usize, &HSTRING::from(tr(message)));
+        }
+        let _ = AppendMenuW(hmenu, MF_POPUP, point_menu.0 as usize, &HSTRING::from(point.menu_text()));
+    }
+    let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, None);
+    let _ = AppendMenuW(hmenu, MF_STRING, MENU_ID_EXPORT_POINTS as usize, &HSTRING::from(tr(Message::MenuExportPoints)));
+    let _ = AppendMenuW(hmenu, MF_STRING, MENU_ID_CLEAR_POINTS as usize, &HSTRING::from(tr(Message::MenuClearPoints)));
+    Ok(hmenu)
+}
+
+/// Runs a command of the "Recent Points" submenu; `slot` counts from the newest point
+unsafe fn run_point_command(hwnd: HWND, slot: usize, command: u32) {
+    let Some(index) = POINTS.len().checked_sub(slot + 1) else { return };
+    let point = POINTS[index].clone();
+    match command {
+        POINT_COMMAND_COPY => copy_position(hwnd, POINT { x: point.x, y: point.y }),
+        POINT_COMMAND_LABEL => {
+            let label = tr_args(Message::PointLabel, &[("x", &point.x.to_string()), ("y", &point.y.to_string())]);
+            let Some(text) = prompt::prompt_text(tr(Message::PointLabelTitle), &label, &point.label) else { return };
+            // The list may have changed while the prompt was open
+            if let Some(stored) = POINTS.iter_mut().find(|stored| **stored == point) {
+                stored.label = text;
+                save_points_file();
+            }
+        }
+        POINT_COMMAND_DELETE => {
+            POINTS.remove(index);
+            save_points_file();
+        }
+        _ => {}
+    }
+}
+
+/// Asks for a file and exports the captured points in the format of its extension
+unsafe fn export_points_to_file(hwnd: HWND) {
+    let mut buffer = [0u16; 260];
+    // Pairs of description and pattern, each terminated by a NUL
+    let filter = HSTRING::from(format!(
+        "{}\0*.csv\0{}\0*.json\0{}\0*.md\0",
+        tr(Message::CsvFiles),
+        tr(Message::JsonFiles),
+        tr(Message::MarkdownFiles),
+    ));
+    let mut ofn = OPENFILENAMEW {
+        lStructSize: size_of::<OPENFILENAMEW>() as u32,
+        hwndOwner: hwnd,
+        lpstrFilter: PCWSTR(filter.as_ptr()),
+        lpstrFile: PWSTR(buffer.as_mut_ptr()),
+        nMaxFile: buffer.len() as u32,
+        lpstrDefExt: w!("csv"),
+        Flags: OFN_OVERWRITEPROMPT | OFN_PATHMUSTEXIST,
+        ..Default::default()
+    };
+    if !GetSaveFileNameW(&mut ofn).as_bool() {
+        return;
+    }
+
+    let path = PathBuf::from(wide_to_string(&buffer));
+    if let Err(e) = export_points(&POINTS, &path) {
+        MessageBoxW(Some(hwnd), &HSTRING::from(e), w!("MPR"), MB_OK | MB_ICONERROR);
+    }
+}
+
+/// Registers the hotkeys of the configuration; invalid, conflicting and
+/// already taken hotkeys are reported and skipped
+unsafe fn register_hotkeys(hwnd: HWND) {
+    let Some(config) = &CONFIG else { return };
+    let (bindings, problems) = hotkey::parse_bindings(&config.hotkeys);
+    for problem in problems {
+        eprintln!("{}", problem);
+    }
+    for (action, hotkey) in bindings {
+        let id = hotkey_id(action);
+        match RegisterHotKey(Some(hwnd), id, HOT_KEY_MODIFIERS(hotkey.modifiers) | MOD_NOREPEAT, hotkey.key) {
+            Ok(()) => HOTKEYS.push((action, hotkey)),
+            Err(e) => eprintln!("Failed to register {} for {}, it may be used by another program: {}", hotkey, action.name(), e),
+        }
+    }
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Config {
-            text_color: 0x00E6D8AD, // Default color from original code
-            background_color: 0x00000000, // Default transparent background
-        }
+unsafe fn unregister_hotkeys(hwnd: HWND) {
+    for (action, _) in HOTKEYS.drain(..) {
+        let _ = UnregisterHotKey(Some(hwnd), hotkey_id(action));
     }
 }
 
-/// Get the configuration file path in the user's home directory
-fn get_config_path() -> PathBuf {
-    let mut path = env::var("USERPROFILE")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| PathBuf::from("C:\\Users\\Public"));
-    
-    path.push(".mpr");
-    path.push("config.toml");
-    path
+fn hotkey_id(action: Action) -> i32 {
+    Action::ALL.iter().position(|&candidate| candidate == action).map_or(0, |index| index as i32 + 1)
 }
 
-/// Load configuration from file, create with defaults if it doesn't exist
-fn load_config() -> Config {
-    let config_path = get_config_path();
-    
-    // Create .mpr directory if it doesn't exist
-    if let Some(parent) = config_path.parent() {
-        if !parent.exists() {
-            if let Err(e) = fs::create_dir_all(parent) {
-                eprintln!("Failed to create config directory: {}", e);
-                return Config::default();
+/// Runs the action of a pressed hotkey
+unsafe fn run_hotkey_action(hwnd: HWND, action: Action) {
+    match action {
+        Action::CopyPosition => {
+            let mut pt = DISPLAYED_POSITION;
+            if FROZEN || GetCursorPos(&mut pt).is_ok() {
+                copy_position(hwnd, pt);
             }
         }
-    }
-    
-    // Try to load existing config
-    match fs::read_to_string(&config_path) {
-        Ok(content) => {
-            match toml::from_str::<Config>(&content) {
-                Ok(config) => config,
-                Err(e) => {
-                    eprintln!("Failed to parse config file: {}", e);
-                    Config::default()
-                }
+        Action::Freeze => set_frozen(hwnd, !FROZEN),
+        Action::CapturePoint => {
+            let mut pt = DISPLAYED_POSITION;
+            if FROZEN || GetCursorPos(&mut pt).is_ok() {
+                capture_point(pt);
+            }
+        }
+        Action::SetOrigin => {
+            let mut pt = DISPLAYED_POSITION;
+            if FROZEN || GetCursorPos(&mut pt).is_ok() {
+                set_origin(hwnd, pt);
             }
         }
-        Err(_) => {
-            // Config file doesn't exist, create with defaults
-            let config = Config::default();
-            if let Err(e) = save_config(&config) {
-                eprintln!("Failed to save default config: {}", e);
+        Action::NextDisplayMode => next_display_mode(),
+        Action::Measure | Action::MeasureRectangle => {
+            let shape = if action == Action::Measure { Shape::Line } else { Shape::Rectangle };
+            let mut pt = DISPLAYED_POSITION;
+            if FROZEN || GetCursorPos(&mut pt).is_ok() {
+                measure_at(hwnd, pt, shape);
             }
-            config
         }
+        Action::OpenSettings => settings::open_settings_window(),
     }
 }
 
-/// Save configuration to file
-fn save_config(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
-    let config_path = get_config_path();
-    let content = toml::to_string_pretty(config)?;
-    fs::write(config_path, content)?;
-    Ok(())
+/// Menu item text followed by the hotkey of `action`, if one is registered
+unsafe fn menu_label(message: Message, action: Action) -> HSTRING {
+    match HOTKEYS.iter().find(|(bound, _)| *bound == action) {
+        Some((_, hotkey)) => HSTRING::from(format!("{}\t{}", tr(message), hotkey)),
+        None => HSTRING::from(tr(message)),
+    }
 }
 
-/// A 5x7 pixel bitmap font for digits 0-9
-const FONT: [[[u8; 5]; 7]; 10] = [
-    // 0
-    [[0,1,1,1,0], [1,0,0,0,1], [1,0,0,0,1], [1,0,0,0,1], [1,0,0,0,1], [1,0,0,0,1], [0,1,1,1,0]],
-    // 1
-    [[0,0,1,0,0], [0,1,1,0,0], [0,0,1,0,0], [0,0,1,0,0], [0,0,1,0,0], [0,0,1,0,0], [0,1,1,1,0]],
-    // 2
-    [[0,1,1,1,0], [1,0,0,0,1], [0,0,0,0,1], [0,0,1,1,0], [0,1,0,0,0], [1,0,0,0,0], [1,1,1,1,1]],
-    // 3
-    [[1,1,1,1,0], [0,0,0,0,1], [0,0,0,1,0], [0,0,1,1,0], [0,0,0,0,1], [1,0,0,0,1], [0,1,1,1,0]],
-    // 4
-    [[0,0,0,1,0], [0,0,1,1,0], [0,1,0,1,0], [1,0,0,1,0], [1,1,1,1,1], [0,0,0,1,0], [0,0,0,1,0]],
-    // 5
-    [[1,1,1,1,1], [1,0,0,0,0], [1,1,1,1,0], [0,0,0,0,1], [0,0,0,0,1], [1,0,0,0,1], [0,1,1,1,0]],
-    // 6
-    [[0,0,1,1,0], [0,1,0,0,0], [1,0,0,0,0], [1,1,1,1,0], [1,0,0,0,1], [1,0,0,0,1], [0,1,1,1,0]],
-    // 7
-    [[1,1,1,1,1], [0,0,0,0,1], [0,0,0,1,0], [0,0,1,0,0], [0,0,1,0,0], [0,0,1,0,0], [0,0,1,0,0]],
-    // 8
-    [[0,1,1,1,0], [1,0,0,0,1], [1,0,0,0,1], [0,1,1,1,0], [1,0,0,0,1], [1,0,0,0,1], [0,1,1,1,0]],
-    // 9
-    [[0,1,1,1,0], [1,0,0,0,1], [1,0,0,0,1], [0,1,1,1,1], [0,0,0,0,1], [0,0,1,0,0], [0,1,1,0,0]],
-];
+/// Whether `pt` is over the taskbar or its notification area
+unsafe fn is_over_taskbar(pt: POINT) -> bool {
+    let Some(hwnd) = top_level_window_at(pt) else { return false };
+    matches!(
+        window_class(hwnd).as_str(),
+        "Shell_TrayWnd" | "Shell_SecondaryTrayWnd" | "NotifyIconOverflowWindow" | "TopLevelWindowForOverflowXamlIsland"
+    )
+}
 
-/// Custom message ID for tray icon events
-const TRAY_MESSAGE: u32 = WM_USER + 1;
+unsafe fn top_level_window_at(pt: POINT) -> Option<HWND> {
+    let hwnd = WindowFromPoint(pt);
+    (!hwnd.is_invalid()).then(|| GetAncestor(hwnd, GA_ROOT))
+}
 
-/// Menu item IDs for context menu
-const MENU_ID_EXIT: u32 = 1001;
-const MENU_ID_SETTINGS: u32 = 1002;
-const ID_COLOR_BUTTON: isize = 2001;
-const ID_BACKGROUND_COLOR_BUTTON: isize = 2002;
+unsafe fn window_class(hwnd: HWND) -> String {
+    let mut class_name = [0u16; 256];
+    let len = GetClassNameW(hwnd, &mut class_name) as usize;
+    String::from_utf16_lossy(&class_name[..len])
+}
 
-/// Global variables
-static mut CURRENT_ICON: HICON = HICON(null_mut());
-static mut SETTINGS_HWND: HWND = HWND(null_mut());
-static mut CURRENT_TEXT_COLOR: COLORREF = COLORREF(0x00E6D8AD);
-static mut CURRENT_BACKGROUND_COLOR: COLORREF = COLORREF(0x00000000);
-static mut COLOR_BUTTON_BRUSH: HBRUSH = HBRUSH(null_mut());
-static mut BACKGROUND_COLOR_BUTTON_BRUSH: HBRUSH = HBRUSH(null_mut());
-static mut COLOR_BUTTON_HWND: HWND = HWND(null_mut());
-static mut BACKGROUND_COLOR_BUTTON_HWND: HWND = HWND(null_mut());
-static mut CONFIG: Option<Config> = None;
+/// Title, class, frame and client area of the top-level window at `pt`
+unsafe fn query_window_at(pt: POINT) -> Option<WindowInfo> {
+    query_window(top_level_window_at(pt)?)
+}
 
-/// Helper functions
-#[inline] unsafe fn get_current_icon() -> HICON { CURRENT_ICON }
-#[inline] unsafe fn set_current_icon(icon: HICON) { CURRENT_ICON = icon; }
-#[inline] unsafe fn get_current_text_color() -> COLORREF { CURRENT_TEXT_COLOR }
-#[inline] unsafe fn set_current_text_color(color: COLORREF) { 
-    CURRENT_TEXT_COLOR = color; 
-    
-    // Update config and save to file
-    if let Some(config) = &mut CONFIG {
-        config.text_color = color.0;
-        if let Err(e) = save_config(config) {
-            eprintln!("Failed to save config: {}", e);
+/// First visible top-level window in Z order whose title contains `pattern`
+unsafe fn find_window_by_title(pattern: &str) -> Option<HWND> {
+    struct Search<'a> {
+        pattern: &'a str,
+        found: Option<HWND>,
+    }
+
+    unsafe extern "system" fn check(hwnd: HWND, data: LPARAM) -> windows::core::BOOL {
+        let search = &mut *(data.0 as *mut Search);
+        if IsWindowVisible(hwnd).as_bool() && window::title_matches(&window_title(hwnd), search.pattern) {
+            search.found = Some(hwnd);
+            return false.into();
         }
+        true.into()
+    }
+
+    let mut search = Search { pattern, found: None };
+    let _ = EnumWindows(Some(check), LPARAM(&mut search as *mut _ as isize));
+    search.found
+}
+
+unsafe fn window_title(hwnd: HWND) -> String {
+    // Windows of other processes return their title without being sent a message
+    let mut title = vec![0u16; GetWindowTextLengthW(hwnd) as usize + 1];
+    let len = GetWindowTextW(hwnd, &mut title) as usize;
+    String::from_utf16_lossy(&title[..len])
+}
+
+/// Title, class, frame and client area of a top-level window
+unsafe fn query_window(hwnd: HWND) -> Option<WindowInfo> {
+    // The window rectangle includes invisible resize borders, DWM knows the visible frame
+    let mut frame = RECT::default();
+    let dwm_frame = DwmGetWindowAttribute(
+        hwnd,
+        DWMWA_EXTENDED_FRAME_BOUNDS,
+        &mut frame as *mut RECT as *mut c_void,
+        size_of::<RECT>() as u32,
+    );
+    if dwm_frame.is_err() {
+        GetWindowRect(hwnd, &mut frame).ok()?;
+    }
+
+    let mut client = RECT::default();
+    GetClientRect(hwnd, &mut client).ok()?;
+    let mut client_origin = POINT::default();
+    if !ClientToScreen(hwnd, &mut client_origin).as_bool() {
+        return None;
     }
+
+    Some(WindowInfo {
+        title: window_title(hwnd),
+        class: window_class(hwnd),
+        frame: to_rect(frame),
+        client: Rect {
+            left: client_origin.x,
+            top: client_origin.y,
+            right: client_origin.x + client.right,
+            bottom: client_origin.y + client.bottom,
+        },
+    })
 }
 
-#[inline] unsafe fn get_current_background_color() -> COLORREF { CURRENT_BACKGROUND_COLOR }
-#[inline] unsafe fn set_current_background_color(color: COLORREF) { 
-    CURRENT_BACKGROUND_COLOR = color; 
-    
-    // Update config and save to file
-    if let Some(config) = &mut CONFIG {
-        config.background_color = color.0;
-        if let Err(e) = save_config(config) {
-            eprintln!("Failed to save config: {}", e);
+/// Tooltip template from the configuration, or the built-in one if it is empty or invalid
+unsafe fn load_tooltip_template(config: &Config) -> Template {
+    if !config.tooltip.is_empty() {
+        match tooltip::parse_template(&config.tooltip) {
+            Ok(template) => return template,
+            Err(e) => eprintln!("Invalid tooltip template: {}", e),
         }
     }
+    tooltip::parse_template(tr(Message::DefaultTooltip)).unwrap_or_default()
+}
+
+/// Converts a NUL-terminated UTF-16 buffer
+fn wide_to_string(buffer: &[u16]) -> String {
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    String::from_utf16_lossy(&buffer[..len])
 }
 
-/// Creates a settings window
-unsafe fn create_settings_window(hinstance: HINSTANCE) -> Result<HWND, windows::core::Error> {
-    let class_name = w!("MPR_Settings");
+/// Current monitor layout, queried on first use after a change
+unsafe fn desktop_layout() -> &'static DesktopLayout {
+    LAYOUT.get_or_insert_with(|| query_desktop_layout())
+}
 
-    let wc = WNDCLASSW {
-        lpfnWndProc: Some(settings_wndproc),
-        hInstance: hinstance.into(),
-        lpszClassName: class_name,
-        hbrBackground: HBRUSH(GetStockObject(WHITE_BRUSH).0),
-        ..Default::default()
+/// All monitors in the system's enumeration order, with the physical sizes
+/// from the configuration applied
+unsafe fn query_desktop_layout() -> DesktopLayout {
+    let mut layout =
+        DesktopLayout { monitors: monitor_handles().into_iter().filter_map(|hmonitor| query_monitor(hmonitor)).collect() };
+    if let Some(config) = &CONFIG {
+        layout.apply_size_overrides(&config.monitor_sizes);
+    }
+    layout
+}
+
+fn to_rect(rect: RECT) -> Rect {
+    Rect { left: rect.left, top: rect.top, right: rect.right, bottom: rect.bottom }
+}
+
+/// All monitor handles in the system's enumeration order
+unsafe fn monitor_handles() -> Vec<HMONITOR> {
+    unsafe extern "system" fn collect(hmonitor: HMONITOR, _: HDC, _: *mut RECT, data: LPARAM) -> windows::core::BOOL {
+        let handles = &mut *(data.0 as *mut Vec<HMONITOR>);
+        handles.push(hmonitor);
+        true.into()
+    }
+
+    let mut handles: Vec<HMONITOR> = Vec::new();
+    let _ = EnumDisplayMonitors(None, None, Some(collect), LPARAM(&mut handles as *mut _ as isize));
+    handles
+}
+
+/// Name, bounds, work area and DPI of a monitor
+unsafe fn query_monitor(hmonitor: HMONITOR) -> Option<Monitor> {
+    let mut info = MONITORINFOEXW::default();
+    info.monitorInfo.cbSize = size_of::<MONITORINFOEXW>() as u32;
+    if !GetMonitorInfoW(hmonitor, &mut info.monitorInfo).as_bool() {
+        return None;
+    }
+
+    // The first device of a display output is the monitor attached to it; its
+    // device interface name leads to the EDID
+    let device = wide_to_string(&info.szDevice);
+    let mut display = DISPLAY_DEVICEW { cb: size_of::<DISPLAY_DEVICEW>() as u32, ..Default::default() };
+    let (name, edid) = if EnumDisplayDevicesW(&HSTRING::from(&device), 0, &mut display, EDD_GET_DEVICE_INTERFACE_NAME).as_bool() {
+        (wide_to_string(&display.DeviceString), read_edid(&wide_to_string(&display.DeviceID)))
+    } else {
+        (device.trim_start_matches("\\\\.\\").to_string(), None)
     };
 
-    if RegisterClassW(&wc) == 0 { return Err(windows::core::Error::from_win32()); }
+    let (mut dpi_x, mut dpi_y) = (96, 96);
+    let _ = GetDpiForMonitor(hmonitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y);
 
-    let hwnd = CreateWindowExW(
-        Default::default(), class_name, w!("Settings"),
-        WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX,
-        CW_USEDEFAULT, CW_USEDEFAULT, 400, 350,
-        None, None, Some(hinstance), None,
-    )?;
+    Some(Monitor {
+        // The device string is mostly just "Generic PnP Monitor"
+        name: edid.as_ref().map_or(name, Edid::model),
+        rect: to_rect(info.monitorInfo.rcMonitor),
+        work_area: to_rect(info.monitorInfo.rcWork),
+        dpi: dpi_x,
+        size_mm: edid.as_ref().and_then(|edid| edid.size_mm),
+        native_resolution: edid.as_ref().and_then(|edid| edid.native_resolution),
+    })
+}
 
-    // Text Color Label (left of the indicator)
-    let _text_label_hwnd = CreateWindowExW(
-        Default::default(), w!("STATIC"), w!("Text Color:"),
-        WS_CHILD | WS_VISIBLE, 20, 30, 100, 20,
-        Some(hwnd), None, Some(hinstance), None,
+/// EDID of the monitor with the device interface name `device_interface`,
+/// as stored in the registry by the monitor driver
+unsafe fn read_edid(device_interface: &str) -> Option<Edid> {
+    let key = edid_registry_key(device_interface)?;
+    // Base block and up to seven extension blocks
+    let mut data = vec![0u8; 8 * edid::EDID_BLOCK_LEN];
+    let mut size = data.len() as u32;
+    let result = RegGetValueW(
+        HKEY_LOCAL_MACHINE,
+        &HSTRING::from(key),
+        w!("EDID"),
+        RRF_RT_REG_BINARY,
+        None,
+        Some(data.as_mut_ptr() as *mut c_void),
+        Some(&mut size),
     );
+    if result.is_err() {
+        return None;
+    }
+    data.truncate(size as usize);
+    match parse_edid(&data) {
+        Ok(edid) => Some(edid),
+        Err(message) => {
+            eprintln!("Ignoring EDID of {}: {}", device_interface, message);
+            None
+        }
+    }
+}
 
-    // Text Color Button (right-aligned)
-    let text_color_button_hwnd = CreateWindowExW(
-        Default::default(),
-        w!("STATIC"),
-        w!(""),
-        WINDOW_STYLE(WS_CHILD.0 | WS_VISIBLE.0 | WS_BORDER.0 | 0x100), // SS_NOTIFY
-        320, 25, 50, 30,
-        Some(hwnd),
-        Some(HMENU(ID_COLOR_BUTTON as *mut c_void)),
-        Some(hinstance), None,
-    )?;
-
-    // Background Color Label (left of the indicator)
-    let _background_label_hwnd = CreateWindowExW(
-        Default::default(), w!("STATIC"), w!("Background Color:"),
-        WS_CHILD | WS_VISIBLE, 20, 80, 120, 20,
-        Some(hwnd), None, Some(hinstance), None,
-    );
+/// Color of the screen pixel at `pt`, `None` if it cannot be read
+unsafe fn read_pixel_color(pt: POINT) -> Option<u32> {
+    let hdc = GetDC(None);
+    if hdc.is_invalid() {
+        return None;
+    }
+    let color = GetPixel(hdc, pt.x, pt.y);
+    ReleaseDC(None, hdc);
+    (color.0 != CLR_INVALID).then_some(color.0)
+}
 
-    // Background Color Button (right-aligned)
-    let background_color_button_hwnd = CreateWindowExW(
-        Default::default(),
-        w!("STATIC"),
-        w!(""),
-        WINDOW_STYLE(WS_CHILD.0 | WS_VISIBLE.0 | WS_BORDER.0 | 0x100), // SS_NOTIFY
-        320, 75, 50, 30,
-        Some(hwnd),
-        Some(HMENU(ID_BACKGROUND_COLOR_BUTTON as *mut c_void)),
-        Some(hinstance), None,
-    )?;
+/// What the tooltip can show about the cursor position. Values that need the
+/// window under the cursor or the pixel color are only read if `template` uses them.
+unsafe fn query_position_details(sample: &Sample, template: &Template) -> PositionDetails {
+    let pt = sample.pt;
+    let context = pipeline_context();
+    let relative = pipeline::display_coordinates(DisplayMode::Relative, Unit::Pixels, pt.x, pt.y, &context);
+    let mut details = PositionDetails {
+        x: pt.x,
+        y: pt.y,
+        rel_x: relative.x,
+        rel_y: relative.y,
+        dpi: 96,
+        color: if template.uses_any(COLOR_PLACEHOLDERS) { sample.color() } else { None },
+        ..Default::default()
+    };
+    if let Some(position) = context.layout.locate(pt.x, pt.y) {
+        details.monitor_name = position.monitor.name.clone();
+        details.monitor_index = position.index;
+        details.monitor_x = position.x;
+        details.monitor_y = position.y;
+        details.work_x = position.work_x;
+        details.work_y = position.work_y;
+        details.monitor_size_mm = position.monitor.size_mm;
+        details.native_resolution = position.monitor.native_resolution;
+        details.monitor_mm = position.monitor.pixel_size_mm().map(|(width_mm, height_mm)| {
+            (units::from_pixels(position.x, width_mm, Unit::Millimeters), units::from_pixels(position.y, height_mm, Unit::Millimeters))
+        });
+        details.dpi = position.monitor.dpi;
+    }
+    if let Some(window) = template.uses_any(WINDOW_PLACEHOLDERS).then(|| sample.window()).flatten() {
+        (details.client_x, details.client_y) = window.client_offset(pt.x, pt.y);
+        (details.window_x, details.window_y) = window.frame_offset(pt.x, pt.y);
+        details.window_title = window.title.clone();
+        details.window_class = window.class.clone();
+    }
+    if template.uses_any(REFERENCE_PLACEHOLDERS) {
+        let context = pipeline::Context { window: sample.reference_window(), ..context };
+        details.reference = pipeline::reference_position(pt.x, pt.y, &context).map(|scaled| (scaled.x, scaled.y));
+    }
+    if let Some(calibration) = &CALIBRATION {
+        details.calibrated = calibration.apply(pt.x, pt.y);
+        details.calibration_decimals = calibration.decimals;
+    }
+    details
+}
 
-    COLOR_BUTTON_HWND = text_color_button_hwnd;
-    BACKGROUND_COLOR_BUTTON_HWND = background_color_button_hwnd;
+/// Tooltip text for the cursor position, fitted into `szTip`. `coordinates`
+/// are the ones of the display mode.
+unsafe fn tooltip_for_position(sample: &Sample, coordinates: Option<&DisplayedPosition>) -> [u16; tooltip::TOOLTIP_BUFFER_LEN] {
+    let pt = sample.pt;
+    let mut text = match &TOOLTIP_TEMPLATE {
+        Some(template) => {
+            let details = query_position_details(sample, template);
+            template.render(|name| details.value(name))
+        }
+        None => String::new(),
+    };
+    if let Some(line) = coordinates.and_then(|coordinates| display_mode_line(sample, coordinates)) {
+        text = format!("{}\n{}", line, text);
+    }
+    if let Some(lines) = measurement_lines(pt) {
+        text = format!("{}\n{}", lines, text);
+    }
+    if FROZEN {
+        text = format!("{}\n{}", tr(Message::Frozen), text);
+    }
+    if let Some(wizard) = &CALIBRATION_WIZARD {
+        let number = (wizard.points.len() + 1).to_string();
+        text = format!("{}\n{}", tr_args(Message::CalibrationClickPoint, &[("number", &number)]), text);
+    }
+    tooltip::to_tooltip_buffer(&text)
+}
 
-    Ok(hwnd)
+/// Tooltip lines with the start and end of the current measurement, and its
+/// offsets, distance and angle or the size of its rectangle
+unsafe fn measurement_lines(pt: POINT) -> Option<String> {
+    let measurement = MEASURE.current((pt.x, pt.y))?;
+    let points = match MEASURE.state {
+        MeasureState::Anchored { start, .. } => tr_args(Message::MeasuringFrom, &[("x", &start.0.to_string()), ("y", &start.1.to_string())]),
+        _ => tr_args(
+            Message::Measured,
+            &[
+                ("x1", &measurement.value("x1")),
+                ("y1", &measurement.value("y1")),
+                ("x2", &measurement.value("x2")),
+                ("y2", &measurement.value("y2")),
+            ],
+        ),
+    };
+    let values = match measurement.shape {
+        Shape::Line => {
+            // Converted with the pixel size of the monitor the measurement starts on
+            let unit = CONFIG.as_ref().map_or(Unit::Pixels, |config| config.profile().unit);
+            let pixel_size = LAYOUT.as_ref().and_then(|layout| layout.locate(measurement.start.0, measurement.start.1)?.monitor.pixel_size_mm());
+            let [dx, dy, distance] = &measurement.lengths(pixel_size, unit);
+            tr_args(Message::MeasurementValues, &[("dx", dx), ("dy", dy), ("distance", distance), ("angle", &measurement.value("angle"))])
+        }
+        Shape::Rectangle => {
+            let rectangle = measurement.rectangle();
+            tr_args(
+                Message::RectangleValues,
+                &[
+                    ("width", &rectangle.value("width")),
+                    ("height", &rectangle.value("height")),
+                    ("area", &rectangle.value("area")),
+                    ("aspect", &rectangle.value("aspect")),
+                ],
+            )
+        }
+    };
+    Some(format!("{}\n{}", points, values))
 }
 
-/// Settings window procedure
-extern "system" fn settings_wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
-    unsafe {
-        match msg {
-            WM_CLOSE => {
-                if !COLOR_BUTTON_BRUSH.is_invalid() {
-                    let _ = DeleteObject(COLOR_BUTTON_BRUSH.into()); // GEÄNDERT
-                }
-                if !BACKGROUND_COLOR_BUTTON_BRUSH.is_invalid() {
-                    let _ = DeleteObject(BACKGROUND_COLOR_BUTTON_BRUSH.into());
-                }
-                let _ = DestroyWindow(hwnd); // GEÄNDERT
-                SETTINGS_HWND = HWND(null_mut());
-                LRESULT(0)
-            }
+/// Tooltip line with the `position` of display modes other than screen coordinates
+unsafe fn display_mode_line(sample: &Sample, position: &DisplayedPosition) -> Option<String> {
+    let config = CONFIG.as_ref()?;
+    let pt = sample.pt;
+    match config.profile().display_mode {
+        // The monitor and the position on it are in the default tooltip
+        DisplayMode::Absolute | DisplayMode::Monitor if position.unit == Unit::Pixels => None,
+        DisplayMode::Absolute | DisplayMode::Monitor => Some(tr_args(
+            Message::PhysicalPosition,
+            &[("x", &units::format_with_symbol(position.x, position.unit)), ("y", &units::format_with_symbol(position.y, position.unit))],
+        )),
+        DisplayMode::Client | DisplayMode::Window => {
+            let window = sample.window()?;
+            Some(tr_args(
+                Message::WindowPosition,
+                &[
+                    // Leaves room for the other lines of the tooltip
+                    ("title", &tooltip::shorten(&window.title, 40)),
+                    ("class", &window.class),
+                    ("x", &units::format_with_symbol(position.x, position.unit)),
+                    ("y", &units::format_with_symbol(position.y, position.unit)),
+                ],
+            ))
+        }
+        DisplayMode::Logical => Some(tr_args(
+            Message::LogicalPosition,
+            &[
+                ("x", &position.x.to_string()),
+                ("y", &position.y.to_string()),
+                ("physical_x", &pt.x.to_string()),
+                ("physical_y", &pt.y.to_string()),
+            ],
+        )),
+        DisplayMode::Calibrated => {
+            let calibration = CALIBRATION.as_ref()?;
+            Some(tr_args(
+                Message::CalibratedPosition,
+                &[
+                    ("name", &calibration.name),
+                    ("x", &units::format_fixed(position.x, position.decimals)),
+                    ("y", &units::format_fixed(position.y, position.decimals)),
+                ],
+            ))
+        }
+        DisplayMode::Reference => {
+            let reference = &config.reference;
+            let context = pipeline::Context { window: sample.reference_window(), ..pipeline_context() };
+            let scaled = pipeline::reference_position(pt.x, pt.y, &context)?;
+            let message = if scaled.inside { Message::ReferencePosition } else { Message::ReferenceOutside };
+            Some(tr_args(
+                message,
+                &[
+                    ("width", &reference.width.to_string()),
+                    ("height", &reference.height.to_string()),
+                    ("x", &scaled.x.to_string()),
+                    ("y", &scaled.y.to_string()),
+                ],
+            ))
+        }
+        DisplayMode::Color => {
+            let color = sample.color()?;
+            let (r, g, b) = components(color);
+            let (hue, saturation, lightness) = to_hsl(color);
+            Some(tr_args(
+                Message::ColorValues,
+                &[
+                    ("hex", &to_hex(color)),
+                    ("r", &r.to_string()),
+                    ("g", &g.to_string()),
+                    ("b", &b.to_string()),
+                    ("hue", &hue.to_string()),
+                    ("saturation", &saturation.to_string()),
+                    ("lightness", &lightness.to_string()),
+                ],
+            ))
+        }
+        DisplayMode::Relative => Some(tr_args(
+            Message::RelativeTo,
+            &[
+                ("origin", &format!("{}, {}", config.origin.x, config.origin.y)),
+                ("x", &pipeline::format_signed(position.x, position.unit)),
+                ("y", &pipeline::format_signed(position.y, position.unit)),
+            ],
+        )),
+    }
+}
 
-            WM_DESTROY => {
-                SETTINGS_HWND = HWND(null_mut());
-                LRESULT(0)
-            }
+/// Draws an icon mask at `scale` times its size: background first, then the text pixels
+unsafe fn paint_icon(hdc: HDC, left: i32, top: i32, scale: i32, mask: &IconMask, colors: ThemeColors) {
+    let size = ICON_SIZE * scale;
+    let _ = PatBlt(hdc, left, top, size, size, BLACKNESS);
 
-            WM_COMMAND => {
-                let control_id = (wparam.0 & 0xFFFF) as isize;
-                if control_id == ID_COLOR_BUTTON {
-                    static mut CUSTOM_COLORS: [COLORREF; 16] = [COLORREF(0); 16];
-                    let mut cc = CHOOSECOLORW {
-                        lStructSize: size_of::<CHOOSECOLORW>() as u32,
-                        hwndOwner: hwnd,
-                        rgbResult: get_current_text_color(),
-                        lpCustColors: CUSTOM_COLORS.as_mut_ptr(),
-                        Flags: CC_FULLOPEN | CC_RGBINIT,
-                        ..Default::default()
-                    };
-                    if ChooseColorW(&mut cc).as_bool() {
-                        set_current_text_color(cc.rgbResult);
-                        let _ = InvalidateRect(Some(hwnd), None, true);
-                    }
-                } else if control_id == ID_BACKGROUND_COLOR_BUTTON {
-                    static mut CUSTOM_COLORS: [COLORREF; 16] = [COLORREF(0); 16];
-                    let mut cc = CHOOSECOLORW {
-                        lStructSize: size_of::<CHOOSECOLORW>() as u32,
-                        hwndOwner: hwnd,
-                        rgbResult: get_current_background_color(),
-                        lpCustColors: CUSTOM_COLORS.as_mut_ptr(),
-                        Flags: CC_FULLOPEN | CC_RGBINIT,
-                        ..Default::default()
-                    };
-                    if ChooseColorW(&mut cc).as_bool() {
-                        set_current_background_color(cc.rgbResult);
-                        let _ = InvalidateRect(Some(hwnd), None, true);
-                    }
-                }
-                LRESULT(0)
-            }
+    // Fill background with configured background color
+    if colors.background != 0 {
+        let background_brush = CreateSolidBrush(COLORREF(colors.background));
+        if !background_brush.is_invalid() {
+            let _ = FillRect(hdc, &RECT { left, top, right: left + size, bottom: top + size }, background_brush);
+            let _ = DeleteObject(background_brush.into());
+        }
+    }
 
-            WM_CTLCOLORSTATIC => {
-                if lparam.0 as isize == COLOR_BUTTON_HWND.0 as isize {
-                    if !COLOR_BUTTON_BRUSH.is_invalid() {
-                        let _ = DeleteObject(COLOR_BUTTON_BRUSH.into());
-                    }
-                    COLOR_BUTTON_BRUSH = CreateSolidBrush(get_current_text_color());
-                    return LRESULT(COLOR_BUTTON_BRUSH.0 as isize);
-                } else if lparam.0 as isize == BACKGROUND_COLOR_BUTTON_HWND.0 as isize {
-                    if !BACKGROUND_COLOR_BUTTON_BRUSH.is_invalid() {
-                        let _ = DeleteObject(BACKGROUND_COLOR_BUTTON_BRUSH.into());
-                    }
-                    BACKGROUND_COLOR_BUTTON_BRUSH = CreateSolidBrush(get_current_background_color());
-                    return LRESULT(BACKGROUND_COLOR_BUTTON_BRUSH.0 as isize);
-                } else {
-                    // Make labels transparent by returning a transparent brush
-                    static mut TRANSPARENT_BRUSH: HBRUSH = HBRUSH(null_mut());
-                    if TRANSPARENT_BRUSH.is_invalid() {
-                        TRANSPARENT_BRUSH = CreateSolidBrush(COLORREF(0x00FFFFFF)); // Transparent white
-                    }
-                    // Set text color to black for good readability
-                    SetTextColor(HDC(wparam.0 as *mut c_void), COLORREF(0x00000000));
-                    SetBkMode(HDC(wparam.0 as *mut c_void), TRANSPARENT);
-                    return LRESULT(TRANSPARENT_BRUSH.0 as isize);
-                }
+    if scale == 1 {
+        for (x, y) in mask.points() {
+            let _ = SetPixel(hdc, left + x, top + y, COLORREF(colors.text));
+        }
+    } else {
+        let text_brush = CreateSolidBrush(COLORREF(colors.text));
+        if !text_brush.is_invalid() {
+            for (x, y) in mask.points() {
+                let (px, py) = (left + x * scale, top + y * scale);
+                let _ = FillRect(hdc, &RECT { left: px, top: py, right: px + scale, bottom: py + scale }, text_brush);
             }
-
-            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+            let _ = DeleteObject(text_brush.into());
         }
     }
 }
 
-/// Creates a 24x24 pixel icon with the specified coordinates
-unsafe fn create_icon_with_cursor_position(x_pos: u32, y_pos: u32) -> Result<HICON, windows::core::Error> {
+/// Color of the swatch the icon shows in the color display mode of `profile`;
+/// `None` in other modes or when the pixel cannot be read
+unsafe fn swatch_color(profile: &Profile, sample: &Sample) -> Option<u32> {
+    if profile.display_mode == DisplayMode::Color { sample.color() } else { None }
+}
+
+/// Fills the swatch inside the frame of `icon::render_swatch` with `color`,
+/// at `scale` times the icon size
+unsafe fn paint_swatch(hdc: HDC, left: i32, top: i32, scale: i32, color: u32) {
+    let brush = CreateSolidBrush(COLORREF(color));
+    if !brush.is_invalid() {
+        let inset = icon::SWATCH_INSET * scale;
+        let size = ICON_SIZE * scale;
+        let _ = FillRect(hdc, &RECT { left: left + inset, top: top + inset, right: left + size - inset, bottom: top + size - inset }, brush);
+        let _ = DeleteObject(brush.into());
+    }
+}
+
+/// Pixels of the icon for the specified coordinates, or of the swatch frame in
+/// the color display mode, with the marks of the current state
+unsafe fn icon_mask(position: &DisplayedPosition, swatch: Option<u32>) -> IconMask {
+    let mut mask = match swatch {
+        Some(_) => icon::render_swatch(),
+        None => icon::render_position(position, get_current_number_format()),
+    };
+    if FROZEN {
+        mask.mark_frozen();
+    }
+    match MEASURE.shape() {
+        Some(Shape::Line) => mask.mark_measuring(),
+        Some(Shape::Rectangle) => mask.mark_rectangle(),
+        None => {}
+    }
+    mask
+}
+
+/// Colors the icon is drawn in
+unsafe fn current_colors() -> ThemeColors {
+    ThemeColors { text: get_current_text_color().0, background: get_current_background_color().0 }
+}
+
+/// Creates a 24x24 pixel icon from `mask`, with a swatch of `swatch` in the
+/// color display mode
+unsafe fn create_icon(mask: &IconMask, colors: ThemeColors, swatch: Option<u32>) -> Result<HICON, windows::core::Error> {
     let hdc = GetDC(None);
     if hdc.is_invalid() { return Err(windows::core::Error::from_win32()); }
 
@@ -327,7 +1608,7 @@ unsafe fn create_icon_with_cursor_position(x_pos: u32, y_pos: u32) -> Result<HIC
         return Err(windows::core::Error::from_win32());
     }
 
-    let bmp = CreateCompatibleBitmap(hdc, 24, 24);
+    let bmp = CreateCompatibleBitmap(hdc, ICON_SIZE, ICON_SIZE);
     if bmp.is_invalid() {
         let _ = DeleteDC(memdc);
         let _ = ReleaseDC(None, hdc);
@@ -335,45 +1616,18 @@ unsafe fn create_icon_with_cursor_position(x_pos: u32, y_pos: u32) -> Result<HIC
     }
 
     let old_bmp = SelectObject(memdc, bmp.into());
-    let background_color = get_current_background_color();
-    let _ = PatBlt(memdc, 0, 0, 24, 24, BLACKNESS);
-
-    // Fill background with configured background color
-    if background_color.0 != 0 {
-        let background_brush = CreateSolidBrush(background_color);
-        if !background_brush.is_invalid() {
-            let _ = FillRect(memdc, &RECT { left: 0, top: 0, right: 24, bottom: 24 }, background_brush);
-            let _ = DeleteObject(background_brush.into());
-        }
-    }
-
-    let numbers_to_draw = [x_pos % 10000, y_pos % 10000];
-    let text_color = get_current_text_color();
-    let y_positions = [3, 14];
-
-    for (row_idx, &number) in numbers_to_draw.iter().enumerate() {
-        let start_y = y_positions[row_idx];
-        for i in 0..4 {
-            let digit_value = (number / 10_u32.pow(3 - i as u32)) % 10;
-            let glyph = FONT[digit_value as usize];
-            let start_x = 1 + i as i32 * 6;
-            for (y, row) in glyph.iter().enumerate() {
-                for (x, &pixel) in row.iter().enumerate() {
-                    if pixel == 1 {
-                        let _ = SetPixel(memdc, start_x + x as i32, start_y + y as i32, text_color);
-                    }
-                }
-            }
-        }
+    paint_icon(memdc, 0, 0, 1, mask, colors);
+    if let Some(color) = swatch {
+        paint_swatch(memdc, 0, 0, 1, color);
     }
 
-    let mut ii = ICONINFO {
+    let ii = ICONINFO {
         fIcon: true.into(),
         hbmMask: bmp,
         hbmColor: bmp,
         ..Default::default()
     };
-    let hicon = CreateIconIndirect(&mut ii)?;
+    let hicon = CreateIconIndirect(&ii)?;
 
     SelectObject(memdc, old_bmp);
     let _ = DeleteObject(bmp.into());
@@ -387,42 +1641,138 @@ unsafe fn create_icon_with_cursor_position(x_pos: u32, y_pos: u32) -> Result<HIC
 extern "system" fn wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
     unsafe {
         match msg {
+            WM_TIMER if wparam.0 == SAVE_TIMER_ID => {
+                flush_config();
+                LRESULT(0)
+            }
+
             WM_TIMER => {
                 let mut pt = POINT::default();
                 if GetCursorPos(&mut pt).is_ok() {
-                    if let Ok(new_icon) = create_icon_with_cursor_position(pt.x as u32, pt.y as u32) {
-                        let current_icon = get_current_icon();
-                        if !current_icon.is_invalid() {
-                            let _ = DestroyIcon(current_icon);
-                        }
-                        set_current_icon(new_icon);
-
-                        let mut nid = NOTIFYICONDATAW {
-                            cbSize: size_of::<NOTIFYICONDATAW>() as u32,
-                            hWnd: hwnd, uID: 1,
-                            uFlags: NIF_ICON,
-                            hIcon: get_current_icon(),
-                            ..Default::default()
-                        };
-                        let _ = Shell_NotifyIconW(NIM_MODIFY, &mut nid);
+                    update_tray_icon(hwnd, pt);
+                    settings::update_preview(pt);
+                    if !is_over_taskbar(pt) {
+                        LAST_POSITION = pt;
+                    }
+                }
+                LRESULT(0)
+            }
+
+            CALIBRATION_MESSAGE => {
+                // Clicks posted before the hook was removed are ignored
+                if CALIBRATION_HOOK.is_some() {
+                    remove_calibration_hook();
+                    let position = lparam.0 as u32;
+                    let pt = POINT { x: position as u16 as i16 as i32, y: (position >> 16) as u16 as i16 as i32 };
+                    match wparam.0 {
+                        CALIBRATION_CLICK => add_calibration_point(hwnd, pt),
+                        _ => CALIBRATION_WIZARD = None,
                     }
                 }
                 LRESULT(0)
             }
 
+            FREEZE_MESSAGE => {
+                let frozen = match wparam.0 {
+                    FREEZE_ON => true,
+                    FREEZE_OFF => false,
+                    _ => !FROZEN,
+                };
+                set_frozen(hwnd, frozen);
+                LRESULT(FROZEN as isize)
+            }
+
+            WM_HOTKEY => {
+                if let Some(&action) = wparam.0.checked_sub(1).and_then(|index| Action::ALL.get(index)) {
+                    run_hotkey_action(hwnd, action);
+                }
+                LRESULT(0)
+            }
+
+            WM_DISPLAYCHANGE => {
+                // Monitor names, bounds or order may have changed
+                LAYOUT = None;
+                LRESULT(0)
+            }
+
+            WM_DPICHANGED => {
+                LAYOUT = None;
+                LRESULT(0)
+            }
+
+            WM_ENDSESSION => {
+                flush_config();
+                LRESULT(0)
+            }
+
             WM_DESTROY => {
                 PostQuitMessage(0);
                 LRESULT(0)
             }
 
+            WM_SETTINGCHANGE => {
+                // Sent with SPI_SETWORKAREA when the taskbar moves or resizes
+                LAYOUT = None;
+                // Sent with "ImmersiveColorSet" when the light/dark mode changes
+                let appearance = query_system_appearance();
+                if appearance != SYSTEM_APPEARANCE {
+                    SYSTEM_APPEARANCE = appearance;
+                    if CONFIG.as_ref().is_some_and(|config| config.profile().theme.follows_system()) {
+                        let _ = apply_active_profile(hwnd);
+                    }
+                    settings::refresh_settings_window();
+                }
+                DefWindowProcW(hwnd, msg, wparam, lparam)
+            }
+
             TRAY_MESSAGE => {
                 match lparam.0 as u32 {
+                    WM_LBUTTONUP => {
+                        // The cursor is on the tray icon now, copy where it was before
+                        copy_position(hwnd, tray_copy_position());
+                        LRESULT(0)
+                    }
                     WM_RBUTTONUP => {
                         let mut pt = POINT::default();
                         if GetCursorPos(&mut pt).is_ok() {
                             if let Ok(hmenu) = CreatePopupMenu() {
-                                let _ = AppendMenuW(hmenu, MF_STRING, MENU_ID_SETTINGS as usize, w!("Settings..."));
-                                let _ = AppendMenuW(hmenu, MF_STRING, MENU_ID_EXIT as usize, w!("Exit"));
+                                if let Ok(profile_menu) = create_profile_menu() {
+                                    let _ = AppendMenuW(hmenu, MF_POPUP, profile_menu.0 as usize, &HSTRING::from(tr(Message::MenuProfile)));
+                                }
+                                if let Ok(theme_menu) = create_choice_menu("theme", MENU_ID_THEME_FIRST) {
+                                    let _ = AppendMenuW(hmenu, MF_POPUP, theme_menu.0 as usize, &HSTRING::from(tr(Message::MenuTheme)));
+                                }
+                                if let Ok(display_mode_menu) = create_choice_menu("display_mode", MENU_ID_DISPLAY_MODE_FIRST) {
+                                    let _ = AppendMenuW(hmenu, MF_POPUP, display_mode_menu.0 as usize, &HSTRING::from(tr(Message::MenuDisplayMode)));
+                                }
+                                let origin_flags = if is_setting_locked("origin") { MF_STRING | MF_GRAYED } else { MF_STRING };
+                                let _ = AppendMenuW(hmenu, origin_flags, MENU_ID_SET_ORIGIN as usize, &menu_label(Message::MenuSetOrigin, Action::SetOrigin));
+                                if let Ok(calibration_menu) = create_calibration_menu() {
+                                    let _ = AppendMenuW(hmenu, MF_POPUP, calibration_menu.0 as usize, &HSTRING::from(tr(Message::MenuCalibration)));
+                                }
+                                let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, None);
+                                let freeze_flags = if FROZEN { MF_STRING | MF_CHECKED } else { MF_STRING };
+                                let _ = AppendMenuW(hmenu, freeze_flags, MENU_ID_FREEZE as usize, &menu_label(Message::MenuFreeze, Action::Freeze));
+                                let _ = AppendMenuW(hmenu, MF_STRING, MENU_ID_COPY as usize, &menu_label(Message::MenuCopyPosition, Action::CopyPosition));
+                                if let Ok(copy_format_menu) = create_copy_format_menu() {
+                                    let _ = AppendMenuW(hmenu, MF_POPUP, copy_format_menu.0 as usize, &HSTRING::from(tr(Message::MenuCopyFormat)));
+                                }
+                                let _ = AppendMenuW(hmenu, MF_STRING, MENU_ID_CAPTURE_POINT as usize, &menu_label(Message::MenuCapturePoint, Action::CapturePoint));
+                                if let Ok(points_menu) = create_points_menu() {
+                                    let _ = AppendMenuW(hmenu, MF_POPUP, points_menu.0 as usize, &HSTRING::from(tr(Message::MenuRecentPoints)));
+                                }
+                                let measure_flags = if MEASURE.shape() == Some(Shape::Line) { MF_STRING | MF_CHECKED } else { MF_STRING };
+                                let _ = AppendMenuW(hmenu, measure_flags, MENU_ID_MEASURE as usize, &menu_label(Message::MenuMeasure, Action::Measure));
+                                let rectangle_flags = if MEASURE.shape() == Some(Shape::Rectangle) { MF_STRING | MF_CHECKED } else { MF_STRING };
+                                let _ = AppendMenuW(hmenu, rectangle_flags, MENU_ID_MEASURE_RECTANGLE as usize, &menu_label(Message::MenuMeasureRectangle, Action::MeasureRectangle));
+                                let copy_measurement_flags = if MEASURE.last.is_some() { MF_STRING } else { MF_STRING | MF_GRAYED };
+                                let _ = AppendMenuW(hmenu, copy_measurement_flags, MENU_ID_COPY_MEASUREMENT as usize, &HSTRING::from(tr(Message::MenuCopyMeasurement)));
+                                if let Ok(rectangle_format_menu) = create_rectangle_format_menu() {
+                                    let _ = AppendMenuW(hmenu, MF_POPUP, rectangle_format_menu.0 as usize, &HSTRING::from(tr(Message::MenuRectangleFormat)));
+                                }
+                                let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, None);
+                                let _ = AppendMenuW(hmenu, MF_STRING, MENU_ID_SETTINGS as usize, &menu_label(Message::MenuSettings, Action::OpenSettings));
+                                let _ = AppendMenuW(hmenu, MF_STRING, MENU_ID_EXIT as usize, &HSTRING::from(tr(Message::MenuExit)));
                                 let _ = SetForegroundWindow(hwnd);
                                 let _ = TrackPopupMenu(hmenu, TPM_LEFTALIGN | TPM_RIGHTBUTTON, pt.x, pt.y, Some(0), hwnd, None);
                                 let _ = DestroyMenu(hmenu);
@@ -441,19 +1791,81 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM)
                         LRESULT(0)
                     }
                     MENU_ID_SETTINGS => {
-                        if SETTINGS_HWND.is_invalid() {
-                            if let Ok(hinstance) = GetModuleHandleW(None) {
-                                if let Ok(hwnd) = create_settings_window(hinstance.into()) {
-                                    let _ = ShowWindow(hwnd, SW_SHOW);
-                                    let _ = SetForegroundWindow(hwnd);
-                                    SETTINGS_HWND = hwnd;
-                                }
-                            }
-                        } else {
-                            let _ = SetForegroundWindow(SETTINGS_HWND);
+                        settings::open_settings_window();
+                        LRESULT(0)
+                    }
+                    MENU_ID_COPY => {
+                        copy_position(hwnd, tray_copy_position());
+                        LRESULT(0)
+                    }
+                    MENU_ID_FREEZE => {
+                        set_frozen(hwnd, !FROZEN);
+                        LRESULT(0)
+                    }
+                    MENU_ID_CAPTURE_POINT => {
+                        capture_point(tray_copy_position());
+                        LRESULT(0)
+                    }
+                    MENU_ID_MEASURE => {
+                        measure_at(hwnd, tray_copy_position(), Shape::Line);
+                        LRESULT(0)
+                    }
+                    MENU_ID_MEASURE_RECTANGLE => {
+                        measure_at(hwnd, tray_copy_position(), Shape::Rectangle);
+                        LRESULT(0)
+                    }
+                    id @ MENU_ID_RECTANGLE_FORMAT_FIRST..=MENU_ID_RECTANGLE_FORMAT_LAST => {
+                        set_rectangle_format((id - MENU_ID_RECTANGLE_FORMAT_FIRST) as usize);
+                        LRESULT(0)
+                    }
+                    MENU_ID_COPY_MEASUREMENT => {
+                        if let Some(measurement) = MEASURE.last {
+                            copy_measurement(hwnd, measurement);
                         }
                         LRESULT(0)
                     }
+                    MENU_ID_EXPORT_POINTS => {
+                        export_points_to_file(hwnd);
+                        LRESULT(0)
+                    }
+                    MENU_ID_CLEAR_POINTS => {
+                        POINTS.clear();
+                        save_points_file();
+                        LRESULT(0)
+                    }
+                    id @ MENU_ID_POINT_FIRST..=MENU_ID_POINT_LAST => {
+                        let offset = id - MENU_ID_POINT_FIRST;
+                        run_point_command(hwnd, (offset / POINT_COMMANDS) as usize, offset % POINT_COMMANDS);
+                        LRESULT(0)
+                    }
+                    id @ MENU_ID_COPY_FORMAT_FIRST..=MENU_ID_COPY_FORMAT_LAST => {
+                        set_copy_format((id - MENU_ID_COPY_FORMAT_FIRST) as usize);
+                        LRESULT(0)
+                    }
+                    id @ MENU_ID_PROFILE_FIRST..=MENU_ID_PROFILE_LAST => {
+                        switch_profile(hwnd, (id - MENU_ID_PROFILE_FIRST) as usize);
+                        LRESULT(0)
+                    }
+                    id @ MENU_ID_THEME_FIRST..=MENU_ID_THEME_LAST => {
+                        set_choice("theme", (id - MENU_ID_THEME_FIRST) as usize);
+                        LRESULT(0)
+                    }
+                    id @ MENU_ID_DISPLAY_MODE_FIRST..=MENU_ID_DISPLAY_MODE_LAST => {
+                        set_choice("display_mode", (id - MENU_ID_DISPLAY_MODE_FIRST) as usize);
+                        LRESULT(0)
+                    }
+                    MENU_ID_SET_ORIGIN => {
+                        set_origin(hwnd, tray_copy_position());
+                        LRESULT(0)
+                    }
+                    MENU_ID_NEW_CALIBRATION => {
+                        start_calibration(hwnd);
+                        LRESULT(0)
+                    }
+                    id @ MENU_ID_CALIBRATION_FIRST..=MENU_ID_CALIBRATION_LAST => {
+                        select_calibration(hwnd, (id - MENU_ID_CALIBRATION_FIRST) as usize);
+                        LRESULT(0)
+                    }
                     _ => LRESULT(0),
                 }
             }
@@ -463,19 +1875,145 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM)
     }
 }
 
+/// Runs a command line command, prints its output to the parent console and
+/// returns the process exit code
+fn run_command(command: Command, store: &mut LayeredConfig, points_path: Option<&Path>) -> i32 {
+    // GUI subsystem programs have no console of their own
+    unsafe {
+        let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+
+    match command {
+        Command::ConfigShow { origin } => {
+            print!("{}", store.describe(origin));
+            0
+        }
+        Command::ConfigCheck => {
+            let problems = store.config().check();
+            for problem in &problems {
+                println!("warning: {}", problem);
+            }
+            if problems.is_empty() {
+                println!("Configuration OK");
+                0
+            } else {
+                1
+            }
+        }
+        Command::PaletteImport(path) => {
+            if store.is_locked("palette") {
+                eprintln!("error: the palette is locked by the system configuration");
+                return 1;
+            }
+            let result = import_palette(&path).and_then(|palette| {
+                let count = palette.colors.len();
+                let mut config = store.config().clone();
+                config.palette = palette;
+                store.save(&config).map_err(|e| e.to_string())?;
+                Ok(count)
+            });
+            match result {
+                Ok(count) => {
+                    println!("Imported {} colors", count);
+                    0
+                }
+                Err(message) => {
+                    eprintln!("error: {}", message);
+                    1
+                }
+            }
+        }
+        Command::Freeze(state) => {
+            let hwnd = match unsafe { FindWindowW(WINDOW_CLASS, PCWSTR::null()) } {
+                Ok(hwnd) => hwnd,
+                Err(_) => {
+                    eprintln!("error: MPR is not running");
+                    return 1;
+                }
+            };
+            let request = match state {
+                Some(true) => FREEZE_ON,
+                Some(false) => FREEZE_OFF,
+                None => FREEZE_TOGGLE,
+            };
+            let frozen = unsafe { SendMessageW(hwnd, FREEZE_MESSAGE, Some(WPARAM(request)), None) };
+            println!("{}", if frozen.0 != 0 { "Display frozen" } else { "Display follows the cursor" });
+            0
+        }
+        Command::PointsExport(path) => {
+            let result = points_path
+                .ok_or_else(|| "No configuration location, there are no captured points".to_string())
+                .and_then(load_points)
+                .and_then(|points| export_points(&points, &path).map(|()| points.len()));
+            match result {
+                Ok(count) => {
+                    println!("Exported {} points", count);
+                    0
+                }
+                Err(message) => {
+                    eprintln!("error: {}", message);
+                    1
+                }
+            }
+        }
+        Command::PaletteExport(path) => match export_palette(&store.config().palette, &path) {
+            Ok(()) => 0,
+            Err(message) => {
+                eprintln!("error: {}", message);
+                1
+            }
+        },
+    }
+}
+
 /// Main function of the program
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     unsafe {
+        let args = match cli::parse_args(std::env::args().skip(1)) {
+            Ok(args) => args,
+            Err(message) => {
+                let text = format!("{}\n\n{}", message, cli::USAGE);
+                MessageBoxW(None, &HSTRING::from(text), w!("MPR"), MB_OK | MB_ICONERROR);
+                return Err("Invalid command line arguments".into());
+            }
+        };
+
         // Load configuration at startup
-        let config = load_config();
-        CONFIG = Some(config);
-        
-        // Set current text color from config
-        CURRENT_TEXT_COLOR = COLORREF(CONFIG.as_ref().unwrap().text_color);
-        CURRENT_BACKGROUND_COLOR = COLORREF(CONFIG.as_ref().unwrap().background_color);
-        
+        let config_path = resolve_config_path(args.config.as_deref());
+        if config_path.is_none() {
+            eprintln!("No configuration location found, changes will not be saved");
+        }
+        let points_path = config_path.as_deref().map(points::points_path);
+        let mut store = LayeredConfig::load(config_path, &args.overrides);
+
+        if let Some(command) = args.command {
+            let code = run_command(command, &mut store, points_path.as_deref());
+            if code != 0 {
+                std::process::exit(code);
+            }
+            return Ok(());
+        }
+
+        CONFIG = Some(store.config().clone());
+        CONFIG_STORE = Some(store);
+
+        let system_locale = Locale::from_language_id(GetUserDefaultUILanguage());
+        i18n::set_locale(CONFIG.as_ref().map(|config| config.language).unwrap_or_default().resolve(system_locale));
+        TOOLTIP_TEMPLATE = CONFIG.as_ref().map(|config| load_tooltip_template(config));
+        update_calibration();
+        if let Some(path) = points_path {
+            match load_points(&path) {
+                Ok(points) => {
+                    POINTS = points;
+                    POINTS_PATH = Some(path);
+                }
+                // Keeps the unreadable file instead of overwriting it
+                Err(e) => eprintln!("Failed to load points, new points will not be saved: {}", e),
+            }
+        }
+
         let hinstance = GetModuleHandleW(None)?;
-        let class_name = w!("MPR");
+        let class_name = WINDOW_CLASS;
 
         let wc = WNDCLASSW {
             lpfnWndProc: Some(wndproc),
@@ -492,6 +2030,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             Default::default(), class_name, w!(""), WS_OVERLAPPED,
             0, 0, 0, 0, None, None, Some(hinstance.into()), None,
         )?;
+        MAIN_HWND = hwnd;
+
+        // Sets colors and starts the update timer
+        SYSTEM_APPEARANCE = query_system_appearance();
+        if !apply_active_profile(hwnd) {
+            return Err("Failed to set timer".into());
+        }
 
         let mut nid = NOTIFYICONDATAW {
             cbSize: size_of::<NOTIFYICONDATAW>() as u32,
@@ -502,29 +2047,34 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             ..Default::default()
         };
 
-        let initial_icon = create_icon_with_cursor_position(0, 0)?;
+        let initial_icon = create_icon(&icon_mask(&DisplayedPosition::default(), None), current_colors(), None)?;
         set_current_icon(initial_icon);
         nid.hIcon = get_current_icon();
 
-        let tooltip_text = "Mouse Position";
-        let utf16_chars: Vec<u16> = tooltip_text.encode_utf16().collect();
-        nid.szTip[..utf16_chars.len()].copy_from_slice(&utf16_chars);
+        let mut pt = POINT::default();
+        let _ = GetCursorPos(&mut pt);
+        let sample = Sample::new(pt);
+        let coordinates = CONFIG.as_ref().map(|config| display_coordinates(config.profile(), &sample));
+        nid.szTip = tooltip_for_position(&sample, coordinates.as_ref());
 
-        if !Shell_NotifyIconW(NIM_ADD, &mut nid).as_bool() {
+        if !Shell_NotifyIconW(NIM_ADD, &nid).as_bool() {
             return Err("Failed to add tray icon".into());
         }
 
-        if SetTimer(Some(hwnd), 1, 100, None) == 0 {
-            return Err("Failed to set timer".into());
-        }
+        register_hotkeys(hwnd);
 
         let mut msg = MSG::default();
         while GetMessageW(&mut msg, None, 0, 0).into() {
+            if settings::translate_settings_message(&msg) {
+                continue;
+            }
             let _ = TranslateMessage(&msg);
             DispatchMessageW(&msg);
         }
 
-        let _ = Shell_NotifyIconW(NIM_DELETE, &mut nid);
+        flush_config();
+        unregister_hotkeys(hwnd);
+        let _ = Shell_NotifyIconW(NIM_DELETE, &nid);
         let current_icon = get_current_icon();
         if !current_icon.is_invalid() {
             let _ = DestroyIcon(current_icon);
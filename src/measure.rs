@@ -0,0 +1,229 @@
+// Measuring between two points: a small state machine driven by the measure
+// hotkeys, and the distance and angle of a measurement
+
+use crate::config::Unit;
+use crate::pipeline::format_signed;
+use crate::rectangle::Rectangle;
+use crate::template::Template;
+use crate::units;
+
+/// Placeholders available in the copy format of distance measurements
+pub const PLACEHOLDERS: &[&str] = &["x1", "y1", "x2", "y2", "dx", "dy", "distance", "angle"];
+
+/// Copy format of distance measurements when none is configured
+pub const DEFAULT_MEASURE_FORMAT: &str = "dx={dx} dy={dy} distance={distance} angle={angle}";
+
+/// What two marked points measure
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Shape {
+    /// Distance and angle between the points
+    #[default]
+    Line,
+    /// Rectangle with the points as opposite corners
+    Rectangle,
+}
+
+/// Distance between two virtual-desktop positions
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Measurement {
+    pub start: (i32, i32),
+    pub end: (i32, i32),
+    pub shape: Shape,
+}
+
+impl Measurement {
+    pub fn dx(&self) -> i32 {
+        self.end.0 - self.start.0
+    }
+
+    pub fn dy(&self) -> i32 {
+        self.end.1 - self.start.1
+    }
+
+    /// Euclidean distance in pixels
+    pub fn distance(&self) -> f64 {
+        (self.dx() as f64).hypot(self.dy() as f64)
+    }
+
+    /// Direction from start to end in degrees, counterclockwise from the
+    /// positive x axis with y pointing up as in mathematics, in (-180, 180]
+    pub fn angle(&self) -> f64 {
+        let angle = (-self.dy() as f64).atan2(self.dx() as f64).to_degrees();
+        // atan2 gives -180 for straight left
+        if angle == -180.0 { 180.0 } else { angle }
+    }
+
+    /// Signed dx and dy and the distance as text in `unit`, converted with the
+    /// width and height of a pixel in millimetres; in pixels when the pixel
+    /// size is unknown
+    pub fn lengths(&self, pixel_size_mm: Option<(f64, f64)>, unit: Unit) -> [String; 3] {
+        match pixel_size_mm {
+            Some((width_mm, height_mm)) if unit != Unit::Pixels => {
+                let distance_mm = (self.dx() as f64 * width_mm).hypot(self.dy() as f64 * height_mm);
+                [
+                    format_signed(units::from_pixels(self.dx(), width_mm, unit), unit),
+                    format_signed(units::from_pixels(self.dy(), height_mm, unit), unit),
+                    units::format_with_symbol(units::from_mm(distance_mm, unit), unit),
+                ]
+            }
+            _ => [format_signed(self.dx(), Unit::Pixels), format_signed(self.dy(), Unit::Pixels), self.value("distance")],
+        }
+    }
+
+    pub fn rectangle(&self) -> Rectangle {
+        Rectangle::from_corners(self.start, self.end)
+    }
+
+    /// Text of a placeholder; distance and angle with one decimal place
+    pub fn value(&self, name: &str) -> String {
+        match name {
+            "x1" => self.start.0.to_string(),
+            "y1" => self.start.1.to_string(),
+            "x2" => self.end.0.to_string(),
+            "y2" => self.end.1.to_string(),
+            "dx" => self.dx().to_string(),
+            "dy" => self.dy().to_string(),
+            "distance" => format!("{:.1}", self.distance()),
+            "angle" => format!("{:.1}", self.angle()),
+            _ => String::new(),
+        }
+    }
+}
+
+/// Where the measure hotkey is in its cycle
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MeasureState {
+    /// Not measuring, the icon shows positions
+    #[default]
+    Idle,
+    /// Measuring from a start point to the cursor
+    Anchored { start: (i32, i32), shape: Shape },
+    /// Measurement finished, the icon keeps showing it
+    Measured(Measurement),
+}
+
+/// Measure mode: each press of a measure hotkey anchors, finishes or ends a
+/// measurement
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Measure {
+    pub state: MeasureState,
+    /// Most recently finished measurement, kept for copying after the mode ended
+    pub last: Option<Measurement>,
+}
+
+impl Measure {
+    /// Advances idle → anchored at `position` → measured up to `position` →
+    /// idle; `shape` only matters when anchoring
+    pub fn press(&mut self, position: (i32, i32), shape: Shape) {
+        self.state = match self.state {
+            MeasureState::Idle => MeasureState::Anchored { start: position, shape },
+            MeasureState::Anchored { start, shape } => {
+                let measurement = Measurement { start, end: position, shape };
+                self.last = Some(measurement);
+                MeasureState::Measured(measurement)
+            }
+            MeasureState::Measured(_) => MeasureState::Idle,
+        };
+    }
+
+    /// Measurement to show with the cursor at `cursor`: up to the cursor while
+    /// anchored, the finished one afterwards, `None` when idle
+    pub fn current(&self, cursor: (i32, i32)) -> Option<Measurement> {
+        match self.state {
+            MeasureState::Idle => None,
+            MeasureState::Anchored { start, shape } => Some(Measurement { start, end: cursor, shape }),
+            MeasureState::Measured(measurement) => Some(measurement),
+        }
+    }
+
+    /// Shape being measured, `None` when idle
+    pub fn shape(&self) -> Option<Shape> {
+        match self.state {
+            MeasureState::Idle => None,
+            MeasureState::Anchored { shape, .. } => Some(shape),
+            MeasureState::Measured(measurement) => Some(measurement.shape),
+        }
+    }
+}
+
+/// Parses the copy format of distance measurements
+pub fn parse_template(text: &str) -> Result<Template, String> {
+    Template::parse(text, PLACEHOLDERS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(start: (i32, i32), end: (i32, i32)) -> Measurement {
+        Measurement { start, end, shape: Shape::Line }
+    }
+
+    #[test]
+    fn presses_cycle_through_anchored_measured_and_idle() {
+        let mut measure = Measure::default();
+        assert_eq!(measure.current((5, 5)), None);
+        assert_eq!(measure.shape(), None);
+
+        measure.press((10, 20), Shape::Rectangle);
+        assert_eq!(measure.state, MeasureState::Anchored { start: (10, 20), shape: Shape::Rectangle });
+        assert_eq!(measure.current((30, 50)), Some(Measurement { start: (10, 20), end: (30, 50), shape: Shape::Rectangle }));
+        assert_eq!(measure.last, None);
+
+        // The shape of the second press is ignored
+        measure.press((40, 60), Shape::Line);
+        let measured = Measurement { start: (10, 20), end: (40, 60), shape: Shape::Rectangle };
+        assert_eq!(measure.state, MeasureState::Measured(measured));
+        assert_eq!(measure.current((0, 0)), Some(measured));
+        assert_eq!(measure.shape(), Some(Shape::Rectangle));
+
+        measure.press((0, 0), Shape::Line);
+        assert_eq!(measure.state, MeasureState::Idle);
+        assert_eq!(measure.current((0, 0)), None);
+    }
+
+    #[test]
+    fn the_last_measurement_is_kept_until_the_next_one() {
+        let mut measure = Measure::default();
+        measure.press((0, 0), Shape::Line);
+        measure.press((3, 4), Shape::Line);
+        measure.press((9, 9), Shape::Line);
+        assert_eq!(measure.last, Some(line((0, 0), (3, 4))));
+
+        // Anchoring again keeps it for copying
+        measure.press((100, 100), Shape::Line);
+        assert_eq!(measure.last, Some(line((0, 0), (3, 4))));
+        measure.press((100, 110), Shape::Line);
+        assert_eq!(measure.last, Some(line((100, 100), (100, 110))));
+    }
+
+    #[test]
+    fn angles_count_counterclockwise_with_y_up() {
+        assert_eq!(line((0, 0), (10, 0)).angle(), 0.0);
+        // Screen y grows downwards
+        assert_eq!(line((0, 0), (0, -10)).angle(), 90.0);
+        assert_eq!(line((0, 0), (-10, 0)).angle(), 180.0);
+        assert_eq!(line((0, 0), (0, 10)).angle(), -90.0);
+        assert_eq!(line((0, 0), (10, -10)).angle(), 45.0);
+        assert_eq!(line((5, 5), (5, 5)).angle(), 0.0);
+    }
+
+    #[test]
+    fn values_fill_the_placeholders() {
+        let measurement = line((10, 20), (13, 16));
+        let values: Vec<_> = PLACEHOLDERS.iter().map(|name| measurement.value(name)).collect();
+        assert_eq!(values, ["10", "20", "13", "16", "3", "-4", "5.0", "53.1"]);
+        assert_eq!(parse_template(DEFAULT_MEASURE_FORMAT).unwrap().render(|name| measurement.value(name)), "dx=3 dy=-4 distance=5.0 angle=53.1");
+    }
+
+    #[test]
+    fn lengths_use_the_unit_when_the_pixel_size_is_known() {
+        let measurement = line((0, 0), (300, -400));
+        assert_eq!(measurement.lengths(None, Unit::Millimeters), ["+300", "-400", "500.0"]);
+        assert_eq!(measurement.lengths(Some((0.5, 0.5)), Unit::Pixels), ["+300", "-400", "500.0"]);
+        assert_eq!(measurement.lengths(Some((0.5, 0.5)), Unit::Millimeters), ["+150 mm", "-200 mm", "250 mm"]);
+        assert_eq!(measurement.lengths(Some((0.254, 0.254)), Unit::Inches), ["+3.0 in", "-4.0 in", "5.0 in"]);
+        // Pixels taller than wide
+        assert_eq!(measurement.lengths(Some((0.3, 0.6)), Unit::Millimeters), ["+90 mm", "-240 mm", "256 mm"]);
+    }
+}
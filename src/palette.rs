@@ -0,0 +1,291 @@
+// Named color palettes and their GIMP (.gpl) and JSON file formats
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::{components, from_hex, rgb, to_hex};
+
+/// Number of custom color slots in the system color picker
+pub const PICKER_SLOTS: usize = 16;
+
+/// Color shown in unused picker slots
+const EMPTY_SLOT: u32 = rgb(0xFF, 0xFF, 0xFF);
+
+/// Named list of colors shared by all color pickers
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+    pub name: String,
+    pub colors: Vec<u32>, // COLORREF as u32
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette { name: "Custom".to_string(), colors: Vec::new() }
+    }
+}
+
+/// Palette file formats, chosen by file extension
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaletteFormat {
+    Gimp,
+    Json,
+}
+
+impl PaletteFormat {
+    /// `.gpl` files are GIMP palettes, everything else is JSON
+    pub fn from_path(path: &Path) -> PaletteFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("gpl") => PaletteFormat::Gimp,
+            _ => PaletteFormat::Json,
+        }
+    }
+}
+
+/// JSON representation: `{ "name": "...", "colors": ["#RRGGBB", ...] }`
+#[derive(Serialize, Deserialize)]
+struct JsonPalette {
+    #[serde(default)]
+    name: String,
+    colors: Vec<String>,
+}
+
+impl Palette {
+    /// Fill the picker's custom color slots, unused slots stay white
+    pub fn to_picker_slots(&self) -> [u32; PICKER_SLOTS] {
+        let mut slots = [EMPTY_SLOT; PICKER_SLOTS];
+        for (slot, color) in slots.iter_mut().zip(&self.colors) {
+            *slot = *color;
+        }
+        slots
+    }
+
+    /// Take over the picker's custom color slots, keeping colors beyond the
+    /// slot count. The picker has no notion of empty slots, so the slots the
+    /// palette filled stay in use even if they are now white, and white slots
+    /// after them are dropped: a white color added past the end is lost.
+    pub fn update_from_picker_slots(&mut self, slots: &[u32; PICKER_SLOTS]) {
+        let filled = self.colors.len().min(PICKER_SLOTS);
+        let used = slots.iter().rposition(|&color| color != EMPTY_SLOT).map_or(0, |index| index + 1).max(filled);
+        let mut colors = slots[..used].to_vec();
+        colors.extend(self.colors.iter().skip(PICKER_SLOTS));
+        self.colors = colors;
+    }
+
+    pub fn parse(content: &str, format: PaletteFormat) -> Result<Palette, String> {
+        match format {
+            PaletteFormat::Gimp => Palette::parse_gpl(content),
+            PaletteFormat::Json => Palette::parse_json(content),
+        }
+    }
+
+    pub fn serialize(&self, format: PaletteFormat) -> String {
+        match format {
+            PaletteFormat::Gimp => self.to_gpl(),
+            PaletteFormat::Json => self.to_json(),
+        }
+    }
+
+    /// Parse a GIMP palette; color names are not kept
+    pub fn parse_gpl(content: &str) -> Result<Palette, String> {
+        let mut lines = content.lines().enumerate();
+        match lines.next() {
+            Some((_, header)) if header.trim() == "GIMP Palette" => {}
+            _ => return Err("Not a GIMP palette: missing \"GIMP Palette\" header".into()),
+        }
+
+        let mut palette = Palette { name: String::new(), colors: Vec::new() };
+        for (index, line) in lines {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("Columns:") {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix("Name:") {
+                palette.name = name.trim().to_string();
+                continue;
+            }
+
+            let mut channels = line.split_whitespace().take(3).map(|value| value.parse::<u8>());
+            match (channels.next(), channels.next(), channels.next()) {
+                (Some(Ok(r)), Some(Ok(g)), Some(Ok(b))) => palette.colors.push(rgb(r, g, b)),
+                _ => return Err(format!("Line {}: expected \"R G B [name]\", got: {}", index + 1, line)),
+            }
+        }
+        Ok(palette)
+    }
+
+    pub fn to_gpl(&self) -> String {
+        let mut out = String::from("GIMP Palette\n");
+        let _ = writeln!(out, "Name: {}", self.name);
+        out.push_str("#\n");
+        for &color in &self.colors {
+            let (r, g, b) = components(color);
+            let _ = writeln!(out, "{:3} {:3} {:3}\t{}", r, g, b, to_hex(color));
+        }
+        out
+    }
+
+    pub fn parse_json(content: &str) -> Result<Palette, String> {
+        let json: JsonPalette = serde_json::from_str(content).map_err(|e| e.to_string())?;
+        let colors = json
+            .colors
+            .iter()
+            .map(|text| from_hex(text).ok_or_else(|| format!("Invalid color \"{}\", expected #RRGGBB", text)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Palette { name: json.name, colors })
+    }
+
+    pub fn to_json(&self) -> String {
+        let json = JsonPalette {
+            name: self.name.clone(),
+            colors: self.colors.iter().map(|&color| to_hex(color)).collect(),
+        };
+        serde_json::to_string_pretty(&json).unwrap_or_default() + "\n"
+    }
+}
+
+/// Read a palette file, the format is chosen by extension
+pub fn import_palette(path: &Path) -> Result<Palette, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut palette = Palette::parse(&content, PaletteFormat::from_path(path))
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    if palette.name.is_empty() {
+        palette.name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+    }
+    Ok(palette)
+}
+
+/// Write a palette file, the format is chosen by extension
+pub fn export_palette(palette: &Palette, path: &Path) -> Result<(), String> {
+    std::fs::write(path, palette.serialize(PaletteFormat::from_path(path)))
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brand() -> Palette {
+        Palette { name: "Brand".to_string(), colors: vec![rgb(0xE6, 0x9F, 0x00), rgb(0x00, 0x72, 0xB2), rgb(0xFF, 0xFF, 0xFF)] }
+    }
+
+    #[test]
+    fn gpl_round_trips() {
+        let text = brand().to_gpl();
+        assert_eq!(text, "GIMP Palette\nName: Brand\n#\n230 159   0\t#E69F00\n  0 114 178\t#0072B2\n255 255 255\t#FFFFFF\n");
+        assert_eq!(Palette::parse_gpl(&text), Ok(brand()));
+    }
+
+    #[test]
+    fn gpl_skips_comments_and_columns() {
+        let palette = Palette::parse_gpl("GIMP Palette\nName: Web\nColumns: 4\n# comment\n\n  1 2 3 Dark\n").unwrap();
+        assert_eq!(palette, Palette { name: "Web".to_string(), colors: vec![rgb(1, 2, 3)] });
+    }
+
+    #[test]
+    fn gpl_rejects_bad_headers_and_components() {
+        assert_eq!(Palette::parse_gpl("Name: Brand\n1 2 3\n"), Err("Not a GIMP palette: missing \"GIMP Palette\" header".into()));
+        assert_eq!(Palette::parse_gpl(""), Err("Not a GIMP palette: missing \"GIMP Palette\" header".into()));
+        assert_eq!(Palette::parse_gpl("GIMP Palette\n1 2 256\n"), Err("Line 2: expected \"R G B [name]\", got: 1 2 256".into()));
+        assert_eq!(Palette::parse_gpl("GIMP Palette\n1 -2 3\n"), Err("Line 2: expected \"R G B [name]\", got: 1 -2 3".into()));
+        assert_eq!(Palette::parse_gpl("GIMP Palette\n1 2\n"), Err("Line 2: expected \"R G B [name]\", got: 1 2".into()));
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let text = brand().to_json();
+        assert_eq!(text, "{\n  \"name\": \"Brand\",\n  \"colors\": [\n    \"#E69F00\",\n    \"#0072B2\",\n    \"#FFFFFF\"\n  ]\n}\n");
+        assert_eq!(Palette::parse_json(&text), Ok(brand()));
+        assert_eq!(Palette::parse(&text, PaletteFormat::Json), Ok(brand()));
+    }
+
+    #[test]
+    fn json_rejects_invalid_colors_and_documents() {
+        assert_eq!(
+            Palette::parse_json(r##"{"colors": ["#E69F00", "#12345"]}"##),
+            Err("Invalid color \"#12345\", expected #RRGGBB".into())
+        );
+        assert!(Palette::parse_json(r##"{"colors": ["#GGGGGG"]}"##).is_err());
+        assert!(Palette::parse_json(r#"{"name": "Brand"}"#).is_err());
+        assert!(Palette::parse_json("GIMP Palette").is_err());
+        // Names are optional
+        assert_eq!(Palette::parse_json(r#"{"colors": []}"#).unwrap().name, "");
+    }
+
+    #[test]
+    fn formats_follow_the_extension() {
+        assert_eq!(PaletteFormat::from_path(Path::new("brand.gpl")), PaletteFormat::Gimp);
+        assert_eq!(PaletteFormat::from_path(Path::new("BRAND.GPL")), PaletteFormat::Gimp);
+        assert_eq!(PaletteFormat::from_path(Path::new("brand.json")), PaletteFormat::Json);
+        assert_eq!(PaletteFormat::from_path(Path::new("brand")), PaletteFormat::Json);
+    }
+
+    #[test]
+    fn picker_slots_are_filled_with_white() {
+        let slots = brand().to_picker_slots();
+        assert_eq!(&slots[..3], &brand().colors[..]);
+        assert!(slots[3..].iter().all(|&slot| slot == EMPTY_SLOT));
+    }
+
+    #[test]
+    fn picker_slots_keep_white_colors_of_the_palette() {
+        // The white third color survives a round trip, as does one set to white
+        let mut palette = brand();
+        let mut slots = palette.to_picker_slots();
+        slots[0] = EMPTY_SLOT;
+        palette.update_from_picker_slots(&slots);
+        assert_eq!(palette.colors, [EMPTY_SLOT, rgb(0x00, 0x72, 0xB2), EMPTY_SLOT]);
+    }
+
+    #[test]
+    fn picker_slots_add_colors_up_to_the_last_non_white_one() {
+        let mut palette = brand();
+        let mut slots = palette.to_picker_slots();
+        slots[5] = rgb(1, 2, 3);
+        palette.update_from_picker_slots(&slots);
+        assert_eq!(palette.colors.len(), 6);
+        assert_eq!(&palette.colors[3..], [EMPTY_SLOT, EMPTY_SLOT, rgb(1, 2, 3)]);
+
+        // Known limitation: white added after the last used slot counts as empty
+        let mut palette = brand();
+        let mut slots = palette.to_picker_slots();
+        slots[3] = EMPTY_SLOT;
+        palette.update_from_picker_slots(&slots);
+        assert_eq!(palette, brand());
+    }
+
+    #[test]
+    fn picker_slots_keep_colors_beyond_the_slot_count() {
+        let colors: Vec<u32> = (0..20).map(|index| rgb(index, 0, 0)).collect();
+        let mut palette = Palette { colors: colors.clone(), ..Default::default() };
+        let mut slots = palette.to_picker_slots();
+        assert_eq!(slots[..], colors[..PICKER_SLOTS]);
+        slots[15] = rgb(0, 0, 99);
+        palette.update_from_picker_slots(&slots);
+        assert_eq!(palette.colors.len(), 20);
+        assert_eq!(palette.colors[15], rgb(0, 0, 99));
+        assert_eq!(palette.colors[16..], colors[16..]);
+    }
+
+    #[test]
+    fn files_round_trip_and_take_the_name_from_the_file() {
+        let dir = std::env::temp_dir().join(format!("mpr-palette-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in ["brand.gpl", "brand.json"] {
+            let path = dir.join(file);
+            export_palette(&brand(), &path).unwrap();
+            assert_eq!(import_palette(&path), Ok(brand()));
+        }
+        let path = dir.join("unnamed.json");
+        std::fs::write(&path, r##"{"colors": ["#000000"]}"##).unwrap();
+        assert_eq!(import_palette(&path).unwrap().name, "unnamed");
+        let error = import_palette(&dir.join("missing.gpl")).unwrap_err();
+        assert!(error.contains("missing.gpl"), "{}", error);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
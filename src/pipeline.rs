@@ -0,0 +1,206 @@
+// Coordinate pipeline: turns a sampled cursor position into the numbers shown
+// in the icon, according to the display mode and unit
+
+use crate::calibration::ActiveCalibration;
+use crate::config::{DisplayMode, Origin, Unit};
+use crate::dpi::to_logical;
+use crate::layout::DesktopLayout;
+use crate::reference::{Reference, ReferencePosition, ReferenceSource};
+use crate::units;
+use crate::window::WindowInfo;
+
+/// Everything besides the cursor position that the display modes depend on
+#[derive(Clone, Copy, Debug)]
+pub struct Context<'a> {
+    /// Point relative coordinates are measured from
+    pub origin: Origin,
+    pub layout: &'a DesktopLayout,
+    /// Top-level window the window modes measure from, only queried for the
+    /// modes that need it: the one under the cursor, or the configured window
+    /// of the reference mode
+    pub window: Option<&'a WindowInfo>,
+    pub calibration: Option<&'a ActiveCalibration>,
+    pub reference: Option<&'a Reference>,
+}
+
+/// What the icon shows for a cursor position
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DisplayedPosition {
+    /// Negative in relative modes when left of or above the reference point
+    pub x: i32,
+    pub y: i32,
+    /// 1-based number of the monitor the coordinates are relative to
+    pub monitor_index: Option<usize>,
+    /// Unit of `x` and `y`; pixels when the physical size of the monitor is unknown
+    pub unit: Unit,
+    /// Implied decimal places of `x` and `y`, e.g. 1 for tenths of an inch
+    pub decimals: u32,
+}
+
+/// Coordinates shown for the cursor at virtual-desktop position `(x, y)`.
+/// Physical units are converted with the pixel size of the monitor under the
+/// cursor, and do not apply to the logical, calibrated and reference modes,
+/// whose coordinates are not screen pixels.
+pub fn display_coordinates(mode: DisplayMode, unit: Unit, x: i32, y: i32, context: &Context) -> DisplayedPosition {
+    let position = mode_coordinates(mode, x, y, context);
+    if unit == Unit::Pixels || matches!(mode, DisplayMode::Logical | DisplayMode::Calibrated | DisplayMode::Reference) {
+        return position;
+    }
+    match context.layout.locate(x, y).and_then(|located| located.monitor.pixel_size_mm()) {
+        Some((width_mm, height_mm)) => DisplayedPosition {
+            x: units::from_pixels(position.x, width_mm, unit),
+            y: units::from_pixels(position.y, height_mm, unit),
+            unit,
+            decimals: units::decimals(unit),
+            ..position
+        },
+        None => position,
+    }
+}
+
+/// Pixel coordinates of the display mode
+fn mode_coordinates(mode: DisplayMode, x: i32, y: i32, context: &Context) -> DisplayedPosition {
+    match mode {
+        // The color mode shows its swatch instead, positions only in the tooltip
+        DisplayMode::Absolute | DisplayMode::Color => DisplayedPosition { x, y, ..Default::default() },
+        DisplayMode::Relative => DisplayedPosition { x: x - context.origin.x, y: y - context.origin.y, ..Default::default() },
+        DisplayMode::Monitor => match context.layout.locate(x, y) {
+            Some(position) => DisplayedPosition { x: position.x, y: position.y, monitor_index: Some(position.index), ..Default::default() },
+            None => DisplayedPosition { x, y, ..Default::default() },
+        },
+        DisplayMode::Logical => match context.layout.locate(x, y) {
+            Some(position) => {
+                let (rect, dpi) = (position.monitor.rect, position.monitor.dpi);
+                DisplayedPosition { x: to_logical(x, rect.left, dpi), y: to_logical(y, rect.top, dpi), ..Default::default() }
+            }
+            None => DisplayedPosition { x, y, ..Default::default() },
+        },
+        DisplayMode::Client | DisplayMode::Window => {
+            let (x, y) = match context.window {
+                Some(window) if mode == DisplayMode::Client => window.client_offset(x, y),
+                Some(window) => window.frame_offset(x, y),
+                None => (x, y),
+            };
+            DisplayedPosition { x, y, ..Default::default() }
+        }
+        DisplayMode::Calibrated => match context.calibration.and_then(|calibration| Some((calibration.decimals, calibration.apply(x, y)?))) {
+            Some((decimals, (x, y))) => DisplayedPosition { x, y, decimals, ..Default::default() },
+            None => DisplayedPosition { x, y, ..Default::default() },
+        },
+        DisplayMode::Reference => match reference_position(x, y, context) {
+            Some(position) => DisplayedPosition { x: position.x, y: position.y, ..Default::default() },
+            None => DisplayedPosition { x, y, ..Default::default() },
+        },
+    }
+}
+
+/// `(x, y)` in the reference resolution, scaled from the monitor under the
+/// cursor or from the client area of the window in the context
+pub fn reference_position(x: i32, y: i32, context: &Context) -> Option<ReferencePosition> {
+    let reference = context.reference?;
+    let area = match reference.source {
+        ReferenceSource::Monitor => context.layout.locate(x, y)?.monitor.rect,
+        ReferenceSource::Window => context.window?.client,
+    };
+    reference.scale(x, y, area)
+}
+
+/// Signed value with an explicit `+` for positive values, e.g. `+15` or `+1.5 in`
+pub fn format_signed(value: i32, unit: Unit) -> String {
+    let text = units::format_with_symbol(value, unit);
+    if value >= 0 {
+        format!("+{}", text)
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{Monitor, Rect};
+
+    /// Primary monitor at 100% and a 3840 x 2160 monitor at 150% to its left
+    fn layout() -> DesktopLayout {
+        let rect = Rect { left: -3840, top: -540, right: 0, bottom: 1620 };
+        DesktopLayout {
+            monitors: vec![
+                Monitor { rect: Rect { left: 0, top: 0, right: 1920, bottom: 1080 }, dpi: 96, ..Default::default() },
+                Monitor { rect, work_area: rect, dpi: 144, size_mm: Some((960, 540)), ..Default::default() },
+            ],
+        }
+    }
+
+    fn context(layout: &DesktopLayout) -> Context<'_> {
+        Context { origin: Origin::default(), layout, window: None, calibration: None, reference: None }
+    }
+
+    #[test]
+    fn relative_coordinates_are_signed_around_the_origin() {
+        let layout = layout();
+        let context = Context { origin: Origin { x: 100, y: 200 }, ..context(&layout) };
+        let position = |x, y| {
+            let position = display_coordinates(DisplayMode::Relative, Unit::Pixels, x, y, &context);
+            (format_signed(position.x, position.unit), format_signed(position.y, position.unit))
+        };
+        assert_eq!(position(90, 250), ("-10".to_string(), "+50".to_string()));
+        assert_eq!(position(100, 200), ("+0".to_string(), "+0".to_string()));
+        assert_eq!(position(-3000, 0), ("-3100".to_string(), "-200".to_string()));
+    }
+
+    #[test]
+    fn logical_coordinates_scale_from_the_monitor_origin() {
+        let layout = layout();
+        let position = display_coordinates(DisplayMode::Logical, Unit::Pixels, -840, 960, &context(&layout));
+        assert_eq!((position.x, position.y), (-1840, 460));
+        let position = display_coordinates(DisplayMode::Logical, Unit::Pixels, 100, 200, &context(&layout));
+        assert_eq!((position.x, position.y), (100, 200));
+    }
+
+    #[test]
+    fn logical_coordinates_ignore_physical_units() {
+        let layout = layout();
+        let position = display_coordinates(DisplayMode::Logical, Unit::Millimeters, -840, 960, &context(&layout));
+        assert_eq!((position.x, position.y, position.unit), (-1840, 460, Unit::Pixels));
+    }
+
+    #[test]
+    fn reference_coordinates_ignore_physical_units() {
+        let layout = layout();
+        let reference = Reference { width: 1280, height: 720, ..Default::default() };
+        let context = Context { reference: Some(&reference), ..context(&layout) };
+        for unit in [Unit::Pixels, Unit::Millimeters, Unit::Inches] {
+            let position = display_coordinates(DisplayMode::Reference, unit, -1920, 540, &context);
+            assert_eq!((position.x, position.y, position.unit, position.decimals), (640, 360, Unit::Pixels, 0));
+        }
+    }
+
+    #[test]
+    fn monitor_coordinates_convert_to_physical_units() {
+        let layout = layout();
+        let position = display_coordinates(DisplayMode::Monitor, Unit::Millimeters, -1920, 540, &context(&layout));
+        assert_eq!((position.x, position.y, position.unit, position.monitor_index), (480, 270, Unit::Millimeters, Some(2)));
+    }
+
+    #[test]
+    fn window_modes_measure_from_the_supplied_window() {
+        let layout = layout();
+        let window = WindowInfo {
+            frame: Rect { left: 100, top: 100, right: 900, bottom: 700 },
+            client: Rect { left: 108, top: 131, right: 892, bottom: 692 },
+            ..Default::default()
+        };
+        let with_window = Context { window: Some(&window), ..context(&layout) };
+        let position = |mode, x, y| {
+            let position = display_coordinates(mode, Unit::Pixels, x, y, &with_window);
+            (position.x, position.y)
+        };
+        assert_eq!(position(DisplayMode::Client, 500, 400), (392, 269));
+        assert_eq!(position(DisplayMode::Window, 500, 400), (400, 300));
+        // Over the title bar the client offset is negative
+        assert_eq!(position(DisplayMode::Client, 100, 110), (-8, -21));
+        assert_eq!(position(DisplayMode::Window, 100, 110), (0, 10));
+        // Without a window the screen position is shown
+        assert_eq!(display_coordinates(DisplayMode::Client, Unit::Pixels, 500, 400, &context(&layout)).x, 500);
+    }
+}
@@ -0,0 +1,215 @@
+// Captured points: a persistent list of positions and its export formats
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Number of points listed in the "Recent Points" submenu
+pub const RECENT_POINTS: usize = 20;
+
+/// Position stored with the capture action
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapturedPoint {
+    pub x: i32,
+    pub y: i32,
+    /// Local time of the capture, `YYYY-MM-DD HH:MM:SS`
+    pub time: String,
+    #[serde(default)]
+    pub label: String,
+}
+
+impl CapturedPoint {
+    /// Menu text, e.g. `640, 480  Submit button  (14:02:31)`
+    pub fn menu_text(&self) -> String {
+        let time = self.time.split_once(' ').map_or(self.time.as_str(), |(_, time)| time);
+        let mut text = format!("{}, {}", self.x, self.y);
+        if !self.label.is_empty() {
+            let _ = write!(text, "  {}", self.label);
+        }
+        let _ = write!(text, "  ({})", time);
+        // A single `&` would underline the next character
+        text.replace('&', "&&")
+    }
+}
+
+/// Export file formats, chosen by file extension
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Markdown,
+}
+
+impl ExportFormat {
+    /// `.json` and `.md` files get their format, everything else is CSV
+    pub fn from_path(path: &Path) -> ExportFormat {
+        match path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("json") => ExportFormat::Json,
+            Some("md" | "markdown") => ExportFormat::Markdown,
+            _ => ExportFormat::Csv,
+        }
+    }
+}
+
+/// File the points are kept in, next to the configuration file
+pub fn points_path(config_path: &Path) -> PathBuf {
+    config_path.with_file_name("points.json")
+}
+
+/// Contents of the points file
+#[derive(Default, Serialize, Deserialize)]
+struct PointsFile {
+    #[serde(default)]
+    points: Vec<CapturedPoint>,
+}
+
+/// Reads the captured points; a missing file is an empty list
+pub fn load_points(path: &Path) -> Result<Vec<CapturedPoint>, String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    serde_json::from_str::<PointsFile>(&text)
+        .map(|file| file.points)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn save_points(points: &[CapturedPoint], path: &Path) -> Result<(), String> {
+    let file = PointsFile { points: points.to_vec() };
+    let text = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    std::fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Text of the points in `format`, numbered from 1 in capture order
+pub fn export_text(points: &[CapturedPoint], format: ExportFormat) -> String {
+    let mut text = String::new();
+    match format {
+        ExportFormat::Csv => {
+            text.push_str("index,x,y,time,label\r\n");
+            for (index, point) in points.iter().enumerate() {
+                let _ = write!(text, "{},{},{},{},{}\r\n", index + 1, point.x, point.y, point.time, csv_field(&point.label));
+            }
+        }
+        ExportFormat::Json => {
+            text = serde_json::to_string_pretty(points).unwrap_or_default();
+            text.push('\n');
+        }
+        ExportFormat::Markdown => {
+            text.push_str("| # | X | Y | Time | Label |\n|---:|---:|---:|---|---|\n");
+            for (index, point) in points.iter().enumerate() {
+                // Line breaks would end the table row
+                let label = point.label.replace('|', "\\|").replace("\r\n", "<br>").replace(['\r', '\n'], "<br>");
+                let _ = writeln!(text, "| {} | {} | {} | {} | {} |", index + 1, point.x, point.y, point.time, label);
+            }
+        }
+    }
+    text
+}
+
+/// Writes the points to `path` in the format of its extension
+pub fn export_points(points: &[CapturedPoint], path: &Path) -> Result<(), String> {
+    std::fs::write(path, export_text(points, ExportFormat::from_path(path)))
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: i32, y: i32, label: &str) -> CapturedPoint {
+        CapturedPoint { x, y, time: "2024-05-06 14:02:31".to_string(), label: label.to_string() }
+    }
+
+    fn points() -> Vec<CapturedPoint> {
+        vec![point(640, 480, "Submit button"), point(-1200, 35, "")]
+    }
+
+    #[test]
+    fn menu_texts_show_the_time_and_escape_ampersands() {
+        assert_eq!(point(640, 480, "Save & close").menu_text(), "640, 480  Save && close  (14:02:31)");
+        assert_eq!(point(-5, 7, "").menu_text(), "-5, 7  (14:02:31)");
+    }
+
+    #[test]
+    fn formats_follow_the_extension() {
+        assert_eq!(ExportFormat::from_path(Path::new("points.json")), ExportFormat::Json);
+        assert_eq!(ExportFormat::from_path(Path::new("POINTS.MD")), ExportFormat::Markdown);
+        assert_eq!(ExportFormat::from_path(Path::new("points.markdown")), ExportFormat::Markdown);
+        assert_eq!(ExportFormat::from_path(Path::new("points.csv")), ExportFormat::Csv);
+        assert_eq!(ExportFormat::from_path(Path::new("points")), ExportFormat::Csv);
+    }
+
+    #[test]
+    fn csv_export_numbers_points_from_one() {
+        assert_eq!(
+            export_text(&points(), ExportFormat::Csv),
+            "index,x,y,time,label\r\n1,640,480,2024-05-06 14:02:31,Submit button\r\n2,-1200,35,2024-05-06 14:02:31,\r\n"
+        );
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("plain text"), "plain text");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+    }
+
+    #[test]
+    fn json_export_round_trips() {
+        let text = export_text(&points(), ExportFormat::Json);
+        assert!(text.ends_with("]\n"));
+        assert_eq!(serde_json::from_str::<Vec<CapturedPoint>>(&text).unwrap(), points());
+    }
+
+    #[test]
+    fn markdown_export_escapes_pipes_and_line_breaks() {
+        let points = [point(1, 2, "a | b"), point(3, 4, "two\nlines\r\nthree")];
+        assert_eq!(
+            export_text(&points, ExportFormat::Markdown),
+            "| # | X | Y | Time | Label |\n|---:|---:|---:|---|---|\n\
+             | 1 | 1 | 2 | 2024-05-06 14:02:31 | a \\| b |\n\
+             | 2 | 3 | 4 | 2024-05-06 14:02:31 | two<br>lines<br>three |\n"
+        );
+    }
+
+    #[test]
+    fn points_files_round_trip() {
+        let dir = std::env::temp_dir().join(format!("mpr-points-test-{}", std::process::id()));
+        let path = points_path(&dir.join("nested").join("mpr.toml"));
+        assert_eq!(path, dir.join("nested").join("points.json"));
+
+        // Missing files are empty lists, missing directories are created
+        assert_eq!(load_points(&path), Ok(Vec::new()));
+        save_points(&points(), &path).unwrap();
+        assert_eq!(load_points(&path), Ok(points()));
+
+        // Labels are optional in the file
+        std::fs::write(&path, r#"{"points": [{"x": 1, "y": 2, "time": "2024-05-06 14:02:31"}]}"#).unwrap();
+        assert_eq!(load_points(&path), Ok(vec![point(1, 2, "")]));
+
+        std::fs::write(&path, "not json").unwrap();
+        let error = load_points(&path).unwrap_err();
+        assert!(error.starts_with(&path.display().to_string()), "{}", error);
+
+        let export = dir.join("points.md");
+        export_points(&points(), &export).unwrap();
+        assert_eq!(std::fs::read_to_string(&export).unwrap(), export_text(&points(), ExportFormat::Markdown));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,152 @@
+// Details about a cursor position, as filled into templates
+
+use crate::color::{components, to_hex, to_hsl};
+use crate::dpi::{scale_percent, to_logical};
+use crate::units::format_fixed;
+
+/// Placeholders available in tooltip and copy templates
+pub const PLACEHOLDERS: &[&str] = &[
+    "x", "y", "rel_x", "rel_y", "monitor", "monitor_index", "monitor_x", "monitor_y", "work_x", "work_y", "monitor_mm", "native_resolution", "mm_x", "mm_y", "logical_x", "logical_y", "cal_x", "cal_y", "ref_x", "ref_y", "window_title", "window_class", "client_x", "client_y", "window_x", "window_y", "dpi", "scale", "color", "r", "g", "b", "rgb", "hue", "saturation", "lightness", "hsl",
+];
+
+/// Placeholders that need the pixel color under the cursor
+pub const COLOR_PLACEHOLDERS: &[&str] = &["color", "r", "g", "b", "rgb", "hue", "saturation", "lightness", "hsl"];
+
+/// Placeholders that need the top-level window under the cursor
+pub const WINDOW_PLACEHOLDERS: &[&str] = &["window_title", "window_class", "client_x", "client_y", "window_x", "window_y"];
+
+/// Placeholders that need the window of the reference display mode
+pub const REFERENCE_PLACEHOLDERS: &[&str] = &["ref_x", "ref_y"];
+
+/// Details about a cursor position
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PositionDetails {
+    /// Virtual-desktop coordinates
+    pub x: i32,
+    pub y: i32,
+    /// Coordinates relative to the configured origin
+    pub rel_x: i32,
+    pub rel_y: i32,
+    pub monitor_name: String,
+    /// 1-based position of the monitor in the system's enumeration order
+    pub monitor_index: usize,
+    /// Coordinates relative to the top left corner of the monitor
+    pub monitor_x: i32,
+    pub monitor_y: i32,
+    /// Coordinates relative to the top left corner of the monitor's work area
+    pub work_x: i32,
+    pub work_y: i32,
+    /// Physical size of the monitor and the resolution it was built for
+    pub monitor_size_mm: Option<(u32, u32)>,
+    pub native_resolution: Option<(u32, u32)>,
+    /// Millimetres from the top left corner of the monitor, if its size is known
+    pub monitor_mm: Option<(i32, i32)>,
+    pub dpi: u32,
+    /// Position in the active calibration with its implied decimal places, as
+    /// shown by the calibrated display mode
+    pub calibrated: Option<(i32, i32)>,
+    pub calibration_decimals: u32,
+    /// Position in the reference resolution, if the reference area was found
+    pub reference: Option<(i32, i32)>,
+    /// Top-level window under the cursor
+    pub window_title: String,
+    pub window_class: String,
+    /// Coordinates relative to the window's client area and frame
+    pub client_x: i32,
+    pub client_y: i32,
+    pub window_x: i32,
+    pub window_y: i32,
+    /// Color of the pixel under the cursor, if it could be read
+    pub color: Option<u32>,
+}
+
+impl PositionDetails {
+    /// Text of a placeholder
+    pub fn value(&self, name: &str) -> String {
+        let channel = |index: usize| {
+            self.color
+                .map(|color| {
+                    let (r, g, b) = components(color);
+                    [r, g, b][index].to_string()
+                })
+                .unwrap_or_else(|| "-".into())
+        };
+        let hsl = |index: usize| {
+            self.color
+                .map(|color| {
+                    let (hue, saturation, lightness) = to_hsl(color);
+                    [hue, saturation, lightness][index].to_string()
+                })
+                .unwrap_or_else(|| "-".into())
+        };
+        let size = |size: Option<(u32, u32)>, suffix: &str| {
+            size.map(|(width, height)| format!("{} x {}{}", width, height, suffix)).unwrap_or_else(|| "-".into())
+        };
+        // Values that may be unknown show a dash
+        let optional = |value: Option<i32>| value.map(|value| value.to_string()).unwrap_or_else(|| "-".into());
+        let calibrated = |value: Option<i32>| {
+            value.map(|value| format_fixed(value, self.calibration_decimals)).unwrap_or_else(|| "-".into())
+        };
+        match name {
+            "x" => self.x.to_string(),
+            "y" => self.y.to_string(),
+            "rel_x" => self.rel_x.to_string(),
+            "rel_y" => self.rel_y.to_string(),
+            "monitor" => self.monitor_name.clone(),
+            "monitor_index" => self.monitor_index.to_string(),
+            "monitor_x" => self.monitor_x.to_string(),
+            "monitor_y" => self.monitor_y.to_string(),
+            "work_x" => self.work_x.to_string(),
+            "work_y" => self.work_y.to_string(),
+            "monitor_mm" => size(self.monitor_size_mm, " mm"),
+            "native_resolution" => size(self.native_resolution, ""),
+            "mm_x" => optional(self.monitor_mm.map(|(x, _)| x)),
+            "mm_y" => optional(self.monitor_mm.map(|(_, y)| y)),
+            // The monitor origin is where its offset starts
+            "logical_x" => to_logical(self.x, self.x - self.monitor_x, self.dpi).to_string(),
+            "logical_y" => to_logical(self.y, self.y - self.monitor_y, self.dpi).to_string(),
+            "cal_x" => calibrated(self.calibrated.map(|(x, _)| x)),
+            "cal_y" => calibrated(self.calibrated.map(|(_, y)| y)),
+            "ref_x" => optional(self.reference.map(|(x, _)| x)),
+            "ref_y" => optional(self.reference.map(|(_, y)| y)),
+            "window_title" => self.window_title.clone(),
+            "window_class" => self.window_class.clone(),
+            "client_x" => self.client_x.to_string(),
+            "client_y" => self.client_y.to_string(),
+            "window_x" => self.window_x.to_string(),
+            "window_y" => self.window_y.to_string(),
+            "dpi" => self.dpi.to_string(),
+            "scale" => format!("{}%", scale_percent(self.dpi)),
+            "color" => self.color.map(to_hex).unwrap_or_else(|| "-".into()),
+            "r" => channel(0),
+            "g" => channel(1),
+            "b" => channel(2),
+            "rgb" => match self.color.map(components) {
+                Some((r, g, b)) => format!("rgb({}, {}, {})", r, g, b),
+                None => "-".into(),
+            },
+            "hue" => hsl(0),
+            "saturation" => hsl(1),
+            "lightness" => hsl(2),
+            "hsl" => match self.color.map(to_hsl) {
+                Some((hue, saturation, lightness)) => format!("hsl({}, {}%, {}%)", hue, saturation, lightness),
+                None => "-".into(),
+            },
+            _ => String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logical_values_scale_from_the_monitor_origin() {
+        // 150% monitor whose top left corner is at -3840, -540
+        let details = PositionDetails { x: -840, y: 960, monitor_x: 3000, monitor_y: 1500, dpi: 144, ..Default::default() };
+        assert_eq!(details.value("logical_x"), "-1840");
+        assert_eq!(details.value("logical_y"), "460");
+        assert_eq!(details.value("scale"), "150%");
+    }
+}
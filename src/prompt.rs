@@ -0,0 +1,157 @@
+// Small modal window asking for one line of text
+
+use std::ffi::c_void;
+use std::ptr::null_mut;
+
+use windows::core::{w, HSTRING};
+use windows::Win32::Foundation::*;
+use windows::Win32::Graphics::Gdi::*;
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::Controls::EM_SETSEL;
+use windows::Win32::UI::Input::KeyboardAndMouse::SetFocus;
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+use crate::i18n::{tr, Message};
+
+const ID_TEXT_EDIT: usize = 4000;
+
+/// Layout in pixels
+const CLIENT_WIDTH: i32 = 320;
+const CLIENT_HEIGHT: i32 = 110;
+const MARGIN: i32 = 15;
+const BUTTON_WIDTH: i32 = 90;
+const BUTTON_HEIGHT: i32 = 28;
+
+static mut PROMPT_HWND: HWND = HWND(null_mut());
+static mut EDIT_HWND: HWND = HWND(null_mut());
+/// Entered text, set when the prompt is confirmed
+static mut RESULT: Option<String> = None;
+
+/// Asks for a line of text, starting with `initial`. Blocks until the prompt
+/// is closed and returns `None` if it was cancelled or is already open.
+pub unsafe fn prompt_text(title: &str, label: &str, initial: &str) -> Option<String> {
+    if !PROMPT_HWND.is_invalid() {
+        let _ = SetForegroundWindow(PROMPT_HWND);
+        return None;
+    }
+    let hinstance: HINSTANCE = GetModuleHandleW(None).ok()?.into();
+    let hwnd = create_prompt_window(hinstance, title, label).ok()?;
+    PROMPT_HWND = hwnd;
+    RESULT = None;
+
+    let _ = SetWindowTextW(EDIT_HWND, &HSTRING::from(initial));
+    SendMessageW(EDIT_HWND, EM_SETSEL, Some(WPARAM(0)), Some(LPARAM(-1)));
+    let _ = ShowWindow(hwnd, SW_SHOW);
+    let _ = SetForegroundWindow(hwnd);
+    let _ = SetFocus(Some(EDIT_HWND));
+
+    let mut msg = MSG::default();
+    while !PROMPT_HWND.is_invalid() {
+        if !GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            // Leaves WM_QUIT to the main message loop
+            PostQuitMessage(msg.wParam.0 as i32);
+            let _ = DestroyWindow(PROMPT_HWND);
+            break;
+        }
+        if IsDialogMessageW(PROMPT_HWND, &msg).as_bool() || crate::settings::translate_settings_message(&msg) {
+            continue;
+        }
+        let _ = TranslateMessage(&msg);
+        DispatchMessageW(&msg);
+    }
+    RESULT.take()
+}
+
+unsafe fn create_prompt_window(hinstance: HINSTANCE, title: &str, label: &str) -> Result<HWND, windows::core::Error> {
+    let class_name = w!("MPR_Prompt");
+
+    let wc = WNDCLASSW {
+        lpfnWndProc: Some(prompt_wndproc),
+        hInstance: hinstance,
+        lpszClassName: class_name,
+        hbrBackground: GetSysColorBrush(COLOR_WINDOW),
+        ..Default::default()
+    };
+
+    if RegisterClassW(&wc) == 0 && GetLastError() != ERROR_CLASS_ALREADY_EXISTS {
+        return Err(windows::core::Error::from_win32());
+    }
+
+    let style = WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU;
+    let mut rect = RECT { left: 0, top: 0, right: CLIENT_WIDTH, bottom: CLIENT_HEIGHT };
+    AdjustWindowRectEx(&mut rect, style, false, WS_EX_TOPMOST)?;
+
+    let hwnd = CreateWindowExW(
+        WS_EX_TOPMOST, class_name, &HSTRING::from(title), style,
+        CW_USEDEFAULT, CW_USEDEFAULT, rect.right - rect.left, rect.bottom - rect.top,
+        None, None, Some(hinstance), None,
+    )?;
+
+    let font = HFONT(GetStockObject(DEFAULT_GUI_FONT).0);
+    let create = |class, text: &HSTRING, style: WINDOW_STYLE, x, y, width, height, id: usize| {
+        let control = CreateWindowExW(
+            Default::default(), class, text, WS_CHILD | WS_VISIBLE | style,
+            x, y, width, height,
+            Some(hwnd),
+            (id != 0).then_some(HMENU(id as *mut c_void)),
+            Some(hinstance), None,
+        );
+        if let Ok(control) = control {
+            SendMessageW(control, WM_SETFONT, Some(WPARAM(font.0 as usize)), Some(LPARAM(1)));
+        }
+        control
+    };
+
+    create(w!("STATIC"), &HSTRING::from(label), WINDOW_STYLE(0), MARGIN, MARGIN, CLIENT_WIDTH - 2 * MARGIN, 20, 0)?;
+    EDIT_HWND = create(
+        w!("EDIT"), &HSTRING::new(), WS_TABSTOP | WS_BORDER | WINDOW_STYLE(ES_AUTOHSCROLL as u32),
+        MARGIN, MARGIN + 22, CLIENT_WIDTH - 2 * MARGIN, 24, ID_TEXT_EDIT,
+    )?;
+    let button_y = CLIENT_HEIGHT - MARGIN - BUTTON_HEIGHT;
+    create(
+        w!("BUTTON"), &HSTRING::from(tr(Message::Ok)), WS_TABSTOP | WINDOW_STYLE(BS_DEFPUSHBUTTON as u32),
+        CLIENT_WIDTH - MARGIN - 2 * BUTTON_WIDTH - 10, button_y, BUTTON_WIDTH, BUTTON_HEIGHT, IDOK.0 as usize,
+    )?;
+    create(
+        w!("BUTTON"), &HSTRING::from(tr(Message::Cancel)), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32),
+        CLIENT_WIDTH - MARGIN - BUTTON_WIDTH, button_y, BUTTON_WIDTH, BUTTON_HEIGHT, IDCANCEL.0 as usize,
+    )?;
+
+    Ok(hwnd)
+}
+
+/// Window procedure of the prompt
+extern "system" fn prompt_wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    unsafe {
+        match msg {
+            WM_CLOSE => {
+                let _ = DestroyWindow(hwnd);
+                LRESULT(0)
+            }
+
+            WM_DESTROY => {
+                PROMPT_HWND = HWND(null_mut());
+                EDIT_HWND = HWND(null_mut());
+                LRESULT(0)
+            }
+
+            WM_COMMAND => {
+                match (wparam.0 & 0xFFFF) as i32 {
+                    id if id == IDOK.0 => {
+                        let mut buffer = vec![0u16; GetWindowTextLengthW(EDIT_HWND) as usize + 1];
+                        let len = GetWindowTextW(EDIT_HWND, &mut buffer) as usize;
+                        RESULT = Some(String::from_utf16_lossy(&buffer[..len]).trim().to_string());
+                        let _ = DestroyWindow(hwnd);
+                    }
+                    id if id == IDCANCEL.0 => {
+                        let _ = DestroyWindow(hwnd);
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+}
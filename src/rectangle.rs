@@ -0,0 +1,154 @@
+// Rectangles marked by two corners: size, area and aspect ratio
+
+use crate::copy::{resolve_format, CopyFormat};
+use crate::template::Template;
+
+/// Placeholders available in rectangle copy formats
+pub const PLACEHOLDERS: &[&str] = &["x", "y", "width", "height", "area", "aspect", "ratio"];
+
+/// Rectangle format used when none is configured
+pub const DEFAULT_RECTANGLE_FORMAT: &str = "plain";
+
+/// Built-in rectangle formats in menu order
+pub const RECTANGLE_FORMATS: &[CopyFormat] = &[
+    CopyFormat { name: "plain", label: "x,y,w,h", template: "{x},{y},{width},{height}" },
+    CopyFormat { name: "css", label: "CSS left/top/width/height", template: "left: {x}px; top: {y}px; width: {width}px; height: {height}px;" },
+    // X geometry, as taken by ImageMagick, maim, scrot and ffmpeg
+    CopyFormat { name: "geometry", label: "Region wxh+x+y", template: "{width}x{height}+{x}+{y}" },
+];
+
+/// Terms of an aspect ratio are shown reduced, like 16:9, up to this value
+const MAX_ASPECT_TERM: u32 = 99;
+
+/// Rectangle on the virtual desktop
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rectangle {
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rectangle {
+    /// Rectangle between two opposite corners in any order; both corner
+    /// pixels belong to it, so equal corners give a 1x1 rectangle
+    pub fn from_corners(a: (i32, i32), b: (i32, i32)) -> Rectangle {
+        Rectangle {
+            left: a.0.min(b.0),
+            top: a.1.min(b.1),
+            width: a.0.abs_diff(b.0) + 1,
+            height: a.1.abs_diff(b.1) + 1,
+        }
+    }
+
+    pub fn area(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    /// Width divided by height
+    pub fn ratio(&self) -> f64 {
+        self.width as f64 / self.height as f64
+    }
+
+    /// Aspect ratio like `16:9`, or `1.78:1` when the reduced terms are large
+    pub fn aspect(&self) -> String {
+        // Only empty rectangles have no common divisor
+        let divisor = gcd(self.width, self.height).max(1);
+        let (width, height) = (self.width / divisor, self.height / divisor);
+        if width <= MAX_ASPECT_TERM && height <= MAX_ASPECT_TERM {
+            format!("{}:{}", width, height)
+        } else {
+            format!("{:.2}:1", self.ratio())
+        }
+    }
+
+    /// Text of a placeholder; the ratio with two decimal places
+    pub fn value(&self, name: &str) -> String {
+        match name {
+            "x" => self.left.to_string(),
+            "y" => self.top.to_string(),
+            "width" => self.width.to_string(),
+            "height" => self.height.to_string(),
+            "area" => self.area().to_string(),
+            "aspect" => self.aspect(),
+            "ratio" => format!("{:.2}", self.ratio()),
+            _ => String::new(),
+        }
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Template of a configured rectangle format: the name of a built-in format
+/// or a custom template containing at least one placeholder
+pub fn resolve_rectangle_format(format: &str) -> Result<Template, String> {
+    resolve_format(format, "rectangle format", RECTANGLE_FORMATS, PLACEHOLDERS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rectangle(width: u32, height: u32) -> Rectangle {
+        Rectangle { left: 0, top: 0, width, height }
+    }
+
+    #[test]
+    fn corners_can_be_given_in_any_order() {
+        let expected = Rectangle { left: -10, top: 20, width: 111, height: 31 };
+        assert_eq!(Rectangle::from_corners((-10, 20), (100, 50)), expected);
+        assert_eq!(Rectangle::from_corners((100, 50), (-10, 20)), expected);
+        assert_eq!(Rectangle::from_corners((100, 20), (-10, 50)), expected);
+        assert_eq!(Rectangle::from_corners((-10, 50), (100, 20)), expected);
+    }
+
+    #[test]
+    fn equal_corners_give_one_pixel() {
+        let rectangle = Rectangle::from_corners((5, 7), (5, 7));
+        assert_eq!(rectangle, Rectangle { left: 5, top: 7, width: 1, height: 1 });
+        assert_eq!((rectangle.area(), rectangle.aspect()), (1, "1:1".to_string()));
+        // A single row
+        assert_eq!(Rectangle::from_corners((0, 3), (9, 3)).aspect(), "10:1");
+    }
+
+    #[test]
+    fn aspect_ratios_are_reduced() {
+        assert_eq!(rectangle(1920, 1080).aspect(), "16:9");
+        assert_eq!(rectangle(1280, 1024).aspect(), "5:4");
+        assert_eq!(rectangle(2560, 1080).aspect(), "64:27");
+        assert_eq!(rectangle(300, 300).aspect(), "1:1");
+        // Large terms fall back to a decimal ratio
+        assert_eq!(rectangle(1366, 768).aspect(), "1.78:1");
+        assert_eq!(rectangle(101, 1).aspect(), "101.00:1");
+        assert_eq!(rectangle(0, 0).aspect(), "0:0");
+    }
+
+    #[test]
+    fn values_fill_the_placeholders() {
+        let rectangle = Rectangle { left: -10, top: 20, width: 1920, height: 1080 };
+        let values: Vec<_> = PLACEHOLDERS.iter().map(|name| rectangle.value(name)).collect();
+        assert_eq!(values, ["-10", "20", "1920", "1080", "2073600", "16:9", "1.78"]);
+        assert_eq!(rectangle.value("nope"), "");
+    }
+
+    #[test]
+    fn formats_resolve_built_in_names_and_templates() {
+        for format in RECTANGLE_FORMATS {
+            assert!(resolve_rectangle_format(format.name).is_ok(), "{}", format.name);
+        }
+        let rectangle = Rectangle { left: 1, top: 2, width: 3, height: 4 };
+        let render = |format| resolve_rectangle_format(format).unwrap().render(|name| rectangle.value(name));
+        assert_eq!(render("geometry"), "3x4+1+2");
+        assert_eq!(render("{aspect}"), "3:4");
+        assert!(RECTANGLE_FORMATS.iter().any(|format| format.name == DEFAULT_RECTANGLE_FORMAT));
+    }
+
+    #[test]
+    fn unknown_names_are_reported_as_rectangle_formats() {
+        let error = resolve_rectangle_format("svg").unwrap_err();
+        assert!(error.starts_with("Unknown rectangle format \"svg\""), "{}", error);
+        assert!(error.ends_with("a template like \"{x},{y},{width},{height}\""), "{}", error);
+    }
+}
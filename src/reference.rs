@@ -0,0 +1,202 @@
+// Scaling of positions to a fixed reference resolution, as used by games and
+// kiosk applications designed for one resolution
+
+use serde::{Deserialize, Serialize};
+
+use crate::layout::Rect;
+
+/// Area that is scaled to the reference resolution
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferenceSource {
+    /// The monitor under the cursor
+    #[default]
+    Monitor,
+    /// The client area of a window: the one named by `window_title`, or the
+    /// one under the cursor
+    Window,
+}
+
+/// How scaled positions are turned into whole numbers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    #[default]
+    Nearest,
+    /// Towards negative infinity, like integer division in most game code
+    Floor,
+    Ceil,
+}
+
+/// How an area with another aspect ratio than the reference is handled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Letterbox {
+    /// Scales width and height independently, distorting the image
+    #[default]
+    Stretch,
+    /// Scales uniformly to fit the area, centered with bars on two sides;
+    /// positions on the bars are outside the reference resolution
+    Fit,
+    /// Scales uniformly to cover the area, centered and cropped on two sides
+    Fill,
+}
+
+/// Reference resolution and how positions are scaled to it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Reference {
+    pub width: u32,
+    pub height: u32,
+    pub source: ReferenceSource,
+    /// Part of the title of the window used as source, empty for the window
+    /// under the cursor
+    pub window_title: String,
+    pub rounding: Rounding,
+    pub letterbox: Letterbox,
+}
+
+impl Default for Reference {
+    fn default() -> Self {
+        Reference {
+            width: 1920,
+            height: 1080,
+            source: ReferenceSource::Monitor,
+            window_title: String::new(),
+            rounding: Rounding::Nearest,
+            letterbox: Letterbox::Stretch,
+        }
+    }
+}
+
+/// A position in the reference resolution
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReferencePosition {
+    pub x: i32,
+    pub y: i32,
+    /// Whether the position is on the reference image, false on letterbox bars
+    pub inside: bool,
+}
+
+impl Reference {
+    /// Virtual-desktop position `(x, y)` scaled from `area` to the reference
+    /// resolution, as `x * width / area width` when stretching; `None` for an
+    /// empty area or reference
+    pub fn scale(&self, x: i32, y: i32, area: Rect) -> Option<ReferencePosition> {
+        if area.width() <= 0 || area.height() <= 0 || self.width == 0 || self.height == 0 {
+            return None;
+        }
+        // Area pixels per reference unit
+        let scale_x = area.width() as f64 / self.width as f64;
+        let scale_y = area.height() as f64 / self.height as f64;
+        let (scale_x, scale_y) = match self.letterbox {
+            Letterbox::Stretch => (scale_x, scale_y),
+            Letterbox::Fit => (scale_x.min(scale_y), scale_x.min(scale_y)),
+            Letterbox::Fill => (scale_x.max(scale_y), scale_x.max(scale_y)),
+        };
+        // Offset of the centered image from the area, negative when cropped
+        let offset_x = (area.width() as f64 - self.width as f64 * scale_x) / 2.0;
+        let offset_y = (area.height() as f64 - self.height as f64 * scale_y) / 2.0;
+
+        let reference_x = ((x - area.left) as f64 - offset_x) / scale_x;
+        let reference_y = ((y - area.top) as f64 - offset_y) / scale_y;
+        let round = |value: f64| match self.rounding {
+            Rounding::Nearest => value.round(),
+            Rounding::Floor => value.floor(),
+            Rounding::Ceil => value.ceil(),
+        } as i32;
+        Some(ReferencePosition {
+            x: round(reference_x),
+            y: round(reference_y),
+            inside: (0.0..self.width as f64).contains(&reference_x) && (0.0..self.height as f64).contains(&reference_y),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference(width: u32, height: u32, letterbox: Letterbox) -> Reference {
+        Reference { width, height, letterbox, ..Default::default() }
+    }
+
+    fn area(left: i32, top: i32, width: i32, height: i32) -> Rect {
+        Rect { left, top, right: left + width, bottom: top + height }
+    }
+
+    fn scaled(reference: &Reference, x: i32, y: i32, area: Rect) -> (i32, i32, bool) {
+        let position = reference.scale(x, y, area).unwrap();
+        (position.x, position.y, position.inside)
+    }
+
+    #[test]
+    fn stretching_scales_each_axis_on_its_own() {
+        let wide = reference(1920, 1080, Letterbox::Stretch);
+        let area = area(100, 50, 1024, 768);
+        assert_eq!(scaled(&wide, 100, 50, area), (0, 0, true));
+        assert_eq!(scaled(&wide, 612, 434, area), (960, 540, true));
+        assert_eq!(scaled(&wide, 1123, 817, area), (1918, 1079, true));
+    }
+
+    #[test]
+    fn fitting_16_9_on_4_3_adds_bars_above_and_below() {
+        // 1024 x 576 image 96 px below the top of the area
+        let wide = reference(1920, 1080, Letterbox::Fit);
+        let area = area(100, 50, 1024, 768);
+        assert_eq!(scaled(&wide, 612, 434, area), (960, 540, true));
+        assert_eq!(scaled(&wide, 100, 146, area), (0, 0, true));
+        // On the bars
+        assert_eq!(scaled(&wide, 100, 60, area), (0, -161, false));
+        assert_eq!(scaled(&wide, 612, 722, area), (960, 1080, false));
+    }
+
+    #[test]
+    fn fitting_4_3_on_16_9_adds_bars_left_and_right() {
+        // 1440 x 1080 image 240 px right of the left edge
+        let classic = reference(1024, 768, Letterbox::Fit);
+        let area = area(0, 0, 1920, 1080);
+        assert_eq!(scaled(&classic, 240, 0, area), (0, 0, true));
+        assert_eq!(scaled(&classic, 1679, 1079, area), (1023, 767, true));
+        assert_eq!(scaled(&classic, 100, 540, area), (-100, 384, false));
+        assert_eq!(scaled(&classic, 1680, 540, area), (1024, 384, false));
+    }
+
+    #[test]
+    fn filling_crops_the_longer_side() {
+        // 16:9 on 4:3: 1365 x 768 image cropped left and right
+        let wide = reference(1920, 1080, Letterbox::Fill);
+        let area_4_3 = area(100, 50, 1024, 768);
+        assert_eq!(scaled(&wide, 100, 50, area_4_3), (240, 0, true));
+        assert_eq!(scaled(&wide, 612, 434, area_4_3), (960, 540, true));
+
+        // 4:3 on 16:9: 1920 x 1440 image cropped at the top and bottom
+        let classic = reference(1024, 768, Letterbox::Fill);
+        let area_16_9 = area(0, 0, 1920, 1080);
+        assert_eq!(scaled(&classic, 0, 0, area_16_9), (0, 96, true));
+        assert_eq!(scaled(&classic, 960, 540, area_16_9), (512, 384, true));
+    }
+
+    #[test]
+    fn rounding_modes_round_in_their_direction() {
+        let area = area(0, 0, 1920, 1080);
+        let rounded = |rounding, x| {
+            let reference = Reference { width: 1280, height: 720, rounding, ..Default::default() };
+            reference.scale(x, 0, area).unwrap().x
+        };
+        // 1000 / 1.5 = 666.67
+        assert_eq!([Rounding::Nearest, Rounding::Floor, Rounding::Ceil].map(|rounding| rounded(rounding, 1000)), [667, 666, 667]);
+        // -1 / 1.5 = -0.67, left of the area
+        assert_eq!([Rounding::Nearest, Rounding::Floor, Rounding::Ceil].map(|rounding| rounded(rounding, -1)), [-1, -1, 0]);
+        // Exact values stay
+        assert_eq!([Rounding::Nearest, Rounding::Floor, Rounding::Ceil].map(|rounding| rounded(rounding, 3)), [2, 2, 2]);
+    }
+
+    #[test]
+    fn empty_areas_and_references_scale_nothing() {
+        let wide = reference(1920, 1080, Letterbox::Fit);
+        assert_eq!(wide.scale(0, 0, area(0, 0, 0, 1080)), None);
+        assert_eq!(wide.scale(0, 0, area(0, 0, 1920, -5)), None);
+        assert_eq!(reference(0, 1080, Letterbox::Fit).scale(0, 0, area(0, 0, 1920, 1080)), None);
+    }
+}
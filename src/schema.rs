@@ -0,0 +1,350 @@
+// Declarative description of the settings shown in the settings window
+
+use toml::{Table, Value};
+
+use crate::config::{Config, MIN_INTERVAL_MS};
+use crate::copy::resolve_copy_format;
+use crate::hotkey::Hotkey;
+use crate::i18n::{tr, tr_args, Message};
+use crate::measure;
+use crate::rectangle::resolve_rectangle_format;
+use crate::tooltip;
+
+/// Section of the settings window a field is placed in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Group {
+    Appearance,
+    Display,
+    General,
+    Hotkeys,
+}
+
+impl Group {
+    pub fn label(self) -> &'static str {
+        match self {
+            Group::Appearance => tr(Message::GroupAppearance),
+            Group::Display => tr(Message::GroupDisplay),
+            Group::General => tr(Message::GroupGeneral),
+            Group::Hotkeys => tr(Message::GroupHotkeys),
+        }
+    }
+
+    /// Whether the fields of this group belong to the active profile rather
+    /// than to the configuration as a whole
+    pub fn in_profile(self) -> bool {
+        matches!(self, Group::Appearance | Group::Display)
+    }
+
+    /// Column of the settings window the group is placed in
+    pub fn column(self) -> usize {
+        match self {
+            Group::Appearance | Group::Display => 0,
+            Group::General => 1,
+            Group::Hotkeys => 2,
+        }
+    }
+}
+
+/// One entry of a choice list: serialized value and label
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Choice {
+    pub value: &'static str,
+    pub label: Message,
+}
+
+/// Kind of control used to edit a field, with its valid values
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+    /// COLORREF value edited with the color picker
+    Color,
+    /// One of a fixed set of string values
+    Choice(&'static [Choice]),
+    /// Whole number within an inclusive range
+    Number { min: i64, max: i64 },
+    /// Free text checked by a parser
+    Text(TextKind),
+}
+
+/// What a text field holds, which decides how it is checked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextKind {
+    /// Hotkey such as "Ctrl+Alt+C", empty for none
+    Hotkey,
+    /// Tooltip template, empty for the built-in one
+    Tooltip,
+    /// Built-in copy format name or template
+    CopyFormat,
+    /// Measurement template
+    MeasureFormat,
+    /// Built-in rectangle format name or template
+    RectangleFormat,
+}
+
+impl TextKind {
+    fn check(self, text: &str) -> Result<(), String> {
+        match self {
+            TextKind::Hotkey if text.is_empty() => Ok(()),
+            TextKind::Hotkey => Hotkey::parse(text).map(|_| ()),
+            TextKind::Tooltip if text.is_empty() => Ok(()),
+            TextKind::Tooltip => tooltip::parse_template(text).map(|_| ()),
+            TextKind::CopyFormat => resolve_copy_format(text).map(|_| ()),
+            TextKind::MeasureFormat => measure::parse_template(text).map(|_| ()),
+            TextKind::RectangleFormat => resolve_rectangle_format(text).map(|_| ()),
+        }
+    }
+}
+
+/// Settings window description of a configuration value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Field {
+    /// Key of the field in the configuration file, relative to the active
+    /// profile for fields of profile groups
+    pub key: &'static str,
+    /// Label in the settings window; `&` marks the access key
+    pub label: Message,
+    pub group: Group,
+    pub control: Control,
+}
+
+const THEME_CHOICES: &[Choice] = &[
+    Choice { value: "custom", label: Message::ThemeCustom },
+    Choice { value: "classic", label: Message::ThemeClassic },
+    Choice { value: "high_contrast", label: Message::ThemeHighContrast },
+    Choice { value: "solarized_dark", label: Message::ThemeSolarizedDark },
+    Choice { value: "solarized_light", label: Message::ThemeSolarizedLight },
+    Choice { value: "taskbar", label: Message::ThemeTaskbar },
+    Choice { value: "auto", label: Message::ThemeAuto },
+];
+
+const DISPLAY_MODE_CHOICES: &[Choice] = &[
+    Choice { value: "absolute", label: Message::DisplayAbsolute },
+    Choice { value: "relative", label: Message::DisplayRelative },
+    Choice { value: "monitor", label: Message::DisplayMonitor },
+    Choice { value: "logical", label: Message::DisplayLogical },
+    Choice { value: "client", label: Message::DisplayClient },
+    Choice { value: "window", label: Message::DisplayWindow },
+    Choice { value: "calibrated", label: Message::DisplayCalibrated },
+    Choice { value: "reference", label: Message::DisplayReference },
+    Choice { value: "color", label: Message::DisplayColor },
+];
+
+const NUMBER_FORMAT_CHOICES: &[Choice] = &[
+    Choice { value: "zero_padded", label: Message::NumberZeroPadded },
+    Choice { value: "space_padded", label: Message::NumberSpacePadded },
+];
+
+const UNIT_CHOICES: &[Choice] = &[
+    Choice { value: "pixels", label: Message::UnitPixels },
+    Choice { value: "millimeters", label: Message::UnitMillimeters },
+    Choice { value: "inches", label: Message::UnitInches },
+    Choice { value: "points", label: Message::UnitPoints },
+];
+
+const LANGUAGE_CHOICES: &[Choice] = &[
+    Choice { value: "system", label: Message::LanguageSystem },
+    Choice { value: "en", label: Message::LanguageEnglish },
+    Choice { value: "de", label: Message::LanguageGerman },
+];
+
+/// Range of origin coordinates, well beyond any virtual desktop
+const ORIGIN_LIMIT: i64 = 100_000;
+
+/// All settings in tab order
+pub const FIELDS: &[Field] = &[
+    Field { key: "theme", label: Message::FieldTheme, group: Group::Appearance, control: Control::Choice(THEME_CHOICES) },
+    Field { key: "text_color", label: Message::FieldTextColor, group: Group::Appearance, control: Control::Color },
+    Field { key: "background_color", label: Message::FieldBackgroundColor, group: Group::Appearance, control: Control::Color },
+    Field { key: "display_mode", label: Message::FieldDisplayMode, group: Group::Display, control: Control::Choice(DISPLAY_MODE_CHOICES) },
+    Field { key: "number_format", label: Message::FieldNumberFormat, group: Group::Display, control: Control::Choice(NUMBER_FORMAT_CHOICES) },
+    Field { key: "unit", label: Message::FieldUnit, group: Group::Display, control: Control::Choice(UNIT_CHOICES) },
+    Field { key: "interval_ms", label: Message::FieldInterval, group: Group::Display, control: Control::Number { min: MIN_INTERVAL_MS as i64, max: 10_000 } },
+    Field { key: "language", label: Message::FieldLanguage, group: Group::General, control: Control::Choice(LANGUAGE_CHOICES) },
+    Field { key: "tooltip", label: Message::FieldTooltip, group: Group::General, control: Control::Text(TextKind::Tooltip) },
+    Field { key: "copy_format", label: Message::FieldCopyFormat, group: Group::General, control: Control::Text(TextKind::CopyFormat) },
+    Field { key: "measure_format", label: Message::FieldMeasureFormat, group: Group::General, control: Control::Text(TextKind::MeasureFormat) },
+    Field { key: "rectangle_format", label: Message::FieldRectangleFormat, group: Group::General, control: Control::Text(TextKind::RectangleFormat) },
+    Field { key: "origin.x", label: Message::FieldOriginX, group: Group::General, control: Control::Number { min: -ORIGIN_LIMIT, max: ORIGIN_LIMIT } },
+    Field { key: "origin.y", label: Message::FieldOriginY, group: Group::General, control: Control::Number { min: -ORIGIN_LIMIT, max: ORIGIN_LIMIT } },
+    Field { key: "hotkeys.copy_position", label: Message::HotkeyCopyPosition, group: Group::Hotkeys, control: Control::Text(TextKind::Hotkey) },
+    Field { key: "hotkeys.freeze", label: Message::HotkeyFreeze, group: Group::Hotkeys, control: Control::Text(TextKind::Hotkey) },
+    Field { key: "hotkeys.capture_point", label: Message::HotkeyCapturePoint, group: Group::Hotkeys, control: Control::Text(TextKind::Hotkey) },
+    Field { key: "hotkeys.set_origin", label: Message::HotkeySetOrigin, group: Group::Hotkeys, control: Control::Text(TextKind::Hotkey) },
+    Field { key: "hotkeys.next_display_mode", label: Message::HotkeyNextDisplayMode, group: Group::Hotkeys, control: Control::Text(TextKind::Hotkey) },
+    Field { key: "hotkeys.measure", label: Message::HotkeyMeasure, group: Group::Hotkeys, control: Control::Text(TextKind::Hotkey) },
+    Field { key: "hotkeys.measure_rectangle", label: Message::HotkeyMeasureRectangle, group: Group::Hotkeys, control: Control::Text(TextKind::Hotkey) },
+    Field { key: "hotkeys.open_settings", label: Message::HotkeyOpenSettings, group: Group::Hotkeys, control: Control::Text(TextKind::Hotkey) },
+];
+
+/// Groups in the order they first appear in `FIELDS`
+pub fn groups() -> Vec<Group> {
+    let mut groups = Vec::new();
+    for field in FIELDS {
+        if !groups.contains(&field.group) {
+            groups.push(field.group);
+        }
+    }
+    groups
+}
+
+impl Field {
+    /// Label without access key marker and colon, for messages
+    pub fn name(&self) -> String {
+        tr(self.label).trim_end_matches(':').replace('&', "")
+    }
+
+    /// Checks that `value` is valid for this field's control
+    pub fn validate(&self, value: &Value) -> Result<(), String> {
+        let name = self.name();
+        match (self.control, value) {
+            (Control::Color, Value::Integer(color)) if (0..=0x00FF_FFFF).contains(color) => Ok(()),
+            (Control::Color, _) => Err(tr_args(Message::MustBeColor, &[("name", &name)])),
+            (Control::Choice(choices), Value::String(text)) if choices.iter().any(|c| c.value == text) => Ok(()),
+            (Control::Choice(choices), _) => {
+                let values: Vec<_> = choices.iter().map(|c| c.value).collect();
+                Err(tr_args(Message::MustBeOneOf, &[("name", &name), ("values", &values.join(", "))]))
+            }
+            (Control::Number { min, max }, Value::Integer(number)) if (min..=max).contains(number) => Ok(()),
+            (Control::Number { min, max }, _) => Err(tr_args(
+                Message::MustBeBetween,
+                &[("name", &name), ("min", &min.to_string()), ("max", &max.to_string())],
+            )),
+            (Control::Text(kind), Value::String(text)) => kind.check(text).map_err(|message| format!("{}: {}", name, message)),
+            (Control::Text(_), _) => Err(format!("{}: not a text", name)),
+        }
+    }
+
+    /// Dotted configuration key of this field, resolved against the active profile
+    pub fn config_key(&self, config: &Config) -> String {
+        if self.group.in_profile() {
+            config.profile_key(self.key)
+        } else {
+            self.key.to_string()
+        }
+    }
+
+    /// Current value of this field in `config`. Unset text fields, such as
+    /// unbound hotkeys, read as empty text.
+    pub fn get(&self, config: &Config) -> Option<Value> {
+        let value = if self.group.in_profile() {
+            to_table(config.profile()).remove(self.key)
+        } else {
+            let table = to_table(config);
+            let mut parts = self.key.split('.');
+            let first = parts.next().and_then(|part| table.get(part)).cloned();
+            parts.try_fold(first, |value, part| Some(value?.get(part).cloned())).flatten()
+        };
+        match (value, self.control) {
+            (None, Control::Text(_)) => Some(Value::String(String::new())),
+            (value, _) => value,
+        }
+    }
+
+    /// Validates `value` and stores it in `config`
+    pub fn set(&self, config: &mut Config, value: Value) -> Result<(), String> {
+        self.validate(&value)?;
+        if self.group.in_profile() {
+            let profile = config.profile_mut();
+            let mut table = to_table(profile);
+            table.insert(self.key.to_string(), value);
+            *profile = from_table(table)?;
+        } else {
+            let mut table = to_table(config);
+            let mut parts: Vec<&str> = self.key.split('.').collect();
+            let last = parts.pop().unwrap_or_default();
+            let mut current = &mut table;
+            for part in parts {
+                let entry = current.entry(part).or_insert_with(|| Value::Table(Table::new()));
+                current = entry.as_table_mut().ok_or_else(|| format!("{}: not a table", part))?;
+            }
+            current.insert(last.to_string(), value);
+            *config = from_table(table)?;
+        }
+        Ok(())
+    }
+}
+
+fn to_table<T: serde::Serialize>(value: &T) -> Table {
+    match Value::try_from(value) {
+        Ok(Value::Table(table)) => table,
+        _ => Table::new(),
+    }
+}
+
+fn from_table<T: serde::de::DeserializeOwned>(table: Table) -> Result<T, String> {
+    Value::Table(table).try_into().map_err(|e: toml::de::Error| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey::Action;
+
+    /// Settings edited outside the settings window, by prefix
+    const NOT_IN_WINDOW: &[&str] = &[
+        // Chosen from the Profile menu
+        "active_profile",
+        // Edited through the fields while the profile is active
+        "profiles",
+        // Edited with the palette buttons
+        "palette",
+        // Created with New Calibration and chosen from the Calibration menu
+        "calibration",
+        "calibrations",
+        // Rarely changed resolution of the reference display mode
+        "reference",
+        // Per-monitor overrides keyed by monitor
+        "monitor_sizes",
+    ];
+
+    /// Dotted keys of all values in `table`, without descending into excluded tables
+    fn collect_keys(table: &Table, prefix: &str, keys: &mut Vec<String>) {
+        for (key, value) in table {
+            let key = format!("{}{}", prefix, key);
+            match value {
+                Value::Table(table) if !NOT_IN_WINDOW.contains(&key.as_str()) => collect_keys(table, &format!("{}.", key), keys),
+                _ => keys.push(key),
+            }
+        }
+    }
+
+    #[test]
+    fn every_setting_is_in_the_schema_or_excluded() {
+        let mut all = Vec::new();
+        collect_keys(&to_table(&Config::default()), "", &mut all);
+        for key in all {
+            let excluded = NOT_IN_WINDOW.iter().any(|prefix| key == *prefix || key.starts_with(&format!("{}.", prefix)));
+            assert!(excluded || FIELDS.iter().any(|field| field.key == key), "{} is missing from the schema", key);
+        }
+    }
+
+    #[test]
+    fn every_action_has_a_hotkey_field() {
+        for action in Action::ALL {
+            let key = format!("hotkeys.{}", action.name());
+            assert!(FIELDS.iter().any(|field| field.key == key), "{} is missing from the schema", key);
+        }
+    }
+
+    #[test]
+    fn every_field_reads_a_valid_default() {
+        let config = Config::default();
+        for field in FIELDS {
+            let value = field.get(&config).unwrap_or_else(|| panic!("{} has no value", field.key));
+            assert_eq!(field.validate(&value), Ok(()), "{}", field.key);
+        }
+    }
+
+    #[test]
+    fn global_fields_set_nested_values() {
+        let mut config = Config::default();
+        let field = FIELDS.iter().find(|field| field.key == "origin.x").unwrap();
+        field.set(&mut config, Value::Integer(-1920)).unwrap();
+        assert_eq!(config.origin.x, -1920);
+
+        let field = FIELDS.iter().find(|field| field.key == "hotkeys.open_settings").unwrap();
+        assert_eq!(field.get(&config), Some(Value::String(String::new())));
+        field.set(&mut config, Value::String("Ctrl+Alt+S".to_string())).unwrap();
+        assert_eq!(config.hotkeys.get("open_settings").map(String::as_str), Some("Ctrl+Alt+S"));
+        assert!(field.set(&mut config, Value::String("S".to_string())).is_err());
+    }
+}
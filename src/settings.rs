@@ -0,0 +1,701 @@
+// Settings window, generated from the declarative settings schema
+
+use std::ffi::c_void;
+use std::mem::size_of;
+use std::path::PathBuf;
+use std::ptr::null_mut;
+
+use windows::core::{w, HSTRING, PCWSTR, PWSTR};
+use windows::Win32::Foundation::*;
+use windows::Win32::Graphics::Gdi::*;
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::Controls::Dialogs::*;
+use windows::Win32::UI::Controls::{DRAWITEMSTRUCT, ODS_DISABLED, ODS_FOCUS, ODS_SELECTED};
+use windows::Win32::UI::Input::KeyboardAndMouse::{EnableWindow, GetFocus, IsWindowEnabled, SetFocus};
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+use crate::color::{check_contrast, contrast_ratio, suggest_text_color, to_hex, MIN_CONTRAST_RATIO};
+use crate::config::Config;
+use crate::i18n::{tr, tr_args, Message};
+use crate::icon::{self, ICON_SIZE};
+use crate::palette::{export_palette, import_palette, Palette};
+use crate::schema::{self, Control, Field, FIELDS};
+use crate::theme::Theme;
+use crate::{
+    apply_settings, display_coordinates, is_setting_locked, paint_icon, paint_swatch, resolve_profile_colors, save_current_config, swatch_color, Sample,
+    CONFIG,
+};
+
+/// Control IDs: one per schema field starting at `ID_FIELD_FIRST`, in `FIELDS` order
+const ID_FIELD_FIRST: usize = 3000;
+const ID_SUGGEST_COLOR_BUTTON: usize = 2003;
+const ID_IMPORT_PALETTE_BUTTON: usize = 2004;
+const ID_EXPORT_PALETTE_BUTTON: usize = 2005;
+const ID_RESET_BUTTON: usize = 2006;
+/// `IsDialogMessageW` sends these IDs for Enter and Esc
+const ID_APPLY_BUTTON: usize = IDOK.0 as usize;
+const ID_CANCEL_BUTTON: usize = IDCANCEL.0 as usize;
+
+/// Layout of the generated window, in pixels. Groups are stacked in columns
+/// side by side, see `Group::column`.
+const COLUMNS: i32 = 3;
+const COLUMN_WIDTH: i32 = 360;
+const CLIENT_WIDTH: i32 = MARGIN + COLUMNS * (COLUMN_WIDTH + MARGIN);
+const MARGIN: i32 = 20;
+const GROUP_PADDING: i32 = 15;
+const GROUP_HEADER: i32 = 20;
+const ROW_HEIGHT: i32 = 35;
+const LABEL_WIDTH: i32 = 160;
+const CONTROL_WIDTH: i32 = 170;
+const SWATCH_WIDTH: i32 = 50;
+const BUTTON_WIDTH: i32 = 110;
+const BUTTON_HEIGHT: i32 = 28;
+/// The preview shows the tray icon enlarged by this factor
+const PREVIEW_SCALE: i32 = 4;
+const PREVIEW_SIZE: i32 = ICON_SIZE * PREVIEW_SCALE;
+
+/// Dark red text of the contrast warning and validation errors
+const WARNING_COLOR: u32 = 0x000000C0;
+
+/// Generated control of a schema field
+struct FieldControl {
+    field: &'static Field,
+    hwnd: HWND,
+}
+
+/// Global variables
+static mut SETTINGS_HWND: HWND = HWND(null_mut());
+static mut FIELD_CONTROLS: Vec<FieldControl> = Vec::new();
+static mut CONTRAST_LABEL_HWND: HWND = HWND(null_mut());
+static mut SUGGEST_COLOR_BUTTON_HWND: HWND = HWND(null_mut());
+static mut STATUS_LABEL_HWND: HWND = HWND(null_mut());
+static mut APPLY_BUTTON_HWND: HWND = HWND(null_mut());
+/// Control to focus again when the window is reactivated
+static mut FOCUSED_HWND: HWND = HWND(null_mut());
+/// Configuration edited in the window; it only replaces the current one on Apply
+static mut PENDING: Option<Config> = None;
+/// Last cursor position, shown in the preview
+static mut PREVIEW_POSITION: POINT = POINT { x: 0, y: 0 };
+
+/// Opens the settings window or brings the open one to the front
+pub unsafe fn open_settings_window() {
+    if !SETTINGS_HWND.is_invalid() {
+        let _ = SetForegroundWindow(SETTINGS_HWND);
+        return;
+    }
+    if let Ok(hinstance) = GetModuleHandleW(None) {
+        if let Ok(hwnd) = create_settings_window(hinstance.into()) {
+            SETTINGS_HWND = hwnd;
+            reload_settings_window();
+            let _ = ShowWindow(hwnd, SW_SHOW);
+            let _ = SetForegroundWindow(hwnd);
+            if let Some(control) = FIELD_CONTROLS.iter().find(|control| IsWindowEnabled(control.hwnd).as_bool()) {
+                let _ = SetFocus(Some(control.hwnd));
+            }
+        }
+    }
+}
+
+/// Handles keyboard navigation of the settings window: Tab, access keys,
+/// Enter and Esc. Returns `true` if the message was processed.
+pub unsafe fn translate_settings_message(msg: &MSG) -> bool {
+    !SETTINGS_HWND.is_invalid() && IsDialogMessageW(SETTINGS_HWND, msg).as_bool()
+}
+
+/// Discards pending changes and shows the current configuration
+pub unsafe fn reload_settings_window() {
+    if SETTINGS_HWND.is_invalid() {
+        return;
+    }
+    let Some(config) = &CONFIG else { return };
+    PENDING = Some(config.clone());
+    refresh_settings_window();
+}
+
+/// Updates all controls of the open settings window from the pending values
+pub unsafe fn refresh_settings_window() {
+    if SETTINGS_HWND.is_invalid() {
+        return;
+    }
+    let (Some(config), Some(pending)) = (&CONFIG, &PENDING) else { return };
+
+    for control in FIELD_CONTROLS.iter() {
+        let field = control.field;
+        let value = field.get(pending);
+        match field.control {
+            Control::Choice(choices) => {
+                let index = value
+                    .as_ref()
+                    .and_then(|value| value.as_str())
+                    .and_then(|value| choices.iter().position(|choice| choice.value == value));
+                // -1 clears the selection
+                SendMessageW(control.hwnd, CB_SETCURSEL, Some(WPARAM(index.unwrap_or(usize::MAX))), None);
+            }
+            Control::Number { .. } => {
+                let text = value.and_then(|value| value.as_integer()).map(|n| n.to_string()).unwrap_or_default();
+                let _ = SetWindowTextW(control.hwnd, &HSTRING::from(text));
+            }
+            Control::Text(_) => {
+                let text = value.as_ref().and_then(|value| value.as_str()).unwrap_or_default();
+                let _ = SetWindowTextW(control.hwnd, &HSTRING::from(text));
+            }
+            Control::Color => {
+                // The swatch text is not drawn but read by screen readers
+                let color = value.and_then(|value| value.as_integer()).unwrap_or_default();
+                let name = format!("{} {}", field.name(), to_hex(color as u32));
+                let _ = SetWindowTextW(control.hwnd, &HSTRING::from(name));
+                let _ = InvalidateRect(Some(control.hwnd), None, true);
+            }
+        }
+
+        let mut locked = is_setting_locked(&field.config_key(config));
+        if field.control == Control::Color {
+            // Picking a color also switches to the custom theme, unless it is already used
+            locked |= pending.profile().theme != Theme::Custom && is_setting_locked(&config.profile_key("theme"));
+        }
+        let _ = EnableWindow(control.hwnd, !locked);
+    }
+
+    let changed = FIELDS.iter().any(|field| field.get(config) != field.get(pending)) || config.palette != pending.palette;
+    let _ = EnableWindow(APPLY_BUTTON_HWND, changed);
+    update_contrast_warning();
+    invalidate_preview();
+}
+
+/// Moves the preview to a new cursor position
+pub unsafe fn update_preview(pt: POINT) {
+    if PREVIEW_POSITION != pt {
+        PREVIEW_POSITION = pt;
+        invalidate_preview();
+    }
+}
+
+unsafe fn invalidate_preview() {
+    if !SETTINGS_HWND.is_invalid() {
+        let _ = InvalidateRect(Some(SETTINGS_HWND), Some(&preview_rect()), false);
+    }
+}
+
+fn preview_rect() -> RECT {
+    RECT { left: MARGIN, top: MARGIN, right: MARGIN + PREVIEW_SIZE, bottom: MARGIN + PREVIEW_SIZE }
+}
+
+/// Draws the tray icon as it will look with the pending values
+unsafe fn paint_preview(hdc: HDC) {
+    let Some(pending) = &PENDING else { return };
+    let profile = pending.profile();
+    let colors = resolve_profile_colors(profile);
+    let sample = Sample::new(PREVIEW_POSITION);
+    if let Some(color) = swatch_color(profile, &sample) {
+        paint_icon(hdc, MARGIN, MARGIN, PREVIEW_SCALE, &icon::render_swatch(), colors);
+        paint_swatch(hdc, MARGIN, MARGIN, PREVIEW_SCALE, color);
+        return;
+    }
+    let position = display_coordinates(profile, &sample);
+    let mask = icon::render_position(&position, profile.number_format);
+    paint_icon(hdc, MARGIN, MARGIN, PREVIEW_SCALE, &mask, colors);
+}
+
+/// Registers the window class once and creates the window with one control per schema field
+unsafe fn create_settings_window(hinstance: HINSTANCE) -> Result<HWND, windows::core::Error> {
+    let class_name = w!("MPR_Settings");
+
+    let wc = WNDCLASSW {
+        lpfnWndProc: Some(settings_wndproc),
+        hInstance: hinstance,
+        lpszClassName: class_name,
+        hbrBackground: GetSysColorBrush(COLOR_WINDOW),
+        ..Default::default()
+    };
+
+    if RegisterClassW(&wc) == 0 && GetLastError() != ERROR_CLASS_ALREADY_EXISTS {
+        return Err(windows::core::Error::from_win32());
+    }
+
+    let style = WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX;
+    let mut rect = RECT { left: 0, top: 0, right: CLIENT_WIDTH, bottom: client_height() };
+    AdjustWindowRectEx(&mut rect, style, false, Default::default())?;
+
+    let hwnd = CreateWindowExW(
+        Default::default(), class_name, &HSTRING::from(tr(Message::SettingsTitle)), style,
+        CW_USEDEFAULT, CW_USEDEFAULT, rect.right - rect.left, rect.bottom - rect.top,
+        None, None, Some(hinstance), None,
+    )?;
+
+    let font = HFONT(GetStockObject(DEFAULT_GUI_FONT).0);
+    let create = |class, text: &HSTRING, style: WINDOW_STYLE, x, y, width, height, id: usize| {
+        let control = CreateWindowExW(
+            Default::default(), class, text, WS_CHILD | WS_VISIBLE | style,
+            x, y, width, height,
+            Some(hwnd),
+            (id != 0).then_some(HMENU(id as *mut c_void)),
+            Some(hinstance), None,
+        );
+        if let Ok(control) = control {
+            SendMessageW(control, WM_SETFONT, Some(WPARAM(font.0 as usize)), Some(LPARAM(1)));
+        }
+        control
+    };
+
+    // The preview itself is painted in WM_PAINT
+    create(
+        w!("STATIC"), &HSTRING::from(tr(Message::PreviewDescription)),
+        WINDOW_STYLE(0), 2 * MARGIN + PREVIEW_SIZE, MARGIN, CLIENT_WIDTH - 3 * MARGIN - PREVIEW_SIZE, 60, 0,
+    )?;
+    let mut column_y = [MARGIN + PREVIEW_SIZE + MARGIN; COLUMNS as usize];
+    for group in schema::groups() {
+        let fields: Vec<(usize, &'static Field)> = FIELDS
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| field.group == group)
+            .collect();
+        let column_x = MARGIN + group.column() as i32 * (COLUMN_WIDTH + MARGIN);
+        let control_x = column_x + COLUMN_WIDTH - GROUP_PADDING - CONTROL_WIDTH;
+        let y = &mut column_y[group.column()];
+
+        create(
+            w!("BUTTON"), &HSTRING::from(group.label()), WINDOW_STYLE(BS_GROUPBOX as u32),
+            column_x, *y, COLUMN_WIDTH, group_height(fields.len()), 0,
+        )?;
+
+        let mut row_y = *y + GROUP_HEADER;
+        for (index, field) in fields {
+            let id = ID_FIELD_FIRST + index;
+            create(
+                w!("STATIC"), &HSTRING::from(tr(field.label)), WINDOW_STYLE(0),
+                column_x + GROUP_PADDING, row_y + 5, LABEL_WIDTH, 20, 0,
+            )?;
+
+            let control_hwnd = match field.control {
+                Control::Color => create(
+                    w!("BUTTON"), &HSTRING::from(field.name()),
+                    WS_TABSTOP | WINDOW_STYLE(BS_OWNERDRAW as u32),
+                    column_x + COLUMN_WIDTH - GROUP_PADDING - SWATCH_WIDTH, row_y, SWATCH_WIDTH, 26, id,
+                )?,
+                Control::Choice(choices) => {
+                    let combo = create(
+                        w!("COMBOBOX"), &HSTRING::new(),
+                        WS_TABSTOP | WS_VSCROLL | WINDOW_STYLE(CBS_DROPDOWNLIST as u32),
+                        control_x, row_y, CONTROL_WIDTH, 200, id,
+                    )?;
+                    for choice in choices {
+                        SendMessageW(combo, CB_ADDSTRING, None, Some(LPARAM(HSTRING::from(tr(choice.label)).as_ptr() as isize)));
+                    }
+                    combo
+                }
+                // No ES_NUMBER, which would rule out negative numbers
+                Control::Number { .. } | Control::Text(_) => create(
+                    w!("EDIT"), &HSTRING::new(),
+                    WS_TABSTOP | WS_BORDER | WINDOW_STYLE(ES_AUTOHSCROLL as u32),
+                    control_x, row_y, CONTROL_WIDTH, 24, id,
+                )?,
+            };
+            FIELD_CONTROLS.push(FieldControl { field, hwnd: control_hwnd });
+            row_y += ROW_HEIGHT;
+        }
+        *y += group_height(row_count(group)) + MARGIN;
+    }
+    let mut y = column_y.into_iter().max().unwrap_or_default();
+
+    // Contrast warning, only filled in when the colors are hard to read
+    CONTRAST_LABEL_HWND = create(w!("STATIC"), &HSTRING::new(), WINDOW_STYLE(0), MARGIN, y, CLIENT_WIDTH - 2 * MARGIN, 40, 0)?;
+    SUGGEST_COLOR_BUTTON_HWND = create(
+        w!("BUTTON"), &HSTRING::from(tr(Message::UseSuggestedColor)), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32),
+        MARGIN, y + 45, 200, 28, ID_SUGGEST_COLOR_BUTTON,
+    )?;
+    y += 45 + 28 + MARGIN;
+
+    // Palette of the color pickers
+    create(w!("STATIC"), &HSTRING::from(tr(Message::CustomColors)), WINDOW_STYLE(0), MARGIN, y + 5, 150, 20, 0)?;
+    create(
+        w!("BUTTON"), &HSTRING::from(tr(Message::ImportPalette)), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32),
+        CLIENT_WIDTH - MARGIN - 190, y, 90, 28, ID_IMPORT_PALETTE_BUTTON,
+    )?;
+    create(
+        w!("BUTTON"), &HSTRING::from(tr(Message::ExportPalette)), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32),
+        CLIENT_WIDTH - MARGIN - 90, y, 90, 28, ID_EXPORT_PALETTE_BUTTON,
+    )?;
+    y += 28 + MARGIN;
+
+    // Validation errors of edited values
+    STATUS_LABEL_HWND = create(w!("STATIC"), &HSTRING::new(), WINDOW_STYLE(0), MARGIN, y, CLIENT_WIDTH - 2 * MARGIN, 20, 0)?;
+    y += 20 + MARGIN;
+
+    create(
+        w!("BUTTON"), &HSTRING::from(tr(Message::ResetToDefaults)), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32),
+        MARGIN, y, BUTTON_WIDTH, BUTTON_HEIGHT, ID_RESET_BUTTON,
+    )?;
+    APPLY_BUTTON_HWND = create(
+        w!("BUTTON"), &HSTRING::from(tr(Message::Apply)), WS_TABSTOP | WINDOW_STYLE(BS_DEFPUSHBUTTON as u32),
+        CLIENT_WIDTH - MARGIN - 2 * 90 - 10, y, 90, BUTTON_HEIGHT, ID_APPLY_BUTTON,
+    )?;
+    create(
+        w!("BUTTON"), &HSTRING::from(tr(Message::Cancel)), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32),
+        CLIENT_WIDTH - MARGIN - 90, y, 90, BUTTON_HEIGHT, ID_CANCEL_BUTTON,
+    )?;
+
+    Ok(hwnd)
+}
+
+fn row_count(group: schema::Group) -> usize {
+    FIELDS.iter().filter(|field| field.group == group).count()
+}
+
+fn group_height(rows: usize) -> i32 {
+    GROUP_HEADER + rows as i32 * ROW_HEIGHT + GROUP_PADDING / 2
+}
+
+/// Client height needed for the longest column of groups plus the fixed rows below them
+fn client_height() -> i32 {
+    let mut columns = [0; COLUMNS as usize];
+    for group in schema::groups() {
+        columns[group.column()] += group_height(row_count(group)) + MARGIN;
+    }
+    let groups = columns.into_iter().max().unwrap_or_default();
+    MARGIN + (PREVIEW_SIZE + MARGIN) + groups + (45 + 28 + MARGIN) + (28 + MARGIN) + (20 + MARGIN) + BUTTON_HEIGHT + MARGIN
+}
+
+/// Text of an edit control
+unsafe fn control_text(hwnd: HWND) -> String {
+    let mut buffer = vec![0u16; GetWindowTextLengthW(hwnd) as usize + 1];
+    let len = GetWindowTextW(hwnd, &mut buffer) as usize;
+    String::from_utf16_lossy(&buffer[..len])
+}
+
+/// Reads the value of a choice, number or text control into the pending values
+unsafe fn commit_field_control(index: usize) {
+    let field = &FIELDS[index];
+    let Some(control) = FIELD_CONTROLS.iter().find(|control| std::ptr::eq(control.field, field)) else { return };
+
+    let value = match field.control {
+        Control::Choice(choices) => {
+            let selected = SendMessageW(control.hwnd, CB_GETCURSEL, None, None).0;
+            match usize::try_from(selected).ok().and_then(|selected| choices.get(selected)) {
+                Some(choice) => toml::Value::String(choice.value.to_string()),
+                None => return,
+            }
+        }
+        Control::Number { .. } => match control_text(control.hwnd).trim().parse::<i64>() {
+            Ok(number) => toml::Value::Integer(number),
+            Err(_) => toml::Value::String(String::new()),
+        },
+        Control::Text(_) => toml::Value::String(control_text(control.hwnd).trim().to_string()),
+        Control::Color => return,
+    };
+
+    edit_field_value(field, value);
+}
+
+/// Stores a new pending value for `field` and shows validation errors in the status line
+unsafe fn edit_field_value(field: &Field, value: toml::Value) {
+    let Some(pending) = &mut PENDING else { return };
+    if field.get(pending).as_ref() == Some(&value) {
+        return;
+    }
+
+    let mut edited = pending.clone();
+    if field.control == Control::Color {
+        // Picking a color switches to the custom theme
+        edited.profile_mut().theme = Theme::Custom;
+    }
+    let result = field.set(&mut edited, value);
+    if result.is_ok() {
+        *pending = edited;
+    }
+
+    set_status(&result.err().unwrap_or_default());
+    // Reverts rejected input
+    refresh_settings_window();
+}
+
+/// Pending color of a color field
+unsafe fn pending_color(field: &Field) -> COLORREF {
+    let color = PENDING
+        .as_ref()
+        .and_then(|pending| field.get(pending))
+        .and_then(|value| value.as_integer())
+        .unwrap_or_default();
+    COLORREF(color as u32)
+}
+
+/// Applies the pending values and saves them
+unsafe fn apply_pending() {
+    // Enter applies while a number or text is still being edited
+    for (index, field) in FIELDS.iter().enumerate() {
+        if let Control::Number { .. } | Control::Text(_) = field.control {
+            commit_field_control(index);
+        }
+    }
+
+    let Some(pending) = &PENDING else { return };
+    let (edited, palette) = (pending.clone(), pending.palette.clone());
+    if let Err(message) = apply_settings(edited) {
+        set_status(&message);
+        return;
+    }
+    set_palette(palette);
+    set_status("");
+    reload_settings_window();
+}
+
+/// Replaces the pending settings with the defaults, except for locked settings
+unsafe fn reset_pending() {
+    let (Some(config), Some(pending)) = (&CONFIG, &mut PENDING) else { return };
+    let defaults = Config::default();
+    for field in FIELDS {
+        if !is_setting_locked(&field.config_key(config)) {
+            if let Some(value) = field.get(&defaults) {
+                let _ = field.set(pending, value);
+            }
+        }
+    }
+    set_status("");
+    refresh_settings_window();
+}
+
+unsafe fn set_status(message: &str) {
+    let _ = SetWindowTextW(STATUS_LABEL_HWND, &HSTRING::from(message));
+}
+
+/// Opens the system color picker with the pending palette as custom colors.
+/// Changes to the custom colors are kept even if the dialog is canceled.
+unsafe fn choose_color(hwnd: HWND, initial: COLORREF) -> Option<COLORREF> {
+    let Some(pending) = &mut PENDING else { return None };
+    let mut slots = pending.palette.to_picker_slots().map(COLORREF);
+
+    let mut cc = CHOOSECOLORW {
+        lStructSize: size_of::<CHOOSECOLORW>() as u32,
+        hwndOwner: hwnd,
+        rgbResult: initial,
+        lpCustColors: slots.as_mut_ptr(),
+        Flags: CC_FULLOPEN | CC_RGBINIT,
+        ..Default::default()
+    };
+    let chosen = ChooseColorW(&mut cc).as_bool();
+
+    pending.palette.update_from_picker_slots(&slots.map(|color| color.0));
+    chosen.then_some(cc.rgbResult)
+}
+
+/// Replaces the shared palette and saves it
+unsafe fn set_palette(palette: Palette) {
+    if is_setting_locked("palette") {
+        return;
+    }
+    if let Some(config) = &mut CONFIG {
+        if config.palette != palette {
+            config.palette = palette;
+            save_current_config();
+        }
+    }
+}
+
+/// Asks for a palette file to open or save; `.gpl` and `.json` are supported
+unsafe fn choose_palette_file(hwnd: HWND, save: bool) -> Option<PathBuf> {
+    let mut buffer = [0u16; 260];
+    // Pairs of description and pattern, each terminated by a NUL
+    let filter = HSTRING::from(format!(
+        "{}\0*.gpl\0{}\0*.json\0",
+        tr(Message::GimpPaletteFiles),
+        tr(Message::JsonPaletteFiles),
+    ));
+    let mut ofn = OPENFILENAMEW {
+        lStructSize: size_of::<OPENFILENAMEW>() as u32,
+        hwndOwner: hwnd,
+        lpstrFilter: PCWSTR(filter.as_ptr()),
+        lpstrFile: PWSTR(buffer.as_mut_ptr()),
+        nMaxFile: buffer.len() as u32,
+        lpstrDefExt: w!("gpl"),
+        ..Default::default()
+    };
+
+    let chosen = if save {
+        ofn.Flags = OFN_OVERWRITEPROMPT | OFN_PATHMUSTEXIST;
+        GetSaveFileNameW(&mut ofn)
+    } else {
+        ofn.Flags = OFN_FILEMUSTEXIST | OFN_PATHMUSTEXIST;
+        GetOpenFileNameW(&mut ofn)
+    };
+    if !chosen.as_bool() {
+        return None;
+    }
+
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    Some(PathBuf::from(String::from_utf16_lossy(&buffer[..len])))
+}
+
+/// Shows an error message box
+unsafe fn show_error(hwnd: HWND, message: &str) {
+    MessageBoxW(Some(hwnd), &HSTRING::from(message), w!("MPR"), MB_OK | MB_ICONERROR);
+}
+
+/// Shows or hides the contrast warning for the pending colors
+unsafe fn update_contrast_warning() {
+    if CONTRAST_LABEL_HWND.is_invalid() {
+        return;
+    }
+    let Some(pending) = &PENDING else { return };
+    let colors = resolve_profile_colors(pending.profile());
+    let check = check_contrast(colors.text, colors.background);
+    let text = match check.suggestion {
+        Some(suggestion) => tr_args(
+            Message::LowContrast,
+            &[("ratio", &format!("{:.1}", check.ratio)), ("color", &to_hex(suggestion))],
+        ),
+        None => String::new(),
+    };
+    let _ = SetWindowTextW(CONTRAST_LABEL_HWND, &HSTRING::from(text));
+    let _ = ShowWindow(SUGGEST_COLOR_BUTTON_HWND, if check.is_sufficient() { SW_HIDE } else { SW_SHOW });
+}
+
+/// Draws a color swatch button with its focus and pressed state
+unsafe fn draw_swatch(item: &DRAWITEMSTRUCT, color: COLORREF) {
+    let mut rect = item.rcItem;
+    let frame = if item.itemState.0 & ODS_DISABLED.0 != 0 { COLOR_GRAYTEXT } else { COLOR_WINDOWTEXT };
+    FrameRect(item.hDC, &rect, GetSysColorBrush(frame));
+
+    let _ = InflateRect(&mut rect, -1, -1);
+    if item.itemState.0 & ODS_SELECTED.0 != 0 {
+        let _ = InflateRect(&mut rect, -1, -1);
+    }
+    let brush = CreateSolidBrush(color);
+    if !brush.is_invalid() {
+        FillRect(item.hDC, &rect, brush);
+        let _ = DeleteObject(brush.into());
+    }
+
+    if item.itemState.0 & ODS_FOCUS.0 != 0 {
+        let _ = InflateRect(&mut rect, -2, -2);
+        let _ = DrawFocusRect(item.hDC, &rect);
+    }
+}
+
+/// Settings window procedure
+extern "system" fn settings_wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    unsafe {
+        match msg {
+            WM_CLOSE => {
+                // Closing discards pending changes like Cancel
+                let _ = DestroyWindow(hwnd);
+                LRESULT(0)
+            }
+
+            WM_ACTIVATE => {
+                // Windows only restores the keyboard focus of dialog boxes by itself
+                if wparam.0 & 0xFFFF == WA_INACTIVE as usize {
+                    FOCUSED_HWND = GetFocus();
+                    LRESULT(0)
+                } else if !FOCUSED_HWND.is_invalid() {
+                    let _ = SetFocus(Some(FOCUSED_HWND));
+                    LRESULT(0)
+                } else {
+                    DefWindowProcW(hwnd, msg, wparam, lparam)
+                }
+            }
+
+            WM_PAINT => {
+                let mut ps = PAINTSTRUCT::default();
+                let hdc = BeginPaint(hwnd, &mut ps);
+                paint_preview(hdc);
+                let _ = EndPaint(hwnd, &ps);
+                LRESULT(0)
+            }
+
+            WM_DESTROY => {
+                FIELD_CONTROLS.clear();
+                SETTINGS_HWND = HWND(null_mut());
+                CONTRAST_LABEL_HWND = HWND(null_mut());
+                SUGGEST_COLOR_BUTTON_HWND = HWND(null_mut());
+                STATUS_LABEL_HWND = HWND(null_mut());
+                APPLY_BUTTON_HWND = HWND(null_mut());
+                FOCUSED_HWND = HWND(null_mut());
+                PENDING = None;
+                LRESULT(0)
+            }
+
+            WM_COMMAND => {
+                let control_id = wparam.0 & 0xFFFF;
+                let notification = ((wparam.0 >> 16) & 0xFFFF) as u32;
+
+                if let Some(index) = control_id.checked_sub(ID_FIELD_FIRST).filter(|&index| index < FIELDS.len()) {
+                    let field = &FIELDS[index];
+                    match (field.control, notification) {
+                        (Control::Color, BN_CLICKED) => {
+                            if let Some(color) = choose_color(hwnd, pending_color(field)) {
+                                edit_field_value(field, toml::Value::Integer(color.0 as i64));
+                            }
+                            refresh_settings_window();
+                        }
+                        (Control::Choice(_), CBN_SELCHANGE) | (Control::Number { .. } | Control::Text(_), EN_KILLFOCUS) => {
+                            commit_field_control(index);
+                        }
+                        _ => {}
+                    }
+                } else if control_id == ID_SUGGEST_COLOR_BUTTON {
+                    if let Some(pending) = &PENDING {
+                        let colors = resolve_profile_colors(pending.profile());
+                        if let Some(field) = FIELDS.iter().find(|field| field.key == "text_color") {
+                            edit_field_value(field, toml::Value::Integer(suggest_text_color(colors.text, colors.background) as i64));
+                        }
+                    }
+                } else if control_id == ID_IMPORT_PALETTE_BUTTON {
+                    if let Some(path) = choose_palette_file(hwnd, false) {
+                        match import_palette(&path) {
+                            Ok(palette) => {
+                                if let Some(pending) = &mut PENDING {
+                                    pending.palette = palette;
+                                }
+                                refresh_settings_window();
+                            }
+                            Err(message) => show_error(hwnd, &message),
+                        }
+                    }
+                } else if control_id == ID_EXPORT_PALETTE_BUTTON {
+                    if let Some(path) = choose_palette_file(hwnd, true) {
+                        if let Some(pending) = &PENDING {
+                            if let Err(message) = export_palette(&pending.palette, &path) {
+                                show_error(hwnd, &message);
+                            }
+                        }
+                    }
+                } else if control_id == ID_RESET_BUTTON {
+                    reset_pending();
+                } else if control_id == ID_APPLY_BUTTON {
+                    apply_pending();
+                } else if control_id == ID_CANCEL_BUTTON {
+                    let _ = DestroyWindow(hwnd);
+                }
+                LRESULT(0)
+            }
+
+            WM_DRAWITEM => {
+                let item = &*(lparam.0 as *const DRAWITEMSTRUCT);
+                match FIELD_CONTROLS.iter().find(|control| control.hwnd == item.hwndItem) {
+                    Some(control) => {
+                        draw_swatch(item, pending_color(control.field));
+                        LRESULT(1)
+                    }
+                    None => DefWindowProcW(hwnd, msg, wparam, lparam),
+                }
+            }
+
+            WM_CTLCOLORSTATIC => {
+                // Labels use the system colors so that high contrast themes apply,
+                // warnings in dark red where that is readable
+                let hdc = HDC(wparam.0 as *mut c_void);
+                let window_color = GetSysColor(COLOR_WINDOW);
+                let is_warning = lparam.0 == CONTRAST_LABEL_HWND.0 as isize || lparam.0 == STATUS_LABEL_HWND.0 as isize;
+                let label_color = if is_warning && contrast_ratio(WARNING_COLOR, window_color) >= MIN_CONTRAST_RATIO {
+                    WARNING_COLOR
+                } else {
+                    GetSysColor(COLOR_WINDOWTEXT)
+                };
+                SetTextColor(hdc, COLORREF(label_color));
+                SetBkMode(hdc, TRANSPARENT);
+                LRESULT(GetSysColorBrush(COLOR_WINDOW).0 as isize)
+            }
+
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+}
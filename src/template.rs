@@ -0,0 +1,127 @@
+// Text templates with `{name}` placeholders
+
+/// Parsed template; `{{` and `}}` stand for literal braces
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Placeholder(String),
+}
+
+impl Template {
+    /// Parses `text`, accepting only the placeholders listed in `names`
+    pub fn parse(text: &str, names: &[&str]) -> Result<Template, String> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("Unclosed placeholder {{{}", name)),
+                        }
+                    }
+                    let name = name.trim();
+                    if !names.contains(&name) {
+                        return Err(format!("Unknown placeholder {{{}}}, expected one of: {}", name, names.join(", ")));
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Placeholder(name.to_string()));
+                }
+                '}' => return Err("Unmatched }, write }} for a literal brace".into()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Text(literal));
+        }
+        Ok(Template { parts })
+    }
+
+    /// Whether any of the placeholders `names` occurs in the template
+    pub fn uses_any(&self, names: &[&str]) -> bool {
+        self.parts.iter().any(|part| matches!(part, Part::Placeholder(name) if names.contains(&name.as_str())))
+    }
+
+    /// Fills in the placeholders with the text returned by `value`
+    pub fn render(&self, value: impl Fn(&str) -> String) -> String {
+        let mut text = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(literal) => text.push_str(literal),
+                Part::Placeholder(name) => text.push_str(&value(name)),
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: &[&str] = &["x", "y"];
+
+    fn render(text: &str) -> Result<String, String> {
+        Template::parse(text, NAMES).map(|template| template.render(|name| format!("<{}>", name)))
+    }
+
+    #[test]
+    fn placeholders_are_filled_in() {
+        assert_eq!(render("{x},{y}"), Ok("<x>,<y>".to_string()));
+        assert_eq!(render("x = { x }"), Ok("x = <x>".to_string()));
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(render("{{x}}"), Ok("{x}".to_string()));
+        assert_eq!(render("{{{x}}}"), Ok("{<x>}".to_string()));
+        assert_eq!(render("a}}b{{c"), Ok("a}b{c".to_string()));
+    }
+
+    #[test]
+    fn unclosed_placeholders_are_rejected() {
+        let error = render("{x},{y").unwrap_err();
+        assert!(error.starts_with("Unclosed placeholder {y"), "{}", error);
+    }
+
+    #[test]
+    fn unknown_placeholders_are_rejected() {
+        let error = render("{z}").unwrap_err();
+        assert!(error.starts_with("Unknown placeholder {z}"), "{}", error);
+        assert!(error.ends_with("x, y"), "{}", error);
+    }
+
+    #[test]
+    fn stray_closing_braces_are_rejected() {
+        let error = render("{x}}").unwrap_err();
+        assert!(error.starts_with("Unmatched }"), "{}", error);
+        assert!(render("x}").is_err());
+    }
+
+    #[test]
+    fn uses_any_finds_placeholders_but_not_literals() {
+        let template = Template::parse("{{y}} {x}", NAMES).unwrap();
+        assert!(template.uses_any(&["x"]));
+        assert!(!template.uses_any(&["y"]));
+    }
+}
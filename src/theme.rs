@@ -0,0 +1,104 @@
+// Built-in color themes and their resolution against the system appearance
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::rgb;
+
+/// Color theme of the tray icon
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    /// The configured `text_color` and `background_color`
+    #[default]
+    Custom,
+    /// Green digits on black
+    Classic,
+    /// White digits on black
+    HighContrast,
+    SolarizedDark,
+    SolarizedLight,
+    /// Matches the light or dark taskbar
+    Taskbar,
+    /// Dark digits on light apps, light digits on dark apps
+    Auto,
+}
+
+impl Theme {
+    /// Whether the colors depend on the system light/dark setting
+    pub fn follows_system(self) -> bool {
+        matches!(self, Theme::Taskbar | Theme::Auto)
+    }
+}
+
+/// Light/dark state of the system, as queried from the platform
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SystemAppearance {
+    /// Applications use the light theme
+    pub apps_light: bool,
+    /// Taskbar and start menu use the light theme
+    pub taskbar_light: bool,
+}
+
+/// Text and background color pair as COLORREF values
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThemeColors {
+    pub text: u32,
+    pub background: u32,
+}
+
+const DARK_BACKGROUND: u32 = rgb(0, 0, 0);
+const LIGHT_BACKGROUND: u32 = rgb(0xF3, 0xF3, 0xF3);
+
+/// Colors to draw with for `theme`; `custom` is used for `Theme::Custom`
+pub fn resolve(theme: Theme, custom: ThemeColors, system: SystemAppearance) -> ThemeColors {
+    match theme {
+        Theme::Custom => custom,
+        Theme::Classic => ThemeColors { text: rgb(0x00, 0xFF, 0x00), background: DARK_BACKGROUND },
+        Theme::HighContrast => ThemeColors { text: rgb(0xFF, 0xFF, 0xFF), background: DARK_BACKGROUND },
+        Theme::SolarizedDark => ThemeColors { text: rgb(0x93, 0xA1, 0xA1), background: rgb(0x00, 0x2B, 0x36) },
+        Theme::SolarizedLight => ThemeColors { text: rgb(0x58, 0x6E, 0x75), background: rgb(0xFD, 0xF6, 0xE3) },
+        Theme::Taskbar => light_or_dark(system.taskbar_light),
+        Theme::Auto => light_or_dark(system.apps_light),
+    }
+}
+
+fn light_or_dark(light: bool) -> ThemeColors {
+    if light {
+        ThemeColors { text: rgb(0x1A, 0x1A, 0x1A), background: LIGHT_BACKGROUND }
+    } else {
+        ThemeColors { text: rgb(0xFF, 0xFF, 0xFF), background: DARK_BACKGROUND }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUSTOM: ThemeColors = ThemeColors { text: rgb(0x12, 0x34, 0x56), background: rgb(0x65, 0x43, 0x21) };
+    const DARK: SystemAppearance = SystemAppearance { apps_light: false, taskbar_light: false };
+
+    #[test]
+    fn custom_theme_uses_the_configured_colors() {
+        assert_eq!(resolve(Theme::Custom, CUSTOM, DARK), CUSTOM);
+    }
+
+    #[test]
+    fn fixed_themes_ignore_the_configured_colors_and_the_system() {
+        for theme in [Theme::Classic, Theme::HighContrast, Theme::SolarizedDark, Theme::SolarizedLight] {
+            assert!(!theme.follows_system());
+            let colors = resolve(theme, CUSTOM, DARK);
+            assert_ne!(colors, CUSTOM);
+            assert_eq!(resolve(theme, CUSTOM, SystemAppearance { apps_light: true, taskbar_light: true }), colors);
+        }
+        assert_eq!(resolve(Theme::Classic, CUSTOM, DARK), ThemeColors { text: rgb(0x00, 0xFF, 0x00), background: DARK_BACKGROUND });
+    }
+
+    #[test]
+    fn system_themes_follow_their_own_setting() {
+        let light_taskbar = SystemAppearance { apps_light: false, taskbar_light: true };
+        assert!(Theme::Taskbar.follows_system() && Theme::Auto.follows_system());
+        assert_eq!(resolve(Theme::Taskbar, CUSTOM, light_taskbar).background, LIGHT_BACKGROUND);
+        assert_eq!(resolve(Theme::Auto, CUSTOM, light_taskbar).background, DARK_BACKGROUND);
+        assert_eq!(resolve(Theme::Taskbar, CUSTOM, DARK), resolve(Theme::Auto, CUSTOM, DARK));
+    }
+}
@@ -0,0 +1,110 @@
+// Tray tooltip with the position details that do not fit into the icon
+
+use crate::position::PLACEHOLDERS;
+use crate::template::Template;
+
+/// Size of `NOTIFYICONDATAW::szTip` in UTF-16 units, including the terminating NUL
+pub const TOOLTIP_BUFFER_LEN: usize = 128;
+
+/// Parses a tooltip template
+pub fn parse_template(text: &str) -> Result<Template, String> {
+    Template::parse(text, PLACEHOLDERS)
+}
+
+/// At most `max_chars` characters of `text`, ending with an ellipsis if it was cut
+pub fn shorten(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// Converts `text` into a NUL-terminated `szTip` buffer. Text that does not fit
+/// is cut at a character boundary and ends with an ellipsis.
+pub fn to_tooltip_buffer(text: &str) -> [u16; TOOLTIP_BUFFER_LEN] {
+    let max_len = TOOLTIP_BUFFER_LEN - 1;
+    let mut units: Vec<u16> = text.encode_utf16().collect();
+
+    if units.len() > max_len {
+        let mut cut = String::new();
+        let mut len = 0;
+        for c in text.chars() {
+            // Leaves room for the ellipsis
+            if len + c.len_utf16() > max_len - 1 {
+                break;
+            }
+            len += c.len_utf16();
+            cut.push(c);
+        }
+        units = cut.trim_end().encode_utf16().collect();
+        units.push('…' as u16);
+    }
+
+    let mut buffer = [0u16; TOOLTIP_BUFFER_LEN];
+    buffer[..units.len()].copy_from_slice(&units);
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Text of a buffer up to the NUL; fails on a split surrogate pair
+    fn text(buffer: &[u16; TOOLTIP_BUFFER_LEN]) -> String {
+        let len = buffer.iter().position(|&unit| unit == 0).expect("NUL terminated");
+        String::from_utf16(&buffer[..len]).expect("no split surrogate pair")
+    }
+
+    #[test]
+    fn text_up_to_127_units_is_kept() {
+        let full = "a".repeat(127);
+        assert_eq!(text(&to_tooltip_buffer(&full)), full);
+        assert_eq!(text(&to_tooltip_buffer("")), "");
+    }
+
+    #[test]
+    fn longer_text_ends_with_an_ellipsis() {
+        for len in [128, 129, 500] {
+            let shown = text(&to_tooltip_buffer(&"a".repeat(len)));
+            assert_eq!(shown, format!("{}…", "a".repeat(126)), "{}", len);
+            assert_eq!(shown.encode_utf16().count(), 127);
+        }
+    }
+
+    #[test]
+    fn trailing_spaces_before_the_ellipsis_are_removed() {
+        let long = format!("{}  {}", "a".repeat(124), "b".repeat(10));
+        assert_eq!(text(&to_tooltip_buffer(&long)), format!("{}…", "a".repeat(124)));
+    }
+
+    #[test]
+    fn surrogate_pairs_are_never_split() {
+        // Fits exactly with the emoji as the last two units
+        let fits = format!("{}😀", "a".repeat(125));
+        assert_eq!(text(&to_tooltip_buffer(&fits)), fits);
+        // One unit too long: the whole emoji makes room for the ellipsis
+        let cut = format!("{}😀", "a".repeat(126));
+        assert_eq!(text(&to_tooltip_buffer(&cut)), format!("{}…", "a".repeat(126)));
+        // The emoji would end at unit 127, where the ellipsis goes
+        let cut = format!("{}😀b", "a".repeat(125));
+        assert_eq!(text(&to_tooltip_buffer(&cut)), format!("{}…", "a".repeat(125)));
+        let kept = format!("{}😀bb", "a".repeat(124));
+        assert_eq!(text(&to_tooltip_buffer(&kept)), format!("{}😀…", "a".repeat(124)));
+    }
+
+    #[test]
+    fn templates_accept_only_known_placeholders() {
+        assert!(parse_template("{x}, {y}").is_ok());
+        assert!(parse_template("{nope}").is_err());
+    }
+
+    #[test]
+    fn shortened_text_ends_with_an_ellipsis() {
+        assert_eq!(shorten("Untitled - Notepad", 18), "Untitled - Notepad");
+        assert_eq!(shorten("Untitled - Notepad", 12), "Untitled -…");
+        // Counted in characters, not bytes
+        assert_eq!(shorten("Größenänderung", 6), "Größe…");
+        assert_eq!(shorten("abc", 0), "…");
+    }
+}
@@ -0,0 +1,69 @@
+// Conversion of pixel distances into physical units
+
+use crate::config::Unit;
+
+const MM_PER_INCH: f64 = 25.4;
+const POINTS_PER_INCH: f64 = 72.0;
+
+/// Decimal places values in `unit` are shown with
+pub fn decimals(unit: Unit) -> u32 {
+    match unit {
+        Unit::Inches => 1,
+        Unit::Pixels | Unit::Millimeters | Unit::Points => 0,
+    }
+}
+
+/// Abbreviation shown after values, e.g. `mm`
+pub fn symbol(unit: Unit) -> &'static str {
+    match unit {
+        Unit::Pixels => "px",
+        Unit::Millimeters => "mm",
+        Unit::Inches => "in",
+        Unit::Points => "pt",
+    }
+}
+
+/// `pixels` in `unit` on a monitor with pixels of `pixel_mm` millimetres, as
+/// a whole number of the unit's smallest shown step (tenths of an inch)
+pub fn from_pixels(pixels: i32, pixel_mm: f64, unit: Unit) -> i32 {
+    match unit {
+        Unit::Pixels => pixels,
+        _ => from_mm(pixels as f64 * pixel_mm, unit),
+    }
+}
+
+/// `mm` millimetres in a physical `unit`, as a whole number of the unit's
+/// smallest shown step; pixels are taken as one millimetre each
+pub fn from_mm(mm: f64, unit: Unit) -> i32 {
+    let value = match unit {
+        Unit::Pixels | Unit::Millimeters => mm,
+        Unit::Inches => mm / MM_PER_INCH,
+        Unit::Points => mm / MM_PER_INCH * POINTS_PER_INCH,
+    };
+    (value * 10f64.powi(decimals(unit) as i32)).round() as i32
+}
+
+/// Value returned by `from_pixels` as text, e.g. `-0.5` for -5 tenths of an inch
+pub fn format_value(value: i32, unit: Unit) -> String {
+    format_fixed(value, decimals(unit))
+}
+
+/// Fixed-point value with `decimals` implied decimal places as text, e.g.
+/// `-0.05` for -5 with two
+pub fn format_fixed(value: i32, decimals: u32) -> String {
+    if decimals == 0 {
+        return value.to_string();
+    }
+    let scale = 10u32.pow(decimals);
+    let sign = if value < 0 { "-" } else { "" };
+    let magnitude = value.unsigned_abs();
+    format!("{}{}.{:0width$}", sign, magnitude / scale, magnitude % scale, width = decimals as usize)
+}
+
+/// Value with its unit, e.g. `12 mm`; pixels are shown as plain numbers
+pub fn format_with_symbol(value: i32, unit: Unit) -> String {
+    match unit {
+        Unit::Pixels => value.to_string(),
+        _ => format!("{} {}", format_value(value, unit), symbol(unit)),
+    }
+}
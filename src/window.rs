@@ -0,0 +1,57 @@
+// Top-level windows, as used by the window and reference display modes
+
+use crate::layout::Rect;
+
+/// Top-level window with its bounds in virtual-desktop coordinates
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WindowInfo {
+    pub title: String,
+    pub class: String,
+    /// Visible window frame, without the invisible resize borders
+    pub frame: Rect,
+    /// Client area, the window without its title bar and borders
+    pub client: Rect,
+}
+
+impl WindowInfo {
+    /// Offset of `(x, y)` from the top left corner of the client area;
+    /// negative over the title bar or left border
+    pub fn client_offset(&self, x: i32, y: i32) -> (i32, i32) {
+        (x - self.client.left, y - self.client.top)
+    }
+
+    /// Offset of `(x, y)` from the top left corner of the window frame
+    pub fn frame_offset(&self, x: i32, y: i32) -> (i32, i32) {
+        (x - self.frame.left, y - self.frame.top)
+    }
+}
+
+/// Whether a window title contains `pattern`, ignoring case
+pub fn title_matches(title: &str, pattern: &str) -> bool {
+    title.to_lowercase().contains(&pattern.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_are_measured_from_the_frame_and_client_area() {
+        let window = WindowInfo {
+            frame: Rect { left: -1000, top: 50, right: -200, bottom: 650 },
+            client: Rect { left: -992, top: 81, right: -208, bottom: 642 },
+            ..Default::default()
+        };
+        assert_eq!(window.frame_offset(-900, 100), (100, 50));
+        assert_eq!(window.client_offset(-900, 100), (92, 19));
+        assert_eq!(window.client_offset(-1000, 50), (-8, -31));
+    }
+
+    #[test]
+    fn titles_match_case_insensitively() {
+        assert!(title_matches("Untitled - Notepad", "notepad"));
+        assert!(title_matches("Übersicht", "üBERSICHT"));
+        assert!(title_matches("Anything", ""));
+        assert!(!title_matches("Notepad", "Notepad++"));
+    }
+}
@@ -0,0 +1,443 @@
+// X11 backend for Linux desktops. There is no Linux front end with a tray
+// icon yet; these are the system calls it builds on, sharing parsing and the
+// display modes with Windows.
+#![allow(dead_code)] // Used by the Linux front end, which does not exist yet
+
+use std::ffi::{c_int, c_long, c_uint, c_ulong, CStr};
+use std::ptr::{null, null_mut};
+
+use x11::keysym::*;
+use x11::xlib;
+
+use crate::hotkey::{Action, Hotkey, MOD_ALT, MOD_CONTROL, MOD_SHIFT, MOD_WIN};
+use crate::layout::Rect;
+use crate::window::WindowInfo;
+
+/// Modifier masks of the `MOD_*` bits; Alt is Mod1 and the Windows key Super, Mod4
+const MODIFIER_MASKS: [(u32, c_uint); 4] = [
+    (MOD_CONTROL, xlib::ControlMask),
+    (MOD_ALT, xlib::Mod1Mask),
+    (MOD_SHIFT, xlib::ShiftMask),
+    (MOD_WIN, xlib::Mod4Mask),
+];
+
+/// CapsLock and NumLock, which is Mod2 on practically every keyboard map.
+/// A grab only matches the exact modifiers, so each hotkey is grabbed with
+/// every combination of them.
+const LOCK_MASKS: [c_uint; 4] = [0, xlib::LockMask, xlib::Mod2Mask, xlib::LockMask | xlib::Mod2Mask];
+
+/// Set by `record_error` when the X server rejects a request
+static mut REQUEST_FAILED: bool = false;
+
+/// Connection to the X server and the hotkeys grabbed through it
+pub struct Display {
+    raw: *mut xlib::Display,
+    grabs: Vec<Grab>,
+}
+
+/// A hotkey grabbed on the root window
+struct Grab {
+    action: Action,
+    keycode: c_int,
+    mask: c_uint,
+}
+
+impl Display {
+    /// Connects to the display named by `$DISPLAY`
+    pub fn open() -> Option<Display> {
+        let raw = unsafe { xlib::XOpenDisplay(null()) };
+        (!raw.is_null()).then_some(Display { raw, grabs: Vec::new() })
+    }
+
+    fn root(&self) -> xlib::Window {
+        unsafe { xlib::XDefaultRootWindow(self.raw) }
+    }
+
+    /// Runs `requests` and waits for their errors, which are recorded instead
+    /// of ending the process; false if any of them failed
+    unsafe fn checked(&self, requests: impl FnOnce()) -> bool {
+        let previous = xlib::XSetErrorHandler(Some(record_error));
+        REQUEST_FAILED = false;
+        requests();
+        xlib::XSync(self.raw, xlib::False);
+        xlib::XSetErrorHandler(previous);
+        !REQUEST_FAILED
+    }
+
+    /// Grabs `bindings` on the root window in place of the previous ones.
+    /// Returns one message per hotkey that has no key on the keyboard or is
+    /// already grabbed by another program; those are skipped.
+    pub fn grab_hotkeys(&mut self, bindings: &[(Action, Hotkey)]) -> Vec<String> {
+        self.ungrab_hotkeys();
+        let mut problems = Vec::new();
+        for &(action, hotkey) in bindings {
+            let keycode = keysym(hotkey.key).map_or(0, |keysym| unsafe { xlib::XKeysymToKeycode(self.raw, keysym) });
+            if keycode == 0 {
+                problems.push(format!("Failed to register {} for {}, the key is not on the keyboard", hotkey, action.name()));
+                continue;
+            }
+            let grab = Grab { action, keycode: keycode as c_int, mask: modifier_mask(hotkey.modifiers) };
+            let root = self.root();
+            let grabbed = unsafe {
+                self.checked(|| {
+                    for lock in LOCK_MASKS {
+                        xlib::XGrabKey(self.raw, grab.keycode, grab.mask | lock, root, xlib::False, xlib::GrabModeAsync, xlib::GrabModeAsync);
+                    }
+                })
+            };
+            if grabbed {
+                self.grabs.push(grab);
+            } else {
+                // Releases the combinations that were grabbed before the failing one
+                self.ungrab(&grab);
+                problems.push(format!("Failed to register {} for {}, it may be used by another program", hotkey, action.name()));
+            }
+        }
+        problems
+    }
+
+    pub fn ungrab_hotkeys(&mut self) {
+        for grab in std::mem::take(&mut self.grabs) {
+            self.ungrab(&grab);
+        }
+        unsafe { xlib::XFlush(self.raw) };
+    }
+
+    fn ungrab(&self, grab: &Grab) {
+        for lock in LOCK_MASKS {
+            unsafe { xlib::XUngrabKey(self.raw, grab.keycode, grab.mask | lock, self.root()) };
+        }
+    }
+
+    /// Actions of the hotkeys pressed since the last call. Does not block, so
+    /// the update timer can poll it.
+    pub fn pressed_hotkeys(&self) -> Vec<Action> {
+        let mut actions = Vec::new();
+        while unsafe { xlib::XPending(self.raw) } > 0 {
+            let mut event: xlib::XEvent = unsafe { std::mem::zeroed() };
+            unsafe { xlib::XNextEvent(self.raw, &mut event) };
+            if event.get_type() != xlib::KeyPress {
+                continue;
+            }
+            let key = unsafe { event.key };
+            // Ignores the lock keys and pressed mouse buttons
+            let mask = key.state & modifier_mask(MOD_CONTROL | MOD_ALT | MOD_SHIFT | MOD_WIN);
+            if let Some(grab) = self.grabs.iter().find(|grab| grab.keycode == key.keycode as c_int && grab.mask == mask) {
+                actions.push(grab.action);
+            }
+        }
+        actions
+    }
+
+    /// Top-level window at `(x, y)` in root coordinates, the topmost mapped
+    /// child of the root window containing the point. `None` over the desktop
+    /// or if the window closes while it is read.
+    pub fn window_at(&self, x: i32, y: i32) -> Option<WindowInfo> {
+        let mut info = None;
+        let complete = unsafe { self.checked(|| info = self.query_window_at(x, y)) };
+        info.filter(|_| complete)
+    }
+
+    unsafe fn query_window_at(&self, x: i32, y: i32) -> Option<WindowInfo> {
+        let (mut root, mut parent, mut children, mut count) = (0, 0, null_mut(), 0);
+        if xlib::XQueryTree(self.raw, self.root(), &mut root, &mut parent, &mut children, &mut count) == 0 {
+            return None;
+        }
+        let windows = if children.is_null() { Vec::new() } else { std::slice::from_raw_parts(children, count as usize).to_vec() };
+        if !children.is_null() {
+            xlib::XFree(children.cast());
+        }
+
+        // Children are listed bottom to top
+        let (top_level, bounds) = windows.iter().rev().find_map(|&window| {
+            let attributes = self.attributes(window)?;
+            let bounds = outer_rect(&attributes);
+            let visible = attributes.map_state == xlib::IsViewable && attributes.class != xlib::InputOnly;
+            (visible && bounds.contains(x, y)).then_some((window, bounds))
+        })?;
+
+        // With a reparenting window manager the top-level window is its frame
+        // and the application window is a descendant marked with WM_STATE
+        let client = self.client_window(top_level).unwrap_or(top_level);
+        let attributes = self.attributes(client)?;
+        let (mut left, mut top, mut child) = (0, 0, 0);
+        xlib::XTranslateCoordinates(self.raw, client, self.root(), 0, 0, &mut left, &mut top, &mut child);
+        let client_rect = Rect { left, top, right: left + attributes.width, bottom: top + attributes.height };
+
+        let extents = self.property::<c_long>(client, c"_NET_FRAME_EXTENTS", xlib::XA_CARDINAL);
+        Some(WindowInfo {
+            title: self.window_title(client),
+            class: self.window_class(client),
+            frame: extents.and_then(|extents| frame_around(client_rect, &extents)).unwrap_or(bounds),
+            client: client_rect,
+        })
+    }
+
+    /// Color of the screen pixel at `(x, y)` as `0x00BBGGRR`, `None` outside
+    /// the screen or on a visual without color masks
+    pub fn pixel_color(&self, x: i32, y: i32) -> Option<u32> {
+        let mut image = null_mut();
+        // A point outside the root window fails with BadMatch
+        unsafe { self.checked(|| image = xlib::XGetImage(self.raw, self.root(), x, y, 1, 1, xlib::XAllPlanes(), xlib::ZPixmap)) };
+        if image.is_null() {
+            return None;
+        }
+        unsafe {
+            let pixel = xlib::XGetPixel(image, 0, 0);
+            let color = to_colorref(pixel, [(*image).red_mask, (*image).green_mask, (*image).blue_mask]);
+            xlib::XDestroyImage(image);
+            color
+        }
+    }
+
+    unsafe fn attributes(&self, window: xlib::Window) -> Option<xlib::XWindowAttributes> {
+        let mut attributes: xlib::XWindowAttributes = std::mem::zeroed();
+        (xlib::XGetWindowAttributes(self.raw, window, &mut attributes) != 0).then_some(attributes)
+    }
+
+    /// First window below `window` with a `WM_STATE` property, depth first
+    unsafe fn client_window(&self, window: xlib::Window) -> Option<xlib::Window> {
+        if self.property::<u8>(window, c"WM_STATE", xlib::AnyPropertyType as xlib::Atom).is_some() {
+            return Some(window);
+        }
+        let (mut root, mut parent, mut children, mut count) = (0, 0, null_mut(), 0);
+        if xlib::XQueryTree(self.raw, window, &mut root, &mut parent, &mut children, &mut count) == 0 || children.is_null() {
+            return None;
+        }
+        let windows = std::slice::from_raw_parts(children, count as usize).to_vec();
+        xlib::XFree(children.cast());
+        windows.into_iter().find_map(|child| self.client_window(child))
+    }
+
+    /// Title from `_NET_WM_NAME`, which is UTF-8, or else `WM_NAME`
+    unsafe fn window_title(&self, window: xlib::Window) -> String {
+        let utf8 = xlib::XInternAtom(self.raw, c"UTF8_STRING".as_ptr(), xlib::False);
+        if let Some(title) = self.property::<u8>(window, c"_NET_WM_NAME", utf8) {
+            return String::from_utf8_lossy(&title).into_owned();
+        }
+        let mut name = null_mut();
+        if xlib::XFetchName(self.raw, window, &mut name) == 0 || name.is_null() {
+            return String::new();
+        }
+        let title = CStr::from_ptr(name).to_string_lossy().into_owned();
+        xlib::XFree(name.cast());
+        title
+    }
+
+    /// Class name from `WM_CLASS`
+    unsafe fn window_class(&self, window: xlib::Window) -> String {
+        let mut hint = xlib::XClassHint { res_name: null_mut(), res_class: null_mut() };
+        if xlib::XGetClassHint(self.raw, window, &mut hint) == 0 {
+            return String::new();
+        }
+        let class = if hint.res_class.is_null() { String::new() } else { CStr::from_ptr(hint.res_class).to_string_lossy().into_owned() };
+        for text in [hint.res_name, hint.res_class] {
+            if !text.is_null() {
+                xlib::XFree(text.cast());
+            }
+        }
+        class
+    }
+
+    /// Items of a window property, `None` if it is not set or has another
+    /// type. `T` is `u8` for 8-bit properties and `c_long` for 32-bit ones.
+    unsafe fn property<T: Copy>(&self, window: xlib::Window, name: &CStr, kind: xlib::Atom) -> Option<Vec<T>> {
+        let atom = xlib::XInternAtom(self.raw, name.as_ptr(), xlib::True);
+        if atom == 0 {
+            return None;
+        }
+        let (mut actual_kind, mut format, mut count, mut remaining, mut data) = (0, 0, 0, 0, null_mut());
+        let status = xlib::XGetWindowProperty(
+            self.raw,
+            window,
+            atom,
+            0,
+            4096,
+            xlib::False,
+            kind,
+            &mut actual_kind,
+            &mut format,
+            &mut count,
+            &mut remaining,
+            &mut data,
+        );
+        if status != xlib::Success as c_int || data.is_null() {
+            return None;
+        }
+        let matches = actual_kind != 0 && (kind == xlib::AnyPropertyType as xlib::Atom || actual_kind == kind);
+        let items = matches.then(|| std::slice::from_raw_parts(data as *const T, count as usize).to_vec());
+        xlib::XFree(data.cast());
+        items
+    }
+}
+
+impl Drop for Display {
+    /// Closing the connection also releases its grabs
+    fn drop(&mut self) {
+        unsafe { xlib::XCloseDisplay(self.raw) };
+    }
+}
+
+unsafe extern "C" fn record_error(_: *mut xlib::Display, _: *mut xlib::XErrorEvent) -> c_int {
+    REQUEST_FAILED = true;
+    0
+}
+
+/// Bounds of a child of the root window, including its border
+fn outer_rect(attributes: &xlib::XWindowAttributes) -> Rect {
+    let border = attributes.border_width;
+    Rect {
+        left: attributes.x,
+        top: attributes.y,
+        right: attributes.x + attributes.width + 2 * border,
+        bottom: attributes.y + attributes.height + 2 * border,
+    }
+}
+
+/// Frame around `client` from `_NET_FRAME_EXTENTS`: left, right, top and bottom
+fn frame_around(client: Rect, extents: &[c_long]) -> Option<Rect> {
+    let &[left, right, top, bottom] = extents else { return None };
+    Some(Rect {
+        left: client.left - left as i32,
+        top: client.top - top as i32,
+        right: client.right + right as i32,
+        bottom: client.bottom + bottom as i32,
+    })
+}
+
+/// `0x00BBGGRR` value of a pixel with the red, green and blue masks of its
+/// visual; each channel is scaled to 8 bits
+fn to_colorref(pixel: c_ulong, masks: [c_ulong; 3]) -> Option<u32> {
+    if masks.contains(&0) {
+        return None;
+    }
+    let channel = |mask: c_ulong| {
+        let max = mask >> mask.trailing_zeros();
+        let value = (pixel & mask) >> mask.trailing_zeros();
+        ((value * 255 + max / 2) / max) as u32
+    };
+    let [red, green, blue] = masks.map(channel);
+    Some(red | green << 8 | blue << 16)
+}
+
+/// X modifier mask of `MOD_*` bits
+fn modifier_mask(modifiers: u32) -> c_uint {
+    MODIFIER_MASKS.iter().filter(|(modifier, _)| modifiers & modifier != 0).fold(0, |mask, (_, x_mask)| mask | x_mask)
+}
+
+/// Keysym of a virtual-key code as stored in `Hotkey`
+fn keysym(key: u32) -> Option<xlib::KeySym> {
+    let keysym = match key {
+        // The keysym of the unshifted key, which is lowercase for letters
+        0x41..=0x5A => key + 0x20,
+        0x20 | 0x30..=0x39 => key,
+        0x70..=0x87 => XK_F1 + key - 0x70,
+        0x0D => XK_Return,
+        0x09 => XK_Tab,
+        0x08 => XK_BackSpace,
+        0x1B => XK_Escape,
+        0x2D => XK_Insert,
+        0x2E => XK_Delete,
+        0x24 => XK_Home,
+        0x23 => XK_End,
+        0x21 => XK_Prior,
+        0x22 => XK_Next,
+        0x25 => XK_Left,
+        0x26 => XK_Up,
+        0x27 => XK_Right,
+        0x28 => XK_Down,
+        0x2C => XK_Print,
+        0x13 => XK_Pause,
+        _ => return None,
+    };
+    Some(keysym as xlib::KeySym)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Hotkey {
+        Hotkey::parse(text).unwrap()
+    }
+
+    #[test]
+    fn frames_extend_the_client_area_by_the_extents() {
+        let client = Rect { left: 100, top: 130, right: 900, bottom: 730 };
+        let frame = frame_around(client, &[2, 2, 28, 2]);
+        assert_eq!(frame, Some(Rect { left: 98, top: 102, right: 902, bottom: 732 }));
+        assert_eq!(frame_around(client, &[0, 0, 0, 0]), Some(client));
+        assert_eq!(frame_around(client, &[2, 2, 28]), None);
+    }
+
+    #[test]
+    fn outer_bounds_include_the_border() {
+        let mut attributes: xlib::XWindowAttributes = unsafe { std::mem::zeroed() };
+        (attributes.x, attributes.y, attributes.width, attributes.height, attributes.border_width) = (-5, 10, 640, 480, 1);
+        assert_eq!(outer_rect(&attributes), Rect { left: -5, top: 10, right: 637, bottom: 492 });
+    }
+
+    #[test]
+    fn pixels_convert_to_colorref() {
+        let rgb888 = [0xFF0000, 0x00FF00, 0x0000FF];
+        assert_eq!(to_colorref(0xFF8000, rgb888), Some(0x0080FF));
+        assert_eq!(to_colorref(0x123456, rgb888), Some(0x563412));
+        // Bits outside the masks, such as alpha, are ignored
+        assert_eq!(to_colorref(0xFF123456, rgb888), Some(0x563412));
+    }
+
+    #[test]
+    fn narrow_and_wide_channels_scale_to_8_bits() {
+        let rgb565 = [0xF800, 0x07E0, 0x001F];
+        assert_eq!(to_colorref(0xFFFF, rgb565), Some(0xFFFFFF));
+        assert_eq!(to_colorref(0xF800, rgb565), Some(0x0000FF));
+        assert_eq!(to_colorref(0x0400, rgb565), Some(0x008200));
+        let rgb101010 = [0x3FF00000, 0x000FFC00, 0x000003FF];
+        assert_eq!(to_colorref(0x3FF80000, rgb101010), Some(0x0080FF));
+        assert_eq!(to_colorref(0, rgb101010), Some(0));
+    }
+
+    #[test]
+    fn visuals_without_masks_have_no_color() {
+        assert_eq!(to_colorref(0x2A, [0, 0, 0]), None);
+    }
+
+    #[test]
+    fn modifiers_map_to_x_masks() {
+        assert_eq!(modifier_mask(parse("Ctrl+Alt+C").modifiers), xlib::ControlMask | xlib::Mod1Mask);
+        assert_eq!(modifier_mask(parse("Win+Shift+7").modifiers), xlib::Mod4Mask | xlib::ShiftMask);
+        assert_eq!(modifier_mask(parse("F5").modifiers), 0);
+    }
+
+    #[test]
+    fn lock_masks_cover_every_combination() {
+        let all = modifier_mask(MOD_CONTROL | MOD_ALT | MOD_SHIFT | MOD_WIN);
+        assert!(LOCK_MASKS.iter().all(|lock| lock & all == 0));
+        assert_eq!(LOCK_MASKS.iter().fold(0, |mask, lock| mask | lock), xlib::LockMask | xlib::Mod2Mask);
+    }
+
+    #[test]
+    fn keys_map_to_keysyms() {
+        let keysym_of = |text: &str| keysym(parse(text).key).map(|keysym| keysym as c_uint);
+        assert_eq!(keysym_of("Ctrl+C"), Some(XK_c));
+        assert_eq!(keysym_of("Ctrl+7"), Some(XK_7));
+        assert_eq!(keysym_of("Ctrl+Space"), Some(XK_space));
+        assert_eq!(keysym_of("F1"), Some(XK_F1));
+        assert_eq!(keysym_of("F24"), Some(XK_F24));
+        assert_eq!(keysym_of("Ctrl+PgUp"), Some(XK_Prior));
+        assert_eq!(keysym_of("Ctrl+Enter"), Some(XK_Return));
+        assert_eq!(keysym_of("PrintScreen"), Some(XK_Print));
+    }
+
+    #[test]
+    fn every_named_key_has_a_keysym() {
+        let names = [
+            "Space", "Enter", "Tab", "Backspace", "Esc", "Insert", "Delete", "Home", "End", "PageUp", "PageDown", "Left", "Up",
+            "Right", "Down", "PrintScreen", "Pause",
+        ];
+        for name in names {
+            assert!(keysym(parse(&format!("Ctrl+{}", name)).key).is_some(), "{}", name);
+        }
+        assert_eq!(keysym(0xFF), None);
+    }
+}